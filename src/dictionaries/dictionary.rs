@@ -1,4 +1,4 @@
-use crate::{Identifier, TypeError};
+use crate::{Identifier, TypeError, TypeErrorKind};
 use std::collections::HashMap;
 
 #[derive(Clone)]
@@ -16,9 +16,16 @@ impl<T> Default for Dictionary<T> {
 
 impl<T> Dictionary<T> {
     pub(super) fn get(&self, id: &Identifier) -> crate::Result<&T> {
-        self.entries.get(id).ok_or_else(|| TypeError {
-            msg: format!("Variable `{}` not found.", id),
-        })
+        self.entries
+            .get(id)
+            .ok_or_else(|| TypeError::new(TypeErrorKind::UnboundVariable(id.clone())))
+    }
+    pub(super) fn insert(&mut self, id: Identifier, val: T) -> crate::Result<()> {
+        if self.entries.contains_key(&id) {
+            return Err(TypeError::new(TypeErrorKind::AlreadyDefined(id)));
+        }
+        self.entries.insert(id, val);
+        Ok(())
     }
     pub(super) fn names(&self) -> impl Iterator<Item = &Identifier> + Clone {
         self.entries.keys()
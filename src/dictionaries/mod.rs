@@ -1,29 +1,1113 @@
 use crate::dictionaries::dictionary::Dictionary;
 use crate::environment::Context;
-use crate::evaluation::evaluate;
+use crate::evaluation::try_evaluate;
 use crate::expression::Expression;
+use crate::inductive::{
+    check_arity, check_constructor, eliminator, eliminator_name, pi_types, DataDeclaration, Family,
+};
+use crate::meta::{zonk_goal, Goal, MetaContext};
+use crate::pattern::{compile, try_match, Function, FunctionDeclaration};
+use crate::record::{check_record, projection_name, projections, Record, RecordDeclaration};
+use crate::termination::check_termination;
+use crate::typing::{check_is_type, elaborate};
 use crate::value::*;
-use crate::Identifier;
+use crate::{Identifier, Level, TypeError, TypeErrorKind, Warning};
+
+use std::cell::{Ref, RefCell, RefMut};
 
 mod dictionary;
 
 /// A record of global definitions.
-pub struct Definitions(Dictionary<TypedValue>);
+#[derive(Default)]
+pub struct Definitions {
+    items: Dictionary<Global>,
+    families: Dictionary<Family>,
+    records: Dictionary<Record>,
+    functions: Dictionary<Function>,
+    metas: RefCell<MetaContext>,
+    type_in_type: bool,
+}
+
+/// A global definition or postulate, which may be polymorphic in universe levels.
+struct Global {
+    level_params: Vec<Identifier>,
+    /// The checked type, kept to be instantiated at particular levels.
+    type_expr: Expression,
+    body: Body,
+    typed: TypedValue,
+    /// Whether this is, or depends on, a function whose termination was not checked.
+    partial: bool,
+}
+
+/// Where the value of a [`Global`] comes from.
+enum Body {
+    /// A checked expression, kept to be instantiated at particular levels.
+    Defined(Expression),
+    Postulated,
+    /// A constant generated by a data, record or function declaration, which has no level parameters.
+    Generated,
+    /// The eliminator of the inductive family `family`.
+    Eliminator {
+        family: Identifier,
+    },
+}
 
 impl Definitions {
+    /// Creates a record with nothing defined.
+    pub fn new() -> Definitions {
+        Default::default()
+    }
+
+    /// Creates a record with nothing defined, checked under the rule that every universe is of its own type.
+    ///
+    /// This makes the type theory inconsistent, so is only suitable for experiments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::{Context, Definitions};
+    /// use alpha::expression::Expression;
+    /// use alpha::typing::check_type;
+    /// use alpha::value::Type;
+    /// use alpha::Level;
+    ///
+    /// let universe = Expression::Universe(Level::ZERO);
+    /// let stratified = Definitions::new();
+    /// assert!(check_type(&stratified, &Context::EMPTY, &universe, &Type::universe(1)).is_ok());
+    /// assert!(check_type(&stratified, &Context::EMPTY, &universe, &Type::universe(0)).is_err());
+    ///
+    /// let type_in_type = Definitions::type_in_type();
+    /// assert!(check_type(&type_in_type, &Context::EMPTY, &universe, &Type::universe(0)).is_ok());
+    /// ```
+    pub fn type_in_type() -> Definitions {
+        Definitions {
+            type_in_type: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether every universe is of its own type, as for [`type_in_type`](Definitions::type_in_type).
+    pub fn is_type_in_type(&self) -> bool {
+        self.type_in_type
+    }
+
+    /// Defines `name` to be `expr`, after checking that `expr` is of type `type_`.
+    ///
+    /// Both `expr` and `type_` may refer to previously-defined items.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::Definitions;
+    /// use alpha::expression::Expression;
+    /// use alpha::{Identifier, Level};
+    ///
+    /// let x = Identifier::new("x");
+    /// let id = Identifier::new("id");
+    /// let mut defs = Definitions::new();
+    ///
+    /// // defines `id` to be the polymorphic identity function
+    /// let expr = Expression::Lambda {
+    ///     param: x.clone(),
+    ///     param_type: None,
+    ///     ret_val: Box::new(Expression::Lambda {
+    ///         param: x.clone(),
+    ///         param_type: None,
+    ///         ret_val: Box::new(Expression::Variable(x.clone())),
+    ///         implicit: false,
+    ///     }),
+    ///     implicit: false,
+    /// };
+    /// let type_ = Expression::PiType {
+    ///     tparam: x.clone(),
+    ///     tparam_type: Box::new(Expression::Universe(Level::ZERO)),
+    ///     ret_type: Box::new(Expression::PiType {
+    ///         tparam: Identifier::new("y"),
+    ///         tparam_type: Box::new(Expression::Variable(x.clone())),
+    ///         ret_type: Box::new(Expression::Variable(x)),
+    ///         implicit: false,
+    ///     }),
+    ///     implicit: false,
+    /// };
+    /// assert!(defs.define(id.clone(), &expr, &type_).is_ok());
+    ///
+    /// // names cannot be redefined
+    /// assert!(defs.define(id, &expr, &type_).is_err());
+    /// ```
+    pub fn define(
+        &mut self,
+        name: Identifier,
+        expr: &Expression,
+        type_: &Expression,
+    ) -> crate::Result<()> {
+        self.define_polymorphic(name, &[], expr, type_)
+    }
+
+    /// Defines `name` to be `expr` of type `type_`, generically in the universe levels `level_params`.
+    ///
+    /// Each use of `name` must then give a level for each parameter, as in `name.{0, l+1}`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::{Context, Definitions};
+    /// use alpha::parse::parse;
+    /// use alpha::typing::synth_type;
+    /// use alpha::Identifier;
+    ///
+    /// let mut defs = Definitions::new();
+    /// let expr = parse(r"\A.\x.x").unwrap();
+    /// let type_ = parse("Pi(A : U.{l}) Pi(x : A) A").unwrap();
+    /// let l = Identifier::new("l");
+    /// assert!(defs.define_polymorphic(Identifier::new("id"), &[l], &expr, &type_).is_ok());
+    ///
+    /// // `id` can be used at any level, including for the universe it was first defined in
+    /// let use_site = parse("id.{0} (id.{1} U Nat) 3").unwrap();
+    /// assert!(synth_type(&defs, &Context::EMPTY, &use_site).is_ok());
+    ///
+    /// // but not without saying which
+    /// let use_site = parse("id Nat").unwrap();
+    /// assert!(synth_type(&defs, &Context::EMPTY, &use_site).is_err());
+    /// ```
+    pub fn define_polymorphic(
+        &mut self,
+        name: Identifier,
+        level_params: &[Identifier],
+        expr: &Expression,
+        type_expr: &Expression,
+    ) -> crate::Result<()> {
+        self.atomically(|defs| {
+            check_levels_bound(level_params, &[expr, type_expr])
+                .map_err(|e| definition_error(&name, e))?;
+            let (type_expr, type_) = defs.check_is_type(&name, type_expr)?;
+            let expr = elaborate(defs, &Context::EMPTY, expr, &type_)
+                .map_err(|e| definition_error(&name, e))?;
+            let val = try_evaluate(defs, &Environment::EMPTY, &expr)
+                .map_err(|e| definition_error(&name, e))?;
+            let partial = defs.depends_on_partial(&[&expr, &type_expr]);
+            let global = Global {
+                level_params: level_params.to_vec(),
+                type_expr,
+                body: Body::Defined(expr),
+                typed: TypedValue { type_, val },
+                partial,
+            };
+            defs.items.insert(name, global)
+        })
+    }
+
+    /// Assumes `name` to be of type `type_`, without giving it a value.
+    ///
+    /// A postulate is a global, so a local variable of the same name never captures it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::{Context, Definitions};
+    /// use alpha::parse::parse;
+    /// use alpha::read_back::read_back_type;
+    /// use alpha::typing::synth_type;
+    /// use alpha::Identifier;
+    ///
+    /// let mut defs = Definitions::new();
+    /// assert!(defs.postulate(Identifier::new("A"), &parse("U").unwrap()).is_ok());
+    /// assert!(defs.postulate(Identifier::new("a"), &parse("A").unwrap()).is_ok());
+    ///
+    /// let bad = parse("(λ(A : U). a) Empty").unwrap();
+    /// let empty = parse("Empty").unwrap();
+    /// assert!(defs.define(Identifier::new("bad"), &bad, &empty).is_err());
+    ///
+    /// let lambda = parse("λ(A : U). a").unwrap();
+    /// let type_ = synth_type(&defs, &Context::EMPTY, &lambda).unwrap();
+    /// let type_ = read_back_type(&defs, &Context::EMPTY, &type_);
+    /// assert_eq!(type_.to_string(), "\u{220F}(A1 : U)A");
+    /// ```
+    pub fn postulate(&mut self, name: Identifier, type_: &Expression) -> crate::Result<()> {
+        self.postulate_polymorphic(name, &[], type_)
+    }
+
+    /// Assumes `name` to be of type `type_`, without giving it a value,
+    /// generically in the universe levels `level_params`.
+    pub fn postulate_polymorphic(
+        &mut self,
+        name: Identifier,
+        level_params: &[Identifier],
+        type_expr: &Expression,
+    ) -> crate::Result<()> {
+        self.atomically(|defs| {
+            check_levels_bound(level_params, &[type_expr])
+                .map_err(|e| definition_error(&name, e))?;
+            let (type_expr, type_) = defs.check_is_type(&name, type_expr)?;
+            let val = postulate_value(&name, level_params.iter().cloned().map(Level::variable));
+            let partial = defs.depends_on_partial(&[&type_expr]);
+            let global = Global {
+                level_params: level_params.to_vec(),
+                type_expr,
+                body: Body::Postulated,
+                typed: TypedValue { type_, val },
+                partial,
+            };
+            defs.items.insert(name, global)
+        })
+    }
+
+    /// Declares an inductive family, after checking that its constructors are well-typed,
+    /// mention the family only strictly positively, and have fields no larger than the family.
+    ///
+    /// This defines the family, its constructors, and its eliminator, named as given by
+    /// [`eliminator_name`]. The eliminator takes the parameters, a motive into a universe
+    /// at the level it is instantiated at, a method for each constructor, the indices and
+    /// the target, and computes when the target is built from a constructor.
+    /// If the declaration fails to check, nothing is defined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::{Context, Definitions, Environment};
+    /// use alpha::evaluation::evaluate;
+    /// use alpha::inductive::{ConstructorDeclaration, DataDeclaration};
+    /// use alpha::parse::parse;
+    /// use alpha::read_back::read_back_typed;
+    /// use alpha::typing::synth_type;
+    /// use alpha::Identifier;
+    ///
+    /// let constructor = |name, type_| ConstructorDeclaration {
+    ///     name: Identifier::new(name),
+    ///     type_: parse(type_).unwrap(),
+    /// };
+    /// let vec = DataDeclaration {
+    ///     name: Identifier::new("Vec"),
+    ///     params: vec![(Identifier::new("A"), parse("U").unwrap())],
+    ///     arity: parse("Pi(n : Nat) U").unwrap(),
+    ///     constructors: vec![
+    ///         constructor("nil", "Vec A 0"),
+    ///         constructor("cons", "Pi(n : Nat) Pi(x : A) Pi(xs : Vec A n) Vec A (succ n)"),
+    ///     ],
+    /// };
+    /// let mut defs = Definitions::new();
+    /// assert!(defs.declare_data(&vec).is_ok());
+    ///
+    /// // sums a vector
+    /// let sum = parse(
+    ///     r"Vec_elim.{0} Nat (\n.\xs.Nat) 0 (\n.\x.\xs.\s.natrec (\k.Nat) x (\k.\t.succ t) s) 2
+    ///       (cons Nat 1 3 (cons Nat 0 4 (nil Nat)))",
+    /// )
+    /// .unwrap();
+    /// let type_ = synth_type(&defs, &Context::EMPTY, &sum).unwrap();
+    /// let val = evaluate(&defs, &Environment::EMPTY, &sum);
+    /// assert_eq!(read_back_typed(&defs, &Context::EMPTY, &val, &type_).to_string(), "7");
+    ///
+    /// // the family may not occur to the left of an arrow in a field
+    /// let bad = DataDeclaration {
+    ///     name: Identifier::new("Bad"),
+    ///     params: vec![],
+    ///     arity: parse("U").unwrap(),
+    ///     constructors: vec![constructor("bad", "Pi(f : Pi(b : Bad) Empty) Bad")],
+    /// };
+    /// assert!(defs.declare_data(&bad).is_err());
+    /// assert!(defs.lookup_type(&Identifier::new("Bad")).is_err());
+    /// ```
+    pub fn declare_data(&mut self, decl: &DataDeclaration) -> crate::Result<()> {
+        self.atomically(|defs| {
+            let names = decl.defined_names();
+            for (i, name) in names.iter().enumerate() {
+                if defs.items.get(name).is_ok() || names[..i].contains(name) {
+                    return Err(TypeError::new(TypeErrorKind::AlreadyDefined(name.clone())));
+                }
+            }
+            let exprs: Vec<&Expression> = decl.expressions().collect();
+            check_levels_bound(&[], &exprs).map_err(|e| definition_error(&decl.name, e))?;
+            let (indices, level) =
+                check_arity(decl).map_err(|e| definition_error(&decl.name, e))?;
+            let family_type = decl.family_type();
+            let (family_type, type_) = defs.check_is_type(&decl.name, &family_type)?;
+            let val = Value::Data {
+                name: decl.name.clone(),
+                args: Vec::new(),
+            };
+            defs.insert_generated(decl.name.clone(), family_type, type_, val);
+            let result = defs.declare_constructors(decl, &indices, &level);
+            if result.is_err() {
+                for name in &names {
+                    defs.items.entries.remove(name);
+                }
+            } else if defs.depends_on_partial(&exprs) {
+                defs.mark_partial(&names);
+            }
+            result
+        })
+    }
+
+    /// Declares the constructors and eliminator of a family already defined.
+    fn declare_constructors(
+        &mut self,
+        decl: &DataDeclaration,
+        indices: &[(Identifier, Expression)],
+        level: &Level,
+    ) -> crate::Result<()> {
+        let mut constructors = Vec::new();
+        for constructor in &decl.constructors {
+            constructors.push(
+                check_constructor(self, decl, constructor, level)
+                    .map_err(|e| definition_error(&constructor.name, e))?,
+            );
+        }
+        for constructor in &decl.constructors {
+            let type_expr = decl.constructor_type(constructor);
+            let (type_expr, type_) = self.check_is_type(&constructor.name, &type_expr)?;
+            let val = Value::Constructor {
+                name: constructor.name.clone(),
+                args: Vec::new(),
+            };
+            self.insert_generated(constructor.name.clone(), type_expr, type_, val);
+        }
+        let name = eliminator_name(&decl.name);
+        let (type_expr, level_param, family) = eliminator(self, decl, indices, &constructors)
+            .map_err(|e| definition_error(&name, e))?;
+        let (type_expr, type_) = self.check_is_type(&name, &type_expr)?;
+        let level = Level::variable(level_param.clone());
+        let global = Global {
+            level_params: vec![level_param],
+            type_expr,
+            body: Body::Eliminator {
+                family: decl.name.clone(),
+            },
+            typed: TypedValue {
+                type_,
+                val: Value::Eliminator {
+                    family: decl.name.clone(),
+                    levels: vec![level],
+                    args: Vec::new(),
+                },
+            },
+            partial: false,
+        };
+        self.items.insert(name, global)?;
+        self.families.insert(decl.name.clone(), family)
+    }
+
+    /// Declares a record type, after checking the types of its fields.
+    ///
+    /// This defines the record type, in the universe of its largest field,
+    /// and a projection function for each field, named as given by [`projection_name`].
+    /// Two elements of a record type are judgmentally equal when each of their fields are.
+    /// If the declaration fails to check, nothing is defined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::{Context, Definitions, Environment};
+    /// use alpha::evaluation::evaluate;
+    /// use alpha::parse::parse;
+    /// use alpha::read_back::read_back_typed;
+    /// use alpha::record::RecordDeclaration;
+    /// use alpha::typing::synth_type;
+    /// use alpha::Identifier;
+    ///
+    /// let point = RecordDeclaration {
+    ///     name: Identifier::new("Point"),
+    ///     params: vec![(Identifier::new("A"), parse("U").unwrap())],
+    ///     fields: vec![
+    ///         (Identifier::new("x"), parse("A").unwrap()),
+    ///         (Identifier::new("y"), parse("A").unwrap()),
+    ///     ],
+    /// };
+    /// let mut defs = Definitions::new();
+    /// assert!(defs.declare_record(&point).is_ok());
+    ///
+    /// // projecting from a record computes
+    /// let x = parse("Point_x Nat {3, 4}").unwrap();
+    /// let type_ = synth_type(&defs, &Context::EMPTY, &x).unwrap();
+    /// let val = evaluate(&defs, &Environment::EMPTY, &x);
+    /// assert_eq!(read_back_typed(&defs, &Context::EMPTY, &val, &type_).to_string(), "3");
+    ///
+    /// // a point is equal to the point built from its projections
+    /// let eta = parse(
+    ///     r"(\p.refl p : Pi(p : Point Nat) Id (Point Nat) p {Point_x Nat p, Point_y Nat p})",
+    /// );
+    /// assert!(synth_type(&defs, &Context::EMPTY, &eta.unwrap()).is_ok());
+    /// ```
+    pub fn declare_record(&mut self, decl: &RecordDeclaration) -> crate::Result<()> {
+        self.atomically(|defs| {
+            let names = decl.defined_names();
+            for (i, name) in names.iter().enumerate() {
+                if defs.items.get(name).is_ok() || names[..i].contains(name) {
+                    return Err(TypeError::new(TypeErrorKind::AlreadyDefined(name.clone())));
+                }
+            }
+            let exprs: Vec<&Expression> = decl.expressions().collect();
+            check_levels_bound(&[], &exprs).map_err(|e| definition_error(&decl.name, e))?;
+            let level = check_record(defs, decl).map_err(|e| definition_error(&decl.name, e))?;
+            let record_type = pi_types(&decl.params, Expression::Universe(level));
+            let (record_type, type_) = defs.check_is_type(&decl.name, &record_type)?;
+            let val = Value::RecordType {
+                name: decl.name.clone(),
+                args: Vec::new(),
+            };
+            defs.insert_generated(decl.name.clone(), record_type, type_, val);
+            let result = defs.declare_projections(decl);
+            if result.is_err() {
+                for name in &names {
+                    defs.items.entries.remove(name);
+                }
+                defs.records.entries.remove(&decl.name);
+            } else if defs.depends_on_partial(&exprs) {
+                defs.mark_partial(&names);
+            }
+            result
+        })
+    }
+
+    /// Declares the projection functions of a record type already defined.
+    fn declare_projections(&mut self, decl: &RecordDeclaration) -> crate::Result<()> {
+        let (types, record) =
+            projections(self, decl).map_err(|e| definition_error(&decl.name, e))?;
+        // the types of later projections project the earlier fields
+        self.records.entries.insert(decl.name.clone(), record);
+        for ((field, _), type_expr) in decl.fields.iter().zip(types) {
+            let name = projection_name(&decl.name, field);
+            let (type_expr, type_) = self.check_is_type(&name, &type_expr)?;
+            let val = Value::Projection {
+                record: decl.name.clone(),
+                field: field.clone(),
+                args: Vec::new(),
+            };
+            self.insert_generated(name, type_expr, type_, val);
+        }
+        Ok(())
+    }
+
+    /// Defines a function by pattern matching, after checking that each clause is well-typed
+    /// and that together they cover every argument.
+    ///
+    /// Unless the function is `partial`, each call it makes to itself must be on an argument
+    /// structurally smaller than the same parameter, as for [`is_partial`](Definitions::is_partial).
+    /// The function computes once applied to as many arguments as its clauses have patterns,
+    /// by the first clause that matches them, and is stuck if deciding needs to match a neutral value.
+    /// Returns a warning for each clause that no argument reaches.
+    /// If the declaration fails to check, nothing is defined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::{Context, Definitions, Environment};
+    /// use alpha::evaluation::evaluate;
+    /// use alpha::parse::parse;
+    /// use alpha::pattern::{Clause, FunctionDeclaration, Pattern};
+    /// use alpha::read_back::read_back_typed;
+    /// use alpha::typing::synth_type;
+    /// use alpha::Identifier;
+    ///
+    /// let var = |name| Pattern::Variable(Identifier::new(name));
+    /// let clause = |patterns, body| Clause {
+    ///     patterns,
+    ///     body: Some(parse(body).unwrap()),
+    /// };
+    /// let add = FunctionDeclaration {
+    ///     name: Identifier::new("add"),
+    ///     type_: parse("Pi(m : Nat) Pi(n : Nat) Nat").unwrap(),
+    ///     clauses: vec![
+    ///         clause(vec![Pattern::Zero, var("n")], "n"),
+    ///         clause(vec![Pattern::Succ(Box::new(var("m"))), var("n")], "succ (add m n)"),
+    ///     ],
+    ///     partial: false,
+    /// };
+    /// let mut defs = Definitions::new();
+    /// assert!(defs.define_function(&add).unwrap().is_empty());
+    ///
+    /// let sum = parse("add 2 3").unwrap();
+    /// let type_ = synth_type(&defs, &Context::EMPTY, &sum).unwrap();
+    /// let val = evaluate(&defs, &Environment::EMPTY, &sum);
+    /// assert_eq!(read_back_typed(&defs, &Context::EMPTY, &val, &type_).to_string(), "5");
+    ///
+    /// // every argument must be covered
+    /// let pred = FunctionDeclaration {
+    ///     name: Identifier::new("pred"),
+    ///     type_: parse("Pi(n : Nat) Nat").unwrap(),
+    ///     clauses: vec![clause(vec![Pattern::Succ(Box::new(var("n")))], "n")],
+    ///     partial: false,
+    /// };
+    /// let error = defs.define_function(&pred).unwrap_err();
+    /// assert!(error.to_string().contains("do not cover `pred zero`"));
+    /// assert!(defs.lookup_type(&Identifier::new("pred")).is_err());
+    ///
+    /// // a clause after one matching everything is unreachable
+    /// let id = FunctionDeclaration {
+    ///     name: Identifier::new("id"),
+    ///     type_: parse("Pi(b : Bool) Bool").unwrap(),
+    ///     clauses: vec![
+    ///         clause(vec![var("b")], "b"),
+    ///         clause(vec![Pattern::True], "false"),
+    ///     ],
+    ///     partial: false,
+    /// };
+    /// assert_eq!(defs.define_function(&id).unwrap().len(), 1);
+    ///
+    /// // a call stuck on an implicit argument within a pattern computes once it is inferred
+    /// let succ = |pattern| Pattern::Succ(Box::new(pattern));
+    /// let small = FunctionDeclaration {
+    ///     name: Identifier::new("small"),
+    ///     type_: parse("Nat → Bool").unwrap(),
+    ///     clauses: vec![
+    ///         clause(vec![Pattern::Zero], "true"),
+    ///         clause(vec![succ(Pattern::Zero)], "true"),
+    ///         clause(vec![succ(succ(var("n")))], "false"),
+    ///     ],
+    ///     partial: false,
+    /// };
+    /// assert!(defs.define_function(&small).unwrap().is_empty());
+    /// let type_ = parse("{n : Nat} → Id Nat n 1 → Id Bool (small (succ n)) false → Nat").unwrap();
+    /// assert!(defs.postulate(Identifier::new("h"), &type_).is_ok());
+    /// let call = parse("h (refl 1) (refl false)").unwrap();
+    /// assert!(synth_type(&defs, &Context::EMPTY, &call).is_ok());
+    ///
+    /// // matching on `refl` solves for a variable on one side of the equation
+    /// let sym = FunctionDeclaration {
+    ///     name: Identifier::new("sym"),
+    ///     type_: parse("(x y : Nat) → Id Nat x y → Id Nat y x").unwrap(),
+    ///     clauses: vec![clause(vec![var("x"), var("y"), Pattern::Refl], "refl x")],
+    ///     partial: false,
+    /// };
+    /// assert!(defs.define_function(&sym).unwrap().is_empty());
+    /// // and rules out an equation between different constructors
+    /// let absurd = FunctionDeclaration {
+    ///     name: Identifier::new("zero_not_one"),
+    ///     type_: parse("Id Nat 0 1 → Empty").unwrap(),
+    ///     clauses: vec![Clause {
+    ///         patterns: vec![Pattern::Absurd],
+    ///         body: None,
+    ///     }],
+    ///     partial: false,
+    /// };
+    /// assert!(defs.define_function(&absurd).is_ok());
+    ///
+    /// // pairs are matched by their components
+    /// let pair = |fst, snd| Pattern::Pair(Box::new(fst), Box::new(snd));
+    /// let swap = FunctionDeclaration {
+    ///     name: Identifier::new("swap"),
+    ///     type_: parse("(Σ(x : Nat) Bool) → Σ(b : Bool) Nat").unwrap(),
+    ///     clauses: vec![clause(vec![pair(var("n"), var("b"))], "(b, n)")],
+    ///     partial: false,
+    /// };
+    /// assert!(defs.define_function(&swap).unwrap().is_empty());
+    /// let swapped = parse("swap (3, true)").unwrap();
+    /// let type_ = synth_type(&defs, &Context::EMPTY, &swapped).unwrap();
+    /// let val = evaluate(&defs, &Environment::EMPTY, &swapped);
+    /// let swapped = read_back_typed(&defs, &Context::EMPTY, &val, &type_);
+    /// assert_eq!(swapped.to_string(), "(true, 3)");
+    /// // whether or not they are known to be pairs
+    /// let eta = parse(
+    ///     r"\(p : Σ(x : Nat) Bool). (refl (swap p) : Id (Σ(b : Bool) Nat) (swap p) (snd p, fst p))",
+    /// );
+    /// assert!(synth_type(&defs, &Context::EMPTY, &eta.unwrap()).is_ok());
+    /// ```
+    pub fn define_function(&mut self, decl: &FunctionDeclaration) -> crate::Result<Vec<Warning>> {
+        self.define_mutual(std::slice::from_ref(decl))
+    }
+
+    /// Defines a block of functions by pattern matching, which may call each other.
+    ///
+    /// The types of the functions are checked first, in order, so each may mention
+    /// the functions before it. The clauses of each function are then checked with
+    /// every function of the block in scope, though none computes while they are checked.
+    /// Each function is checked as for [`define_function`](Definitions::define_function),
+    /// where a chain of calls through other functions of the block must also make an
+    /// argument structurally smaller. A function with no patterns is a constant.
+    /// If any function fails to check, the error names it, and nothing is defined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::{Context, Definitions, Environment};
+    /// use alpha::evaluation::evaluate;
+    /// use alpha::parse::parse;
+    /// use alpha::pattern::{Clause, FunctionDeclaration, Pattern};
+    /// use alpha::read_back::read_back_typed;
+    /// use alpha::typing::synth_type;
+    /// use alpha::{Identifier, TypeErrorKind};
+    ///
+    /// let clause = |pattern, body| Clause {
+    ///     patterns: vec![pattern],
+    ///     body: Some(parse(body).unwrap()),
+    /// };
+    /// let n = || Pattern::Succ(Box::new(Pattern::Variable(Identifier::new("n"))));
+    /// let function = |name, clauses| FunctionDeclaration {
+    ///     name: Identifier::new(name),
+    ///     type_: parse("Pi(n : Nat) Bool").unwrap(),
+    ///     clauses,
+    ///     partial: false,
+    /// };
+    /// let even = function("even", vec![clause(Pattern::Zero, "true"), clause(n(), "odd n")]);
+    /// let odd = function("odd", vec![clause(Pattern::Zero, "false"), clause(n(), "even n")]);
+    /// let mut defs = Definitions::new();
+    /// assert!(defs.define_mutual(&[even, odd]).is_ok());
+    ///
+    /// let expr = parse("odd 7").unwrap();
+    /// let type_ = synth_type(&defs, &Context::EMPTY, &expr).unwrap();
+    /// let val = evaluate(&defs, &Environment::EMPTY, &expr);
+    /// assert_eq!(read_back_typed(&defs, &Context::EMPTY, &val, &type_).to_string(), "true");
+    ///
+    /// // a block in which one function is ill-typed defines nothing
+    /// let good = function("good", vec![clause(Pattern::Zero, "true"), clause(n(), "bad n")]);
+    /// let bad = function("bad", vec![clause(Pattern::Zero, "0"), clause(n(), "good n")]);
+    /// let error = defs.define_mutual(&[good, bad]).unwrap_err();
+    /// match *error.kind {
+    ///     TypeErrorKind::InDefinition { name, .. } => assert_eq!(name, Identifier::new("bad")),
+    ///     _ => panic!("expected an error in a definition"),
+    /// }
+    /// assert!(defs.lookup_type(&Identifier::new("good")).is_err());
+    /// ```
+    pub fn define_mutual(&mut self, block: &[FunctionDeclaration]) -> crate::Result<Vec<Warning>> {
+        self.atomically(|defs| {
+            for (i, decl) in block.iter().enumerate() {
+                if defs.items.get(&decl.name).is_ok()
+                    || block[..i].iter().any(|other| other.name == decl.name)
+                {
+                    return Err(TypeError::new(TypeErrorKind::AlreadyDefined(
+                        decl.name.clone(),
+                    )));
+                }
+            }
+            let mut warnings = Vec::new();
+            let result = defs.check_mutual(block, &mut warnings);
+            let remove = |defs: &mut Definitions| {
+                for decl in block {
+                    defs.items.entries.remove(&decl.name);
+                    defs.functions.entries.remove(&decl.name);
+                }
+            };
+            // the clauses are checked with each function opaque
+            remove(defs);
+            let compiled = result?;
+            let mut types = Vec::new();
+            for (decl, (function, type_expr, type_)) in block.iter().zip(compiled) {
+                defs.functions.entries.insert(decl.name.clone(), function);
+                types.push((type_expr, type_));
+            }
+            let mut values = Vec::new();
+            for decl in block {
+                // a clause with a hole may have been checked in several cases,
+                // so the function cannot compute
+                let val = if decl
+                    .clauses
+                    .iter()
+                    .any(|clause| clause.body.as_ref().is_some_and(Expression::has_hole))
+                {
+                    postulate_value(&decl.name, std::iter::empty())
+                } else if defs.functions.entries[&decl.name].arity == 0 {
+                    match try_match(defs, &decl.name, &[]) {
+                        Ok(val) => val,
+                        Err(e) => {
+                            remove(defs);
+                            return Err(definition_error(&decl.name, e));
+                        }
+                    }
+                } else {
+                    Value::Function {
+                        name: decl.name.clone(),
+                        args: Vec::new(),
+                    }
+                };
+                values.push(val);
+            }
+            for ((decl, (type_expr, type_)), val) in block.iter().zip(types).zip(values) {
+                defs.insert_generated(decl.name.clone(), type_expr, type_, val);
+            }
+            // a function is partial if it calls one which is, perhaps through the block
+            let mut partial: Vec<Identifier> = Vec::new();
+            loop {
+                let before = partial.len();
+                for decl in block {
+                    if !partial.contains(&decl.name)
+                        && (decl.partial
+                            || defs.depends_on_partial(&decl.expressions())
+                            || decl
+                                .expressions()
+                                .iter()
+                                .any(|expr| partial.iter().any(|name| expr.refers_to_global(name))))
+                    {
+                        partial.push(decl.name.clone());
+                    }
+                }
+                if partial.len() == before {
+                    break;
+                }
+            }
+            defs.mark_partial(&partial);
+            Ok(warnings)
+        })
+    }
+
+    /// Checks a block of functions whose names are known to be unused,
+    /// leaving each postulated, and giving each compiled with its type, elaborated and evaluated.
+    fn check_mutual(
+        &mut self,
+        block: &[FunctionDeclaration],
+        warnings: &mut Vec<Warning>,
+    ) -> crate::Result<Vec<(Function, Expression, Type)>> {
+        let mut types = Vec::new();
+        for decl in block {
+            check_levels_bound(&[], &decl.expressions())
+                .map_err(|e| definition_error(&decl.name, e))?;
+            let (type_expr, type_) = self.check_is_type(&decl.name, &decl.type_)?;
+            let global = Global {
+                level_params: Vec::new(),
+                type_expr: type_expr.clone(),
+                body: Body::Postulated,
+                typed: TypedValue {
+                    type_: type_.clone(),
+                    val: postulate_value(&decl.name, std::iter::empty()),
+                },
+                partial: decl.partial,
+            };
+            self.items.entries.insert(decl.name.clone(), global);
+            types.push((type_expr, type_));
+        }
+        let mut functions = Vec::new();
+        let mut elaborated = Vec::new();
+        for (decl, (type_expr, type_)) in block.iter().zip(types) {
+            let decl = FunctionDeclaration {
+                type_: type_expr.clone(),
+                ..decl.clone()
+            };
+            let (function, decl, found) =
+                compile(self, &decl).map_err(|e| definition_error(&decl.name, e))?;
+            functions.push((function, type_expr, type_));
+            elaborated.push(decl);
+            warnings.extend(found);
+        }
+        // implicit arguments are filled in, so that calls line up with the patterns
+        let group: Vec<&FunctionDeclaration> =
+            elaborated.iter().filter(|decl| !decl.partial).collect();
+        for (member, decl) in group.iter().enumerate() {
+            check_termination(&group, member).map_err(|e| definition_error(&decl.name, e))?;
+        }
+        Ok(functions)
+    }
+
+    /// Runs `define`, forgetting the goals it left open and the constraints it postponed
+    /// if it fails.
+    fn atomically<T>(
+        &mut self,
+        define: impl FnOnce(&mut Definitions) -> crate::Result<T>,
+    ) -> crate::Result<T> {
+        let mark = self.metas().mark();
+        let result = define(self);
+        if result.is_err() {
+            self.metas_mut().truncate(mark);
+        }
+        result
+    }
+
+    /// Whether `name` is defined in terms of a function whose termination was not checked,
+    /// such as one declared `partial`, so that evaluating it may not terminate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::Definitions;
+    /// use alpha::parse::parse;
+    /// use alpha::pattern::{Clause, FunctionDeclaration, Pattern};
+    /// use alpha::Identifier;
+    ///
+    /// let n = Identifier::new("n");
+    /// let mut loop_ = FunctionDeclaration {
+    ///     name: Identifier::new("loop"),
+    ///     type_: parse("Pi(n : Nat) Nat").unwrap(),
+    ///     clauses: vec![Clause {
+    ///         patterns: vec![Pattern::Variable(n)],
+    ///         body: Some(parse("loop (succ n)").unwrap()),
+    ///     }],
+    ///     partial: false,
+    /// };
+    /// let mut defs = Definitions::new();
+    ///
+    /// // the recursive call is on a larger argument
+    /// let error = defs.define_function(&loop_).unwrap_err();
+    /// assert_eq!(error.span.unwrap().to_string(), "1:1-1:14");
+    ///
+    /// // unless the check is skipped, which taints what uses the function
+    /// loop_.partial = true;
+    /// assert!(defs.define_function(&loop_).is_ok());
+    /// let use_site = parse(r"\x.loop x").unwrap();
+    /// let type_ = parse("Pi(x : Nat) Nat").unwrap();
+    /// assert!(defs.define(Identifier::new("f"), &use_site, &type_).is_ok());
+    /// assert!(defs.is_partial(&Identifier::new("loop")));
+    /// assert!(defs.is_partial(&Identifier::new("f")));
+    /// ```
+    pub fn is_partial(&self, name: &Identifier) -> bool {
+        self.items.get(name).is_ok_and(|global| global.partial)
+    }
+
+    /// Whether any of `exprs` refers to a partial definition.
+    fn depends_on_partial(&self, exprs: &[&Expression]) -> bool {
+        exprs.iter().any(|expr| {
+            expr.identifiers()
+                .iter()
+                .any(|id| self.is_partial(id) && expr.refers_to_global(id))
+        })
+    }
+
+    /// Marks each of `names` as partial.
+    fn mark_partial(&mut self, names: &[Identifier]) {
+        for name in names {
+            if let Some(global) = self.items.entries.get_mut(name) {
+                global.partial = true;
+            }
+        }
+    }
+
+    /// Defines a constant generated by a declaration, whose name is known to be unused.
+    fn insert_generated(
+        &mut self,
+        name: Identifier,
+        type_expr: Expression,
+        type_: Type,
+        val: Value,
+    ) {
+        let global = Global {
+            level_params: Vec::new(),
+            type_expr,
+            body: Body::Generated,
+            typed: TypedValue { type_, val },
+            partial: false,
+        };
+        self.items.entries.insert(name, global);
+    }
+
+    /// Finds what evaluation needs to know about the declared record type `name`.
+    pub(crate) fn record(&self, name: &Identifier) -> crate::Result<&Record> {
+        self.records.get(name)
+    }
+
+    /// The goals left open by holes in what has been checked, in the order they were checked.
+    ///
+    /// A definition which fails to check leaves no goals.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::environment::Definitions;
+    /// use alpha::parse::parse;
+    /// use alpha::pattern::{Clause, FunctionDeclaration, Pattern};
+    /// use alpha::Identifier;
+    ///
+    /// let mut defs = Definitions::new();
+    /// let expr = parse(r"\A.\x.?").unwrap();
+    /// let type_ = parse("Pi(A : U) Pi(x : A) A").unwrap();
+    /// assert!(defs.define(Identifier::new("f"), &expr, &type_).is_ok());
+    ///
+    /// let goals = defs.goals();
+    /// assert_eq!(goals.len(), 1);
+    /// assert_eq!(goals[0].type_.to_string(), "A");
+    /// let context: Vec<String> = goals[0]
+    ///     .context
+    ///     .iter()
+    ///     .map(|hypothesis| format!("{} : {}", hypothesis.name, hypothesis.type_))
+    ///     .collect();
+    /// assert_eq!(context, ["A : U", "x : A"]);
+    ///
+    /// // the hole checks, but the definition does not
+    /// let expr = parse("(? : Nat) true").unwrap();
+    /// assert!(defs.define(Identifier::new("g"), &expr, &type_).is_err());
+    /// assert_eq!(defs.goals().len(), 1);
+    ///
+    /// // a hole in a clause is in terms of the variables of its patterns
+    /// let clause = |patterns, body: &str| Clause {
+    ///     patterns,
+    ///     body: Some(parse(body).unwrap()),
+    /// };
+    /// let same = FunctionDeclaration {
+    ///     name: Identifier::new("same"),
+    ///     type_: parse("(n : Nat) → Id Nat n n").unwrap(),
+    ///     clauses: vec![
+    ///         clause(vec![Pattern::Zero], "refl 0"),
+    ///         clause(vec![Pattern::Succ(Box::new(Pattern::Variable(Identifier::new("m"))))], "?"),
+    ///     ],
+    ///     partial: false,
+    /// };
+    /// assert!(defs.define_function(&same).is_ok());
+    /// let goals = defs.goals();
+    /// assert_eq!(goals[1].type_.to_string(), "Id(Nat)(succ(m))(succ(m))");
+    /// let context: Vec<String> = goals[1]
+    ///     .context
+    ///     .iter()
+    ///     .map(|hypothesis| format!("{} : {}", hypothesis.name, hypothesis.type_))
+    ///     .collect();
+    /// assert_eq!(context, ["m : Nat"]);
+    ///
+    /// // as is one in a lambda whose parameter type is given
+    /// let expr = parse(r"\(n : Nat). ?").unwrap();
+    /// assert!(defs.define(Identifier::new("h"), &expr, &parse("Nat → Nat").unwrap()).is_ok());
+    /// assert_eq!(defs.goals()[2].type_.to_string(), "Nat");
+    ///
+    /// // and one which an equation waits on is left to be filled
+    /// let expr = parse(r"\n. refl ?").unwrap();
+    /// assert!(defs.define(Identifier::new("k"), &expr, &same.type_).is_ok());
+    /// assert_eq!(defs.goals()[3].type_.to_string(), "Nat");
+    /// ```
+    pub fn goals(&self) -> Vec<Goal> {
+        let goals: Vec<Goal> = self.metas().goals().cloned().collect();
+        goals.iter().map(|goal| zonk_goal(self, goal)).collect()
+    }
+
+    /// The metavariables made while checking, and the constraints on them postponed.
+    pub fn metas(&self) -> Ref<'_, MetaContext> {
+        self.metas.borrow()
+    }
+
+    pub(crate) fn metas_mut(&self) -> RefMut<'_, MetaContext> {
+        self.metas.borrow_mut()
+    }
+
+    /// Finds the case tree of the function `name` defined by pattern matching.
+    pub(crate) fn function(&self, name: &Identifier) -> crate::Result<&Function> {
+        self.functions.get(name)
+    }
+
+    /// Finds what evaluation needs to know about the declared inductive family `name`.
+    pub(crate) fn family(&self, name: &Identifier) -> crate::Result<&Family> {
+        self.families.get(name)
+    }
+
+    /// Checks that the closed `type_` of `name` is a type, and elaborates and evaluates it.
+    fn check_is_type(
+        &self,
+        name: &Identifier,
+        type_: &Expression,
+    ) -> crate::Result<(Expression, Type)> {
+        let (type_, _) =
+            check_is_type(self, &Context::EMPTY, type_).map_err(|e| definition_error(name, e))?;
+        let type_val = try_evaluate(self, &Environment::EMPTY, &type_)
+            .map(Type::create_type_from_value)
+            .map_err(|e| definition_error(name, e))?;
+        Ok((type_, type_val))
+    }
+
     /// An iterator over defined names.
     pub fn names(&self) -> impl Iterator<Item = &Identifier> + Clone {
-        self.0.names()
+        self.items.names()
     }
 
     /// Determines the [`Type`] of a globally-defined item.
+    ///
+    /// For an item which is polymorphic in universe levels,
+    /// this is in terms of its level parameters.
     pub fn lookup_type(&self, id: &Identifier) -> crate::Result<&Type> {
-        self.0.get(id).map(|tv| &tv.type_)
+        self.items.get(id).map(|global| &global.typed.type_)
     }
 
     /// Determines the [`Value`] of a globally-defined item.
+    ///
+    /// For an item which is polymorphic in universe levels,
+    /// this is in terms of its level parameters.
     pub fn lookup_value(&self, id: &Identifier) -> crate::Result<&Value> {
-        self.0.get(id).map(|tv| &tv.val)
+        self.items.get(id).map(|global| &global.typed.val)
+    }
+
+    /// Determines the universe level parameters of a globally-defined item.
+    pub fn lookup_level_params(&self, id: &Identifier) -> crate::Result<&[Identifier]> {
+        self.items
+            .get(id)
+            .map(|global| global.level_params.as_slice())
+    }
+
+    /// Determines the [`Type`] of a globally-defined item instantiated at `levels`.
+    pub(crate) fn instantiate_type(
+        &self,
+        id: &Identifier,
+        levels: &[Level],
+    ) -> crate::Result<Type> {
+        let global = self.instance(id, levels)?;
+        if levels.is_empty() {
+            return Ok(global.typed.type_.clone());
+        }
+        let type_ = global
+            .type_expr
+            .substitute_levels(&global.level_params, levels)?;
+        try_evaluate(self, &Environment::EMPTY, &type_).map(Type::create_type_from_value)
+    }
+
+    /// Determines the [`Value`] of a globally-defined item instantiated at `levels`.
+    pub(crate) fn instantiate_value(
+        &self,
+        id: &Identifier,
+        levels: &[Level],
+    ) -> crate::Result<Value> {
+        let global = self.instance(id, levels)?;
+        if levels.is_empty() {
+            return Ok(global.typed.val.clone());
+        }
+        match &global.body {
+            Body::Defined(expr) => {
+                let expr = expr.substitute_levels(&global.level_params, levels)?;
+                try_evaluate(self, &Environment::EMPTY, &expr)
+            }
+            Body::Postulated => Ok(postulate_value(id, levels.iter().cloned())),
+            Body::Generated => Ok(global.typed.val.clone()),
+            Body::Eliminator { family } => Ok(Value::Eliminator {
+                family: family.clone(),
+                levels: levels.to_vec(),
+                args: Vec::new(),
+            }),
+        }
+    }
+
+    /// Finds a globally-defined item, checking that it has a parameter for each of `levels`.
+    fn instance(&self, id: &Identifier, levels: &[Level]) -> crate::Result<&Global> {
+        let global = self.items.get(id)?;
+        check_level_count(id, global.level_params.len(), levels)?;
+        Ok(global)
+    }
+}
+
+/// Checks that `name` is instantiated at one level for each of its `expected` parameters.
+pub(crate) fn check_level_count(
+    name: &Identifier,
+    expected: usize,
+    levels: &[Level],
+) -> crate::Result<()> {
+    if levels.len() == expected {
+        Ok(())
+    } else {
+        Err(TypeError::new(TypeErrorKind::WrongLevelCount {
+            name: name.clone(),
+            expected,
+            found: levels.len(),
+        }))
+    }
+}
+
+/// Checks that every universe level variable in `exprs` is one of `level_params`.
+fn check_levels_bound(level_params: &[Identifier], exprs: &[&Expression]) -> crate::Result<()> {
+    match exprs
+        .iter()
+        .flat_map(|expr| expr.level_variables())
+        .find(|id| !level_params.contains(id))
+    {
+        Some(id) => Err(TypeError::new(TypeErrorKind::UnboundLevel(id))),
+        None => Ok(()),
+    }
+}
+
+/// The value of a postulate `name` at `levels`.
+fn postulate_value(name: &Identifier, levels: impl Iterator<Item = Level>) -> Value {
+    // not a variable, which a binder of the same name would capture
+    Value::Neutral {
+        neu: Neutral::Instance {
+            name: name.clone(),
+            levels: levels.collect(),
+        },
+    }
+}
+
+fn definition_error(name: &Identifier, error: TypeError) -> TypeError {
+    TypeError {
+        kind: Box::new(TypeErrorKind::InDefinition {
+            name: name.clone(),
+            error: error.kind,
+        }),
+        ..error
     }
 }
 
@@ -41,8 +1125,9 @@ impl FlatEnvironment {
 
 enum EnvironmentInner<'a> {
     From(&'a Context<'a>),
+    Flat(&'a FlatEnvironment),
     Extend {
-        parent: &'a FlatEnvironment,
+        parent: &'a Environment<'a>,
         var: &'a Identifier,
         val: &'a Value,
     },
@@ -51,32 +1136,35 @@ enum EnvironmentInner<'a> {
 /// An assignment of [`Value`]s to variables.
 pub struct Environment<'a>(EnvironmentInner<'a>);
 
-impl Environment<'_> {
+impl<'a> Environment<'a> {
     /// The empty environment.
     pub const EMPTY: Environment<'static> = Environment(EnvironmentInner::From(&Context::EMPTY));
 
-    /// Creates an environment from a [`Context`] by mapping each variable to itself.
-    pub fn from_context<'a>(ctx: &'a Context<'a>) -> Environment<'a> {
+    /// Creates an environment from a [`Context`],
+    /// by mapping each variable to its value if it has one, and to itself otherwise.
+    pub fn from_context(ctx: &'a Context<'a>) -> Environment<'a> {
         Environment(EnvironmentInner::From(ctx))
     }
 
-    fn extend<'a>(
-        parent: &'a FlatEnvironment,
-        var: &'a Identifier,
-        val: &'a Value,
-    ) -> Environment<'a> {
-        Environment(EnvironmentInner::Extend { parent, var, val })
+    /// The environment extended with a new variable.
+    pub(crate) fn extend(&'a self, var: &'a Identifier, val: &'a Value) -> Environment<'a> {
+        Environment(EnvironmentInner::Extend {
+            parent: self,
+            var,
+            val,
+        })
     }
 
     fn lookup_value(&self, id: &Identifier) -> crate::Result<Value> {
         use EnvironmentInner::*;
         match self.0 {
-            From(ctx) => {
-                ctx.contains(id)?;
-                Ok(Value::Neutral {
+            From(ctx) => Ok(ctx
+                .lookup_value(id)?
+                .cloned()
+                .unwrap_or_else(|| Value::Neutral {
                     neu: Neutral::Variable(id.clone()),
-                })
-            }
+                })),
+            Flat(env) => env.lookup_value(id),
             Extend { parent, var, val } => {
                 if var == id {
                     Ok(val.clone())
@@ -89,23 +1177,25 @@ impl Environment<'_> {
 
     fn to_flat_environment(&self) -> FlatEnvironment {
         match self.0 {
-            EnvironmentInner::From(ctx) => FlatEnvironment(Dictionary {
-                entries: ctx
-                    .into_iter()
-                    .map(|(var, _)| {
-                        (
-                            var.clone(),
-                            Value::Neutral {
+            EnvironmentInner::From(ctx) => {
+                let mut out = FlatEnvironment(Default::default());
+                // later bindings shadow earlier ones, and are visited first
+                for (var, val) in ctx.values() {
+                    out.0
+                        .entries
+                        .entry(var.clone())
+                        .or_insert_with(|| match val {
+                            Some(val) => val.clone(),
+                            None => Value::Neutral {
                                 neu: Neutral::Variable(var.clone()),
                             },
-                        )
-                    })
-                    .collect(),
-            }),
+                        });
+                }
+                out
+            }
+            EnvironmentInner::Flat(env) => env.clone(),
             EnvironmentInner::Extend { parent, var, val } => {
-                let mut out = FlatEnvironment(Dictionary {
-                    entries: parent.0.entries.clone(),
-                });
+                let mut out = parent.to_flat_environment();
                 out.0.entries.insert(var.clone(), val.clone());
                 out
             }
@@ -114,10 +1204,18 @@ impl Environment<'_> {
 }
 
 /// Determines the [`Value`] of a variable.
-pub fn evaluate_var(defs: &Definitions, env: &Environment, var: &Identifier) -> Value {
-    env.lookup_value(var)
-        .or_else(|_| defs.lookup_value(var).cloned())
-        .unwrap()
+///
+/// A global definition which is polymorphic in universe levels is instantiated at `levels`.
+pub fn evaluate_var(
+    defs: &Definitions,
+    env: &Environment,
+    var: &Identifier,
+    levels: &[Level],
+) -> crate::Result<Value> {
+    match env.lookup_value(var) {
+        Ok(val) => check_level_count(var, 0, levels).map(|()| val),
+        Err(_) => defs.instantiate_value(var, levels),
+    }
 }
 
 /// A [`Value`] which depends on an argument,
@@ -148,10 +1246,19 @@ impl Closure {
     }
 
     /// Calls the closure with an argument.
+    ///
+    /// # Panics
+    ///
+    /// Panics if evaluation fails; see [`try_call`](Closure::try_call) for a version which does not panic.
     pub fn call(&self, defs: &Definitions, val: &Value) -> Value {
-        evaluate(
+        self.try_call(defs, val).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Calls the closure with an argument, failing if evaluation fails.
+    pub fn try_call(&self, defs: &Definitions, val: &Value) -> crate::Result<Value> {
+        try_evaluate(
             defs,
-            &Environment::extend(&self.env, &self.param, val),
+            &Environment(EnvironmentInner::Flat(&self.env)).extend(&self.param, val),
             &self.body,
         )
     }
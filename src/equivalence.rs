@@ -2,10 +2,13 @@
 
 use crate::environment::{Context, Definitions};
 use crate::expression::Expression;
+use crate::identifier::fresh_identifier;
 use crate::lists::Names;
-use crate::read_back::read_back_typed;
-use crate::value::{Type, Value};
-use crate::TypeError;
+use crate::meta::force_type;
+use crate::read_back::{try_read_back_type, try_read_back_typed};
+use crate::unify::unify_types;
+use crate::value::{Neutral, Type, Value};
+use crate::{TypeError, TypeErrorKind};
 
 fn alpha_equiv_helper(
     lhs_names: &Names,
@@ -15,22 +18,37 @@ fn alpha_equiv_helper(
 ) -> bool {
     use Expression::*;
     match (lhs, rhs) {
+        (Located { expr: lhs, .. }, _) => alpha_equiv_helper(lhs_names, lhs, rhs_names, rhs),
+        (_, Located { expr: rhs, .. }) => alpha_equiv_helper(lhs_names, lhs, rhs_names, rhs),
         (Variable(lhs_id), Variable(rhs_id)) => {
             lhs_names.index_of(lhs_id) == rhs_names.index_of(rhs_id)
         }
+        (
+            Instance {
+                name: lhs_name,
+                levels: lhs_levels,
+            },
+            Instance {
+                name: rhs_name,
+                levels: rhs_levels,
+            },
+        ) => lhs_name == rhs_name && lhs_levels == rhs_levels,
         (
             PiType {
                 tparam: lhs_tparam,
                 tparam_type: lhs_tparam_type,
                 ret_type: lhs_ret_type,
+                implicit: lhs_implicit,
             },
             PiType {
                 tparam: rhs_tparam,
                 tparam_type: rhs_tparam_type,
                 ret_type: rhs_ret_type,
+                implicit: rhs_implicit,
             },
         ) => {
-            alpha_equiv_helper(lhs_names, lhs_tparam_type, rhs_names, rhs_tparam_type)
+            lhs_implicit == rhs_implicit
+                && alpha_equiv_helper(lhs_names, lhs_tparam_type, rhs_names, rhs_tparam_type)
                 && alpha_equiv_helper(
                     &lhs_names.extend_names(lhs_tparam),
                     lhs_ret_type,
@@ -43,13 +61,18 @@ fn alpha_equiv_helper(
                 param: lhs_param,
                 param_type: lhs_param_type,
                 ret_val: lhs_ret_val,
+                implicit: lhs_implicit,
             },
             Lambda {
                 param: rhs_param,
                 param_type: rhs_param_type,
                 ret_val: rhs_ret_val,
+                implicit: rhs_implicit,
             },
         ) => {
+            if lhs_implicit != rhs_implicit {
+                return false;
+            }
             if let (Some(lhs_param_type), Some(rhs_param_type)) = (lhs_param_type, rhs_param_type) {
                 if !alpha_equiv_helper(lhs_names, lhs_param_type, rhs_names, rhs_param_type) {
                     return false;
@@ -68,16 +91,157 @@ fn alpha_equiv_helper(
             Application {
                 func: lhs_func,
                 arg: lhs_arg,
+                implicit: lhs_implicit,
             },
             Application {
                 func: rhs_func,
                 arg: rhs_arg,
+                implicit: rhs_implicit,
             },
         ) => {
-            alpha_equiv_helper(lhs_names, lhs_func, rhs_names, rhs_func)
+            lhs_implicit == rhs_implicit
+                && alpha_equiv_helper(lhs_names, lhs_func, rhs_names, rhs_func)
                 && alpha_equiv_helper(lhs_names, lhs_arg, rhs_names, rhs_arg)
         }
-        (Universe, Universe) => true,
+        (
+            SigmaType {
+                tparam: lhs_tparam,
+                tparam_type: lhs_tparam_type,
+                snd_type: lhs_snd_type,
+            },
+            SigmaType {
+                tparam: rhs_tparam,
+                tparam_type: rhs_tparam_type,
+                snd_type: rhs_snd_type,
+            },
+        ) => {
+            alpha_equiv_helper(lhs_names, lhs_tparam_type, rhs_names, rhs_tparam_type)
+                && alpha_equiv_helper(
+                    &lhs_names.extend_names(lhs_tparam),
+                    lhs_snd_type,
+                    &rhs_names.extend_names(rhs_tparam),
+                    rhs_snd_type,
+                )
+        }
+        (
+            Pair {
+                fst: lhs_fst,
+                snd: lhs_snd,
+            },
+            Pair {
+                fst: rhs_fst,
+                snd: rhs_snd,
+            },
+        ) => {
+            alpha_equiv_helper(lhs_names, lhs_fst, rhs_names, rhs_fst)
+                && alpha_equiv_helper(lhs_names, lhs_snd, rhs_names, rhs_snd)
+        }
+        (Record { fields: lhs_fields }, Record { fields: rhs_fields }) => {
+            lhs_fields.len() == rhs_fields.len()
+                && lhs_fields
+                    .iter()
+                    .zip(rhs_fields)
+                    .all(|(lhs, rhs)| alpha_equiv_helper(lhs_names, lhs, rhs_names, rhs))
+        }
+        (First { pair: lhs_pair }, First { pair: rhs_pair })
+        | (Second { pair: lhs_pair }, Second { pair: rhs_pair }) => {
+            alpha_equiv_helper(lhs_names, lhs_pair, rhs_names, rhs_pair)
+        }
+        (
+            IdType {
+                type_: lhs_type_,
+                lhs: lhs_lhs,
+                rhs: lhs_rhs,
+            },
+            IdType {
+                type_: rhs_type_,
+                lhs: rhs_lhs,
+                rhs: rhs_rhs,
+            },
+        ) => {
+            alpha_equiv_helper(lhs_names, lhs_type_, rhs_names, rhs_type_)
+                && alpha_equiv_helper(lhs_names, lhs_lhs, rhs_names, rhs_lhs)
+                && alpha_equiv_helper(lhs_names, lhs_rhs, rhs_names, rhs_rhs)
+        }
+        (Refl { elem: lhs_elem }, Refl { elem: rhs_elem }) => {
+            alpha_equiv_helper(lhs_names, lhs_elem, rhs_names, rhs_elem)
+        }
+        (
+            J {
+                motive: lhs_motive,
+                base: lhs_base,
+                eq: lhs_eq,
+            },
+            J {
+                motive: rhs_motive,
+                base: rhs_base,
+                eq: rhs_eq,
+            },
+        ) => {
+            alpha_equiv_helper(lhs_names, lhs_motive, rhs_names, rhs_motive)
+                && alpha_equiv_helper(lhs_names, lhs_base, rhs_names, rhs_base)
+                && alpha_equiv_helper(lhs_names, lhs_eq, rhs_names, rhs_eq)
+        }
+        (Nat, Nat) => true,
+        (Numeral(lhs_n), Numeral(rhs_n)) => lhs_n == rhs_n,
+        (Succ { pred: lhs_pred }, Succ { pred: rhs_pred }) => {
+            alpha_equiv_helper(lhs_names, lhs_pred, rhs_names, rhs_pred)
+        }
+        (
+            NatRec {
+                motive: lhs_motive,
+                base: lhs_base,
+                step: lhs_step,
+                target: lhs_target,
+            },
+            NatRec {
+                motive: rhs_motive,
+                base: rhs_base,
+                step: rhs_step,
+                target: rhs_target,
+            },
+        ) => {
+            alpha_equiv_helper(lhs_names, lhs_motive, rhs_names, rhs_motive)
+                && alpha_equiv_helper(lhs_names, lhs_base, rhs_names, rhs_base)
+                && alpha_equiv_helper(lhs_names, lhs_step, rhs_names, rhs_step)
+                && alpha_equiv_helper(lhs_names, lhs_target, rhs_names, rhs_target)
+        }
+        (Bool, Bool) | (True, True) | (False, False) => true,
+        (
+            If {
+                motive: lhs_motive,
+                on_true: lhs_on_true,
+                on_false: lhs_on_false,
+                target: lhs_target,
+            },
+            If {
+                motive: rhs_motive,
+                on_true: rhs_on_true,
+                on_false: rhs_on_false,
+                target: rhs_target,
+            },
+        ) => {
+            alpha_equiv_helper(lhs_names, lhs_motive, rhs_names, rhs_motive)
+                && alpha_equiv_helper(lhs_names, lhs_on_true, rhs_names, rhs_on_true)
+                && alpha_equiv_helper(lhs_names, lhs_on_false, rhs_names, rhs_on_false)
+                && alpha_equiv_helper(lhs_names, lhs_target, rhs_names, rhs_target)
+        }
+        (Unit, Unit) | (Star, Star) | (Empty, Empty) => true,
+        (Hole(lhs), Hole(rhs)) | (Meta(lhs), Meta(rhs)) => lhs == rhs,
+        (
+            Absurd {
+                motive: lhs_motive,
+                target: lhs_target,
+            },
+            Absurd {
+                motive: rhs_motive,
+                target: rhs_target,
+            },
+        ) => {
+            alpha_equiv_helper(lhs_names, lhs_motive, rhs_names, rhs_motive)
+                && alpha_equiv_helper(lhs_names, lhs_target, rhs_names, rhs_target)
+        }
+        (Universe(lhs_level), Universe(rhs_level)) => lhs_level == rhs_level,
         (
             Annotation {
                 expr: lhs_expr,
@@ -91,6 +255,29 @@ fn alpha_equiv_helper(
             alpha_equiv_helper(lhs_names, lhs_expr, rhs_names, rhs_expr)
                 && alpha_equiv_helper(lhs_names, lhs_type_, rhs_names, rhs_type_)
         }
+        (
+            Let {
+                name: lhs_name,
+                type_: lhs_type_,
+                value: lhs_value,
+                body: lhs_body,
+            },
+            Let {
+                name: rhs_name,
+                type_: rhs_type_,
+                value: rhs_value,
+                body: rhs_body,
+            },
+        ) => {
+            alpha_equiv_helper(lhs_names, lhs_type_, rhs_names, rhs_type_)
+                && alpha_equiv_helper(lhs_names, lhs_value, rhs_names, rhs_value)
+                && alpha_equiv_helper(
+                    &lhs_names.extend_names(lhs_name),
+                    lhs_body,
+                    &rhs_names.extend_names(rhs_name),
+                    rhs_body,
+                )
+        }
         _ => false,
     }
 }
@@ -100,12 +287,10 @@ pub fn alpha_equivalent(lhs: &Expression, rhs: &Expression) -> crate::Result<()>
     if alpha_equiv_helper(&Names::Empty, lhs, &Names::Empty, rhs) {
         Ok(())
     } else {
-        Err(TypeError {
-            msg: format!(
-                "Expressions `{}` and `{}` are not alpha-equivalent.",
-                lhs, rhs
-            ),
-        })
+        Err(TypeError::new(TypeErrorKind::NotAlphaEquivalent {
+            lhs: lhs.clone(),
+            rhs: rhs.clone(),
+        }))
     }
 }
 
@@ -118,7 +303,67 @@ pub fn judgmentally_equal(
     type_: &Type,
 ) -> crate::Result<()> {
     alpha_equivalent(
-        &read_back_typed(defs, ctx, lhs, type_),
-        &read_back_typed(defs, ctx, rhs, type_),
+        &try_read_back_typed(defs, ctx, lhs, type_)?,
+        &try_read_back_typed(defs, ctx, rhs, type_)?,
+    )
+}
+
+/// Checks judgmental equality of [`Type`]s.
+pub fn judgmentally_equal_types(
+    defs: &Definitions,
+    ctx: &Context,
+    lhs: &Type,
+    rhs: &Type,
+) -> crate::Result<()> {
+    alpha_equivalent(
+        &try_read_back_type(defs, ctx, lhs)?,
+        &try_read_back_type(defs, ctx, rhs)?,
     )
 }
+
+/// Checks that a [`Type`] `sub` may be used where `sup` is expected.
+///
+/// This is judgmental equality, except that universes are cumulative,
+/// and pi types are covariant in their codomains.
+/// Metavariables met along the way are solved by [`unify_types`].
+pub fn subtype(defs: &Definitions, ctx: &Context, sub: &Type, sup: &Type) -> crate::Result<()> {
+    let sub = force_type(defs, sub)?;
+    let sup = force_type(defs, sup)?;
+    match (&**sub, &**sup) {
+        (Value::Universe(sub_level), Value::Universe(sup_level)) => {
+            if sub_level.is_at_most(sup_level) {
+                Ok(())
+            } else {
+                Err(TypeError::new(TypeErrorKind::LevelTooLarge {
+                    found: sub_level.clone(),
+                    expected: sup_level.clone(),
+                }))
+            }
+        }
+        (
+            Value::PiType {
+                param_type: sub_param_type,
+                tclosure: sub_tclosure,
+                implicit: sub_implicit,
+            },
+            Value::PiType {
+                param_type: sup_param_type,
+                tclosure: sup_tclosure,
+                implicit: sup_implicit,
+            },
+        ) if sub_implicit == sup_implicit => {
+            unify_types(defs, ctx, sub_param_type, sup_param_type)?;
+            let fresh_id = fresh_identifier(defs, ctx, sup_tclosure);
+            let fresh_var = Value::Neutral {
+                neu: Neutral::Variable(fresh_id.clone()),
+            };
+            subtype(
+                defs,
+                &ctx.extend(&fresh_id, sup_param_type),
+                &Type::create_type_from_value(sub_tclosure.try_call(defs, &fresh_var)?),
+                &Type::create_type_from_value(sup_tclosure.try_call(defs, &fresh_var)?),
+            )
+        }
+        _ => unify_types(defs, ctx, &sub, &sup),
+    }
+}
@@ -2,45 +2,444 @@
 
 use crate::environment::{evaluate_var, Definitions, Environment};
 use crate::expression::Expression;
+use crate::pattern::try_match;
+use crate::record::try_project;
 use crate::value::{Closure, Neutral, Type, Value};
+use crate::{Identifier, Level, Natural, TypeError, TypeErrorKind};
 
 /// Evaluates an expression to a value.
+///
+/// # Panics
+///
+/// Panics if evaluation fails; see [`try_evaluate`] for a version which does not panic.
 pub fn evaluate(defs: &Definitions, env: &Environment, expr: &Expression) -> Value {
+    try_evaluate(defs, env, expr).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Evaluates an expression to a value,
+/// failing if it mentions an unbound variable or is ill-typed.
+pub fn try_evaluate(
+    defs: &Definitions,
+    env: &Environment,
+    expr: &Expression,
+) -> crate::Result<Value> {
     use Expression::*;
-    match expr {
-        Variable(id) => evaluate_var(defs, env, id),
+    Ok(match expr {
+        Variable(id) => evaluate_var(defs, env, id, &[])?,
+        Instance { name, levels } => defs.instantiate_value(name, levels)?,
         PiType {
             tparam,
             tparam_type,
             ret_type,
+            implicit,
         } => Value::PiType {
-            param_type: Box::new(Type::create_type_from_value(evaluate(
+            param_type: Box::new(Type::create_type_from_value(try_evaluate(
                 defs,
                 env,
                 tparam_type,
-            ))),
+            )?)),
             tclosure: Closure::new_in_env(env, tparam.clone(), *ret_type.clone()),
+            implicit: *implicit,
         },
         Lambda { param, ret_val, .. } => Value::Lambda {
             closure: Closure::new_in_env(env, param.clone(), *ret_val.clone()),
         },
-        Application { func, arg } => {
-            do_apply(defs, &evaluate(defs, env, func), &evaluate(defs, env, arg))
+        Application { func, arg, .. } => try_apply(
+            defs,
+            &try_evaluate(defs, env, func)?,
+            &try_evaluate(defs, env, arg)?,
+        )?,
+        SigmaType {
+            tparam,
+            tparam_type,
+            snd_type,
+        } => Value::SigmaType {
+            fst_type: Box::new(Type::create_type_from_value(try_evaluate(
+                defs,
+                env,
+                tparam_type,
+            )?)),
+            tclosure: Closure::new_in_env(env, tparam.clone(), *snd_type.clone()),
+        },
+        Pair { fst, snd } => Value::Pair {
+            fst: Box::new(try_evaluate(defs, env, fst)?),
+            snd: Box::new(try_evaluate(defs, env, snd)?),
+        },
+        Record { fields } => Value::Record {
+            fields: fields
+                .iter()
+                .map(|field| try_evaluate(defs, env, field))
+                .collect::<crate::Result<_>>()?,
+        },
+        First { pair } => try_first(&try_evaluate(defs, env, pair)?)?,
+        Second { pair } => try_second(&try_evaluate(defs, env, pair)?)?,
+        IdType { type_, lhs, rhs } => Value::IdType {
+            type_: Box::new(Type::create_type_from_value(try_evaluate(
+                defs, env, type_,
+            )?)),
+            lhs: Box::new(try_evaluate(defs, env, lhs)?),
+            rhs: Box::new(try_evaluate(defs, env, rhs)?),
+        },
+        Refl { elem } => Value::Refl {
+            elem: Box::new(try_evaluate(defs, env, elem)?),
+        },
+        J { motive, base, eq } => try_j(
+            defs,
+            &try_evaluate(defs, env, motive)?,
+            &try_evaluate(defs, env, base)?,
+            &try_evaluate(defs, env, eq)?,
+        )?,
+        Nat => Value::Nat,
+        Numeral(n) => Value::Numeral(n.clone()),
+        Succ { pred } => try_succ(&try_evaluate(defs, env, pred)?)?,
+        NatRec {
+            motive,
+            base,
+            step,
+            target,
+        } => try_natrec(
+            defs,
+            &try_evaluate(defs, env, motive)?,
+            &try_evaluate(defs, env, base)?,
+            &try_evaluate(defs, env, step)?,
+            &try_evaluate(defs, env, target)?,
+        )?,
+        Bool => Value::Bool,
+        True => Value::True,
+        False => Value::False,
+        If {
+            motive,
+            on_true,
+            on_false,
+            target,
+        } => try_if(
+            &try_evaluate(defs, env, motive)?,
+            &try_evaluate(defs, env, on_true)?,
+            &try_evaluate(defs, env, on_false)?,
+            &try_evaluate(defs, env, target)?,
+        )?,
+        Unit => Value::Unit,
+        Star => Value::Star,
+        Empty => Value::Empty,
+        Absurd { motive, target } => try_absurd(
+            &try_evaluate(defs, env, motive)?,
+            &try_evaluate(defs, env, target)?,
+        )?,
+        Universe(level) => Value::Universe(level.clone()),
+        Hole(meta) => {
+            let context = defs.metas().get(*meta)?.context.clone();
+            let args = context
+                .iter()
+                .map(|var| evaluate_var(defs, env, var, &[]))
+                .collect::<crate::Result<_>>()?;
+            Value::Neutral {
+                neu: Neutral::Meta { meta: *meta, args },
+            }
         }
-        Universe => Value::Universe,
-        Annotation { expr, .. } => evaluate(defs, env, expr),
-    }
+        Meta(meta) => {
+            let solution = defs.metas().get(*meta)?.solution.clone();
+            match solution {
+                Some(solution) => try_evaluate(defs, &Environment::EMPTY, &solution)?,
+                None => Value::Neutral {
+                    neu: Neutral::Meta {
+                        meta: *meta,
+                        args: Vec::new(),
+                    },
+                },
+            }
+        }
+        Let {
+            name, value, body, ..
+        } => {
+            let value = try_evaluate(defs, env, value)?;
+            try_evaluate(defs, &env.extend(name, &value), body)?
+        }
+        Annotation { expr, .. } | Located { expr, .. } => try_evaluate(defs, env, expr)?,
+    })
 }
 
-pub(crate) fn do_apply(defs: &Definitions, func: &Value, arg: &Value) -> Value {
+/// Applies a function to an argument, failing if `func` is not a function.
+pub fn try_apply(defs: &Definitions, func: &Value, arg: &Value) -> crate::Result<Value> {
     match func {
-        Value::Lambda { closure } => closure.call(defs, arg),
-        Value::Neutral { neu } => Value::Neutral {
+        Value::Lambda { closure } => closure.try_call(defs, arg),
+        Value::Data { name, args } => Ok(Value::Data {
+            name: name.clone(),
+            args: args.iter().chain([arg]).cloned().collect(),
+        }),
+        Value::Constructor { name, args } => Ok(Value::Constructor {
+            name: name.clone(),
+            args: args.iter().chain([arg]).cloned().collect(),
+        }),
+        Value::RecordType { name, args } => Ok(Value::RecordType {
+            name: name.clone(),
+            args: args.iter().chain([arg]).cloned().collect(),
+        }),
+        Value::Projection {
+            record,
+            field,
+            args,
+        } => {
+            if args.len() == defs.record(record)?.params {
+                try_project(defs, record, field, args, arg)
+            } else {
+                Ok(Value::Projection {
+                    record: record.clone(),
+                    field: field.clone(),
+                    args: args.iter().chain([arg]).cloned().collect(),
+                })
+            }
+        }
+        Value::Eliminator {
+            family,
+            levels,
+            args,
+        } => {
+            let args: Vec<Value> = args.iter().chain([arg]).cloned().collect();
+            if args.len() == defs.family(family)?.eliminator_arity() {
+                try_eliminate(defs, family, levels, &args)
+            } else {
+                Ok(Value::Eliminator {
+                    family: family.clone(),
+                    levels: levels.clone(),
+                    args,
+                })
+            }
+        }
+        Value::Function { name, args } => {
+            let args: Vec<Value> = args.iter().chain([arg]).cloned().collect();
+            if args.len() == defs.function(name)?.arity {
+                try_match(defs, name, &args)
+            } else {
+                Ok(Value::Function {
+                    name: name.clone(),
+                    args,
+                })
+            }
+        }
+        Value::Neutral {
+            neu: Neutral::Meta { meta, args },
+        } => Ok(Value::Neutral {
+            neu: Neutral::Meta {
+                meta: *meta,
+                args: args.iter().chain([arg]).cloned().collect(),
+            },
+        }),
+        Value::Neutral { neu } => Ok(Value::Neutral {
             neu: Neutral::Application {
                 func: Box::new(neu.clone()),
                 arg: Box::new(arg.clone()),
             },
-        },
-        _ => panic!("Cannot call `{}` because it is not a function.", func),
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot call `{}` because it is not a function.",
+            func
+        )))),
+    }
+}
+
+/// Eliminates an element of a declared inductive family,
+/// given every argument of its eliminator, ending with the target.
+///
+/// When the target is built from a constructor, this applies the method for that constructor
+/// to its fields, each recursive field followed by its elimination.
+fn try_eliminate(
+    defs: &Definitions,
+    family: &Identifier,
+    levels: &[Level],
+    args: &[Value],
+) -> crate::Result<Value> {
+    let info = defs.family(family)?;
+    let Some((target, args)) = args.split_last() else {
+        unreachable!("an eliminator takes at least its target")
+    };
+    match target {
+        Value::Constructor {
+            name,
+            args: constructor_args,
+        } => {
+            let Some((_, rule)) = info.constructors.iter().find(|(c, _)| c == name) else {
+                return Err(TypeError::new(TypeErrorKind::Internal(format!(
+                    "Cannot eliminate `{}` because it is not an element of `{}`.",
+                    target, family
+                ))));
+            };
+            let elim = Value::Eliminator {
+                family: family.clone(),
+                levels: levels.to_vec(),
+                args: Vec::new(),
+            };
+            // the rule takes the parameters, motive and methods, but not the indices
+            let common = &args[..info.params + 1 + info.constructors.len()];
+            let fields = constructor_args.get(info.params..).unwrap_or_default();
+            let mut result = try_apply(defs, rule, &elim)?;
+            for arg in common.iter().chain(fields) {
+                result = try_apply(defs, &result, arg)?;
+            }
+            Ok(result)
+        }
+        Value::Neutral { neu } => Ok(Value::Neutral {
+            neu: Neutral::Eliminator {
+                family: family.clone(),
+                levels: levels.to_vec(),
+                args: args.to_vec(),
+                target: Box::new(neu.clone()),
+            },
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot eliminate `{}` because it is not an element of `{}`.",
+            target, family
+        )))),
+    }
+}
+
+/// Takes the first component of a pair, failing if `pair` is not a pair.
+pub fn try_first(pair: &Value) -> crate::Result<Value> {
+    match pair {
+        Value::Pair { fst, .. } => Ok(*fst.clone()),
+        Value::Neutral { neu } => Ok(Value::Neutral {
+            neu: Neutral::First {
+                pair: Box::new(neu.clone()),
+            },
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot project from `{}` because it is not a pair.",
+            pair
+        )))),
+    }
+}
+
+/// Takes the second component of a pair, failing if `pair` is not a pair.
+pub fn try_second(pair: &Value) -> crate::Result<Value> {
+    match pair {
+        Value::Pair { snd, .. } => Ok(*snd.clone()),
+        Value::Neutral { neu } => Ok(Value::Neutral {
+            neu: Neutral::Second {
+                pair: Box::new(neu.clone()),
+            },
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot project from `{}` because it is not a pair.",
+            pair
+        )))),
+    }
+}
+
+/// Eliminates an equality, failing if `eq` is not an equality.
+///
+/// Computes to `base` applied to `elem` when `eq` is `refl(elem)`.
+pub fn try_j(defs: &Definitions, motive: &Value, base: &Value, eq: &Value) -> crate::Result<Value> {
+    match eq {
+        Value::Refl { elem } => try_apply(defs, base, elem),
+        Value::Neutral { neu } => Ok(Value::Neutral {
+            neu: Neutral::J {
+                motive: Box::new(motive.clone()),
+                base: Box::new(base.clone()),
+                eq: Box::new(neu.clone()),
+            },
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot eliminate `{}` because it is not an equality.",
+            eq
+        )))),
+    }
+}
+
+/// Takes the successor of a natural number, failing if `pred` is not a natural number.
+pub fn try_succ(pred: &Value) -> crate::Result<Value> {
+    match pred {
+        Value::Numeral(n) => Ok(Value::Numeral(n.successor())),
+        Value::Succ { .. } | Value::Neutral { .. } => Ok(Value::Succ {
+            pred: Box::new(pred.clone()),
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot take the successor of `{}` because it is not a natural number.",
+            pred
+        )))),
+    }
+}
+
+/// Eliminates a natural number, failing if `target` is not a natural number.
+///
+/// On a numeral `n`, the `step` is applied `n` times in a loop, starting from `base`.
+pub fn try_natrec(
+    defs: &Definitions,
+    motive: &Value,
+    base: &Value,
+    step: &Value,
+    target: &Value,
+) -> crate::Result<Value> {
+    match target {
+        Value::Numeral(n) => {
+            let mut k = Natural::ZERO;
+            let mut acc = base.clone();
+            while k != *n {
+                acc = try_apply(
+                    defs,
+                    &try_apply(defs, step, &Value::Numeral(k.clone()))?,
+                    &acc,
+                )?;
+                k = k.successor();
+            }
+            Ok(acc)
+        }
+        Value::Succ { pred } => {
+            let acc = try_natrec(defs, motive, base, step, pred)?;
+            try_apply(defs, &try_apply(defs, step, pred)?, &acc)
+        }
+        Value::Neutral { neu } => Ok(Value::Neutral {
+            neu: Neutral::NatRec {
+                motive: Box::new(motive.clone()),
+                base: Box::new(base.clone()),
+                step: Box::new(step.clone()),
+                target: Box::new(neu.clone()),
+            },
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot eliminate `{}` because it is not a natural number.",
+            target
+        )))),
+    }
+}
+
+/// Eliminates a boolean, failing if `target` is not a boolean.
+pub fn try_if(
+    motive: &Value,
+    on_true: &Value,
+    on_false: &Value,
+    target: &Value,
+) -> crate::Result<Value> {
+    match target {
+        Value::True => Ok(on_true.clone()),
+        Value::False => Ok(on_false.clone()),
+        Value::Neutral { neu } => Ok(Value::Neutral {
+            neu: Neutral::If {
+                motive: Box::new(motive.clone()),
+                on_true: Box::new(on_true.clone()),
+                on_false: Box::new(on_false.clone()),
+                target: Box::new(neu.clone()),
+            },
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot eliminate `{}` because it is not a boolean.",
+            target
+        )))),
+    }
+}
+
+/// Eliminates an element of the empty type, failing if `target` is not neutral.
+///
+/// As the empty type has no elements, this never computes.
+pub fn try_absurd(motive: &Value, target: &Value) -> crate::Result<Value> {
+    match target {
+        Value::Neutral { neu } => Ok(Value::Neutral {
+            neu: Neutral::Absurd {
+                motive: Box::new(motive.clone()),
+                target: Box::new(neu.clone()),
+            },
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot eliminate `{}` because it is not an element of the empty type.",
+            target
+        )))),
     }
 }
@@ -1,31 +1,129 @@
 //! A type representing a computation.
 
-use crate::Identifier;
+use crate::meta::MetaVariable;
+use crate::{Identifier, Level, Natural, Span, TypeError, TypeErrorKind};
 use std::fmt;
 
 /// Computes to a [`Value`](crate::value::Value).
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Expression {
     Variable(Identifier),
+    /// A global definition instantiated at the universe levels `levels`,
+    /// which no local variable can shadow; it is written as just its name if there are none.
+    Instance {
+        name: Identifier,
+        levels: Vec<Level>,
+    },
+    /// A pi type, whose parameter is `implicit` if its arguments are left to be inferred.
     PiType {
         tparam: Identifier,
         tparam_type: Box<Expression>,
         ret_type: Box<Expression>,
+        implicit: bool,
     },
+    /// A function, which is `implicit` if it is of an implicit pi type.
     Lambda {
         param: Identifier,
         param_type: Option<Box<Expression>>,
         ret_val: Box<Expression>,
+        implicit: bool,
     },
+    /// An application, which is `implicit` if it gives an implicit argument explicitly.
     Application {
         func: Box<Expression>,
         arg: Box<Expression>,
+        implicit: bool,
+    },
+    SigmaType {
+        tparam: Identifier,
+        tparam_type: Box<Expression>,
+        snd_type: Box<Expression>,
+    },
+    Pair {
+        fst: Box<Expression>,
+        snd: Box<Expression>,
+    },
+    /// An element of a declared record type, given by its fields in order.
+    Record {
+        fields: Vec<Expression>,
+    },
+    First {
+        pair: Box<Expression>,
+    },
+    Second {
+        pair: Box<Expression>,
+    },
+    IdType {
+        type_: Box<Expression>,
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+    },
+    Refl {
+        elem: Box<Expression>,
+    },
+    /// Eliminates an equality `eq` with a `motive` and a `base` case for reflexivity.
+    J {
+        motive: Box<Expression>,
+        base: Box<Expression>,
+        eq: Box<Expression>,
+    },
+    Nat,
+    /// A natural number, stored compactly rather than as repeated successors.
+    Numeral(Natural),
+    Succ {
+        pred: Box<Expression>,
+    },
+    /// Eliminates a natural number `target` with a `motive`, a `base` case and a `step` case.
+    NatRec {
+        motive: Box<Expression>,
+        base: Box<Expression>,
+        step: Box<Expression>,
+        target: Box<Expression>,
     },
-    Universe,
+    Bool,
+    True,
+    False,
+    /// Eliminates a boolean `target` with a `motive` and a case for each boolean.
+    If {
+        motive: Box<Expression>,
+        on_true: Box<Expression>,
+        on_false: Box<Expression>,
+        target: Box<Expression>,
+    },
+    Unit,
+    /// The single element of [`Unit`](Expression::Unit).
+    Star,
+    Empty,
+    /// Eliminates an element `target` of [`Empty`](Expression::Empty), of which there are none.
+    Absurd {
+        motive: Box<Expression>,
+        target: Box<Expression>,
+    },
+    /// The universe of types at a level, which is itself a type at the next level.
+    Universe(Level),
     Annotation {
         expr: Box<Expression>,
         type_: Box<Expression>,
     },
+    /// A part of a term yet to be written, which checks against any type,
+    /// leaving a [`Goal`](crate::meta::Goal) for what it must be.
+    ///
+    /// It stands for the metavariable applied to the variables in scope where it is checked.
+    Hole(MetaVariable),
+    /// A metavariable, standing for a closed term of the type it was created at.
+    Meta(MetaVariable),
+    /// A local definition of `name`, visible while checking `body`.
+    Let {
+        name: Identifier,
+        type_: Box<Expression>,
+        value: Box<Expression>,
+        body: Box<Expression>,
+    },
+    /// An expression together with the span of source text it came from.
+    Located {
+        span: Span,
+        expr: Box<Expression>,
+    },
 }
 
 impl fmt::Display for Expression {
@@ -33,82 +131,471 @@ impl fmt::Display for Expression {
         use Expression::*;
         match self {
             Variable(id) => id.fmt(f),
+            Instance { name, levels } if levels.is_empty() => name.fmt(f),
+            Instance { name, levels } => write!(f, "{}.{{{}}}", name, Levels(levels)),
             PiType {
                 tparam,
                 tparam_type,
                 ret_type,
+                implicit: false,
             } => write!(f, "\u{220F}({} : {}){}", tparam, tparam_type, ret_type),
+            PiType {
+                tparam,
+                tparam_type,
+                ret_type,
+                implicit: true,
+            } => write!(f, "\u{220F}{{{} : {}}}{}", tparam, tparam_type, ret_type),
             Lambda {
                 param,
                 param_type,
                 ret_val,
+                implicit: false,
             } => match param_type {
                 Some(param_type) => write!(f, "\u{03BB}({} : {}){}", param, param_type, ret_val),
                 None => write!(f, "\u{03BB}{}.{}", param, ret_val),
             },
-            Application { func, arg } => write!(f, "({})({})", func, arg),
-            Universe => write!(f, "U"),
+            Lambda {
+                param,
+                param_type,
+                ret_val,
+                implicit: true,
+            } => match param_type {
+                Some(param_type) => write!(f, "\u{03BB}{{{} : {}}}{}", param, param_type, ret_val),
+                None => write!(f, "\u{03BB}{{{}}}{}", param, ret_val),
+            },
+            Application {
+                func,
+                arg,
+                implicit: false,
+            } => write!(f, "({})({})", func, arg),
+            Application {
+                func,
+                arg,
+                implicit: true,
+            } => write!(f, "({}){{{}}}", func, arg),
+            SigmaType {
+                tparam,
+                tparam_type,
+                snd_type,
+            } => write!(f, "\u{03A3}({} : {}){}", tparam, tparam_type, snd_type),
+            Pair { fst, snd } => write!(f, "({}, {})", fst, snd),
+            Record { fields } => {
+                write!(f, "{{")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    field.fmt(f)?;
+                }
+                write!(f, "}}")
+            }
+            First { pair } => write!(f, "fst({})", pair),
+            Second { pair } => write!(f, "snd({})", pair),
+            IdType { type_, lhs, rhs } => write!(f, "Id({})({})({})", type_, lhs, rhs),
+            Refl { elem } => write!(f, "refl({})", elem),
+            J { motive, base, eq } => write!(f, "J({})({})({})", motive, base, eq),
+            Nat => write!(f, "Nat"),
+            Numeral(n) => n.fmt(f),
+            Succ { pred } => write!(f, "succ({})", pred),
+            NatRec {
+                motive,
+                base,
+                step,
+                target,
+            } => write!(f, "natrec({})({})({})({})", motive, base, step, target),
+            Bool => write!(f, "Bool"),
+            True => write!(f, "true"),
+            False => write!(f, "false"),
+            If {
+                motive,
+                on_true,
+                on_false,
+                target,
+            } => write!(f, "if({})({})({})({})", motive, on_true, on_false, target),
+            Unit => write!(f, "Unit"),
+            Star => write!(f, "tt"),
+            Empty => write!(f, "Empty"),
+            Hole(_) => write!(f, "?"),
+            Meta(meta) => meta.fmt(f),
+            Absurd { motive, target } => write!(f, "absurd({})({})", motive, target),
+            Universe(level) => match level.as_constant() {
+                Some(0) => write!(f, "U"),
+                Some(n) => write!(f, "U {}", n),
+                None => write!(f, "U.{{{}}}", level),
+            },
             Annotation { expr, type_ } => write!(f, "({} : {})", expr, type_),
+            Let {
+                name,
+                type_,
+                value,
+                body,
+            } => write!(f, "let {} : {} = {} in {}", name, type_, value, body),
+            Located { expr, .. } => expr.fmt(f),
         }
     }
 }
 
-/*macro_rules! parse_expr {
-    (a($name:literal)) => {
-        Expr::Variable(Identifier{ name: $name })
-    };
-    (P (a($name:literal) : $($t:tt)*) $($r:tt)*) => {
-        Expr::PiType{
-            tparam: Identifier{ name: $name },
-            tparam_type: Box::new(
-                parse_expr!($($t)*)
-            ),
-            ret_type: Box::new(
-                parse_expr!($($r)*)
-            ),
+/// Displays levels separated by commas.
+pub(crate) struct Levels<'a>(pub(crate) &'a [Level]);
+
+impl fmt::Display for Levels<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, level) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            level.fmt(f)?;
         }
-    };
-    (L (a($name:literal) : $($t:tt)*) $($r:tt)*) => {
-        Expr::Lambda{
-            param: Identifier{ name: $name },
-            param_type: Some(Box::new(
-                parse_expr!($($t)*)
-            )),
-            ret_val: Box::new(
-                parse_expr!($($r)*)
-            ),
+        Ok(())
+    }
+}
+
+impl Expression {
+    /// This expression without any [`Located`](Expression::Located) wrappers at the top.
+    pub(crate) fn unlocated(&self) -> &Expression {
+        let mut expr = self;
+        while let Expression::Located { expr: inner, .. } = expr {
+            expr = inner;
         }
-    };
-    (L (a($name:literal)) $($r:tt)*) => {
-        Expr::Lambda{
-            param: Identifier{ name: $name },
-            param_type: None,
-            ret_val: Box::new(
-                parse_expr!($($r)*)
-            ),
+        expr
+    }
+
+    /// Whether the variable `id` occurs free in this expression.
+    pub(crate) fn has_free_variable(&self, id: &Identifier) -> bool {
+        use Expression::*;
+        match self {
+            Variable(var) => var == id,
+            PiType {
+                tparam,
+                tparam_type,
+                ret_type: body,
+                ..
+            }
+            | SigmaType {
+                tparam,
+                tparam_type,
+                snd_type: body,
+            } => tparam_type.has_free_variable(id) || (tparam != id && body.has_free_variable(id)),
+            Lambda {
+                param,
+                param_type,
+                ret_val,
+                ..
+            } => {
+                param_type
+                    .as_ref()
+                    .is_some_and(|type_| type_.has_free_variable(id))
+                    || (param != id && ret_val.has_free_variable(id))
+            }
+            Let {
+                name,
+                type_,
+                value,
+                body,
+            } => {
+                type_.has_free_variable(id)
+                    || value.has_free_variable(id)
+                    || (name != id && body.has_free_variable(id))
+            }
+            _ => self
+                .subexpressions()
+                .into_iter()
+                .any(|expr| expr.has_free_variable(id)),
         }
-    };
-    (($($f:tt)*)($($a:tt)*)) => {
-        Expr::Application{
-            func: Box::new(
-                parse_expr!($($f)*)
-            ),
-            arg: Box::new(
-                parse_expr!($($a)*)
-            ),
+    }
+
+    /// Whether this expression refers to the global `id`,
+    /// by a free variable or by an [`Instance`](Expression::Instance).
+    pub(crate) fn refers_to_global(&self, id: &Identifier) -> bool {
+        self.has_free_variable(id) || self.has_instance(id)
+    }
+
+    fn has_instance(&self, id: &Identifier) -> bool {
+        match self {
+            Expression::Instance { name, .. } => name == id,
+            _ => self
+                .subexpressions()
+                .into_iter()
+                .any(|expr| expr.has_instance(id)),
         }
-    };
-    (U) => {
-        Expr::Universe
-    };
-    (($($e:tt)*) : ($($t:tt)*)) => {
-        Expr::Annotation{
-            expr: Box::new(
-                parse_expr!($($e)*)
-            ),
-            type_: Box::new(
-                parse_expr!($($t)*)
-            ),
+    }
+
+    /// Every identifier occurring in this expression, whether free or bound.
+    pub(crate) fn identifiers(&self) -> Vec<Identifier> {
+        use Expression::*;
+        let mut out = match self {
+            Variable(id)
+            | Instance { name: id, .. }
+            | PiType { tparam: id, .. }
+            | SigmaType { tparam: id, .. }
+            | Lambda { param: id, .. }
+            | Let { name: id, .. } => vec![id.clone()],
+            _ => vec![],
+        };
+        for expr in self.subexpressions() {
+            out.extend(expr.identifiers());
+        }
+        out
+    }
+
+    /// Whether a hole occurs in this expression.
+    pub(crate) fn has_hole(&self) -> bool {
+        matches!(self, Expression::Hole(_))
+            || self.subexpressions().into_iter().any(Expression::has_hole)
+    }
+
+    /// The metavariables and holes occurring in this expression.
+    pub(crate) fn metas(&self) -> Vec<MetaVariable> {
+        let mut out: Vec<MetaVariable> = match self {
+            Expression::Hole(meta) | Expression::Meta(meta) => vec![*meta],
+            _ => Vec::new(),
+        };
+        out.extend(
+            self.subexpressions()
+                .into_iter()
+                .flat_map(Expression::metas),
+        );
+        out
+    }
+
+    /// The immediate subexpressions of this expression.
+    pub(crate) fn subexpressions(&self) -> Vec<&Expression> {
+        use Expression::*;
+        match self {
+            Variable(_)
+            | Instance { .. }
+            | Nat
+            | Numeral(_)
+            | Bool
+            | True
+            | False
+            | Unit
+            | Star
+            | Empty
+            | Hole(_)
+            | Meta(_)
+            | Universe(_) => vec![],
+            PiType {
+                tparam_type,
+                ret_type,
+                ..
+            } => vec![tparam_type, ret_type],
+            Lambda {
+                param_type,
+                ret_val,
+                ..
+            } => param_type.iter().chain([ret_val]).map(|e| &**e).collect(),
+            Application { func, arg, .. } => vec![func, arg],
+            SigmaType {
+                tparam_type,
+                snd_type,
+                ..
+            } => vec![tparam_type, snd_type],
+            Pair { fst, snd } => vec![fst, snd],
+            Record { fields } => fields.iter().collect(),
+            First { pair } | Second { pair } => vec![pair],
+            IdType { type_, lhs, rhs } => vec![type_, lhs, rhs],
+            Refl { elem } => vec![elem],
+            J { motive, base, eq } => vec![motive, base, eq],
+            Succ { pred } => vec![pred],
+            NatRec {
+                motive,
+                base,
+                step,
+                target,
+            } => vec![motive, base, step, target],
+            If {
+                motive,
+                on_true,
+                on_false,
+                target,
+            } => vec![motive, on_true, on_false, target],
+            Absurd { motive, target } => vec![motive, target],
+            Annotation { expr, type_ } => vec![expr, type_],
+            Let {
+                type_, value, body, ..
+            } => vec![type_, value, body],
+            Located { expr, .. } => vec![expr],
         }
-    };
-}*/
+    }
+
+    /// Rebuilds this expression with each immediate subexpression replaced by `f` of it.
+    pub(crate) fn try_map_subexpressions<E>(
+        &self,
+        mut f: impl FnMut(&Expression) -> Result<Expression, E>,
+    ) -> Result<Expression, E> {
+        use Expression::*;
+        let mut f = |expr: &Expression| f(expr).map(Box::new);
+        Ok(match self {
+            Variable(_)
+            | Instance { .. }
+            | Nat
+            | Numeral(_)
+            | Bool
+            | True
+            | False
+            | Unit
+            | Star
+            | Empty
+            | Hole(_)
+            | Meta(_)
+            | Universe(_) => self.clone(),
+            PiType {
+                tparam,
+                tparam_type,
+                ret_type,
+                implicit,
+            } => PiType {
+                tparam: tparam.clone(),
+                tparam_type: f(tparam_type)?,
+                ret_type: f(ret_type)?,
+                implicit: *implicit,
+            },
+            Lambda {
+                param,
+                param_type,
+                ret_val,
+                implicit,
+            } => Lambda {
+                param: param.clone(),
+                param_type: param_type.as_deref().map(&mut f).transpose()?,
+                ret_val: f(ret_val)?,
+                implicit: *implicit,
+            },
+            Application {
+                func,
+                arg,
+                implicit,
+            } => Application {
+                func: f(func)?,
+                arg: f(arg)?,
+                implicit: *implicit,
+            },
+            SigmaType {
+                tparam,
+                tparam_type,
+                snd_type,
+            } => SigmaType {
+                tparam: tparam.clone(),
+                tparam_type: f(tparam_type)?,
+                snd_type: f(snd_type)?,
+            },
+            Pair { fst, snd } => Pair {
+                fst: f(fst)?,
+                snd: f(snd)?,
+            },
+            Record { fields } => Record {
+                fields: fields
+                    .iter()
+                    .map(|field| f(field).map(|field| *field))
+                    .collect::<Result<_, _>>()?,
+            },
+            First { pair } => First { pair: f(pair)? },
+            Second { pair } => Second { pair: f(pair)? },
+            IdType { type_, lhs, rhs } => IdType {
+                type_: f(type_)?,
+                lhs: f(lhs)?,
+                rhs: f(rhs)?,
+            },
+            Refl { elem } => Refl { elem: f(elem)? },
+            J { motive, base, eq } => J {
+                motive: f(motive)?,
+                base: f(base)?,
+                eq: f(eq)?,
+            },
+            Succ { pred } => Succ { pred: f(pred)? },
+            NatRec {
+                motive,
+                base,
+                step,
+                target,
+            } => NatRec {
+                motive: f(motive)?,
+                base: f(base)?,
+                step: f(step)?,
+                target: f(target)?,
+            },
+            If {
+                motive,
+                on_true,
+                on_false,
+                target,
+            } => If {
+                motive: f(motive)?,
+                on_true: f(on_true)?,
+                on_false: f(on_false)?,
+                target: f(target)?,
+            },
+            Absurd { motive, target } => Absurd {
+                motive: f(motive)?,
+                target: f(target)?,
+            },
+            Annotation { expr, type_ } => Annotation {
+                expr: f(expr)?,
+                type_: f(type_)?,
+            },
+            Let {
+                name,
+                type_,
+                value,
+                body,
+            } => Let {
+                name: name.clone(),
+                type_: f(type_)?,
+                value: f(value)?,
+                body: f(body)?,
+            },
+            Located { span, expr } => Located {
+                span: *span,
+                expr: f(expr)?,
+            },
+        })
+    }
+
+    /// The universe level variables occurring in this expression, possibly with repeats.
+    pub(crate) fn level_variables(&self) -> Vec<Identifier> {
+        let levels: &[Level] = match self {
+            Expression::Universe(level) => std::slice::from_ref(level),
+            Expression::Instance { levels, .. } => levels,
+            _ => &[],
+        };
+        let mut out: Vec<Identifier> = levels
+            .iter()
+            .flat_map(|level| level.variables().cloned())
+            .collect();
+        for expr in self.subexpressions() {
+            out.extend(expr.level_variables());
+        }
+        out
+    }
+
+    /// Replaces each of the universe level variables `params` with the corresponding `levels`.
+    pub(crate) fn substitute_levels(
+        &self,
+        params: &[Identifier],
+        levels: &[Level],
+    ) -> crate::Result<Expression> {
+        let substitute = |level: &Level| {
+            level
+                .substitute(|id| {
+                    let index = params.iter().position(|param| param == id)?;
+                    levels.get(index).cloned()
+                })
+                .ok_or_else(|| TypeError::new(TypeErrorKind::UniverseTooLarge(level.clone())))
+        };
+        match self {
+            Expression::Universe(level) => Ok(Expression::Universe(substitute(level)?)),
+            Expression::Instance {
+                name,
+                levels: instance_levels,
+            } => Ok(Expression::Instance {
+                name: name.clone(),
+                levels: instance_levels
+                    .iter()
+                    .map(substitute)
+                    .collect::<crate::Result<_>>()?,
+            }),
+            _ => self.try_map_subexpressions(|expr| expr.substitute_levels(params, levels)),
+        }
+    }
+}
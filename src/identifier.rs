@@ -1,27 +1,122 @@
 use crate::environment::{Context, Definitions};
 use crate::min_excluded;
+use crate::surface::Binder;
 use crate::value::Closure;
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::{Mutex, OnceLock};
 
 /// The name of a variable.
+///
+/// Names are interned in a global symbol table,
+/// so identifiers are cheap to clone, compare and hash.
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Identifier {
-    pub name: usize,
+    symbol: u32,
+}
+
+#[derive(Default)]
+struct SymbolTable {
+    names: Vec<&'static str>,
+    symbols: HashMap<&'static str, u32>,
+}
+
+fn symbol_table() -> &'static Mutex<SymbolTable> {
+    static SYMBOL_TABLE: OnceLock<Mutex<SymbolTable>> = OnceLock::new();
+    SYMBOL_TABLE.get_or_init(Default::default)
+}
+
+impl Identifier {
+    /// Creates the identifier with a given name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::Identifier;
+    ///
+    /// assert!(Identifier::new("x") == Identifier::new("x"));
+    /// assert!(Identifier::new("x") != Identifier::new("y"));
+    /// assert_eq!(Identifier::new("x").name(), "x");
+    /// ```
+    pub fn new(name: &str) -> Identifier {
+        let mut table = symbol_table().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(&symbol) = table.symbols.get(name) {
+            return Identifier { symbol };
+        }
+        // interned names live for the rest of the program
+        let name: &'static str = Box::leak(name.into());
+        let symbol = u32::try_from(table.names.len()).expect("too many identifiers");
+        table.names.push(name);
+        table.symbols.insert(name, symbol);
+        Identifier { symbol }
+    }
+
+    /// The name of the identifier.
+    pub fn name(&self) -> &'static str {
+        symbol_table()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .names[self.symbol as usize]
+    }
+}
+
+impl fmt::Debug for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Identifier").field(&self.name()).finish()
+    }
 }
 
 impl fmt::Display for Identifier {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "a{}", self.name)
+        f.write_str(self.name())
     }
 }
 
-pub(crate) fn fresh_identifier(defs: &Definitions, ctx: &Context, closure: &Closure) -> Identifier {
-    Identifier {
-        name: min_excluded::min_excluded(
-            defs.names()
-                .chain(ctx.names())
-                .chain(closure.names())
-                .map(|id| id.name),
-        ),
+/// Splits a name into a stem and a numeric suffix, with `0` standing for no suffix.
+fn split_suffix(name: &str) -> (&str, usize) {
+    let stem = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    match &name[stem.len()..] {
+        digits if !stem.is_empty() && !digits.is_empty() && !digits.starts_with('0') => {
+            match digits.parse() {
+                Ok(suffix) => (stem, suffix),
+                Err(_) => (name, 0),
+            }
+        }
+        _ => (name, 0),
+    }
+}
+
+/// Creates an identifier named after `base`, with a numeric suffix if necessary,
+/// which is not any of the identifiers in `used`.
+pub(crate) fn fresh_from<'a, I>(base: &str, used: I) -> Identifier
+where
+    I: Iterator<Item = &'a Identifier> + Clone,
+{
+    let (stem, _) = split_suffix(base);
+    let suffix = min_excluded::min_excluded(
+        used.map(|id| split_suffix(id.name()))
+            .filter(|&(id_stem, _)| id_stem == stem)
+            .map(|(_, suffix)| suffix),
+    );
+    match suffix {
+        0 => Identifier::new(stem),
+        _ => Identifier::new(&format!("{}{}", stem, suffix)),
     }
 }
+
+/// Creates an identifier not otherwise in use,
+/// named after the parameter of `closure` with a numeric suffix if necessary,
+/// or after `x` if the parameter is a wildcard, which no variable can refer to.
+pub(crate) fn fresh_identifier(defs: &Definitions, ctx: &Context, closure: &Closure) -> Identifier {
+    let base = match closure.param == Binder::wildcard() {
+        true => "x",
+        false => closure.param.name(),
+    };
+    fresh_from(base, defs.names().chain(ctx.names()).chain(closure.names()))
+}
+
+/// Creates an identifier not defined or in the context,
+/// named after `base` with a numeric suffix if necessary.
+pub(crate) fn fresh_in_context(defs: &Definitions, ctx: &Context, base: &str) -> Identifier {
+    fresh_from(base, defs.names().chain(ctx.names()))
+}
@@ -0,0 +1,467 @@
+//! Declarations of inductive families, and the constants they generate.
+
+use crate::environment::{Context, Definitions, Environment};
+use crate::evaluation::try_evaluate;
+use crate::expression::Expression;
+use crate::identifier::fresh_from;
+use crate::typing::{apply_all, check_is_type};
+use crate::value::{Type, Value};
+use crate::{Identifier, Level, TypeError, TypeErrorKind};
+
+/// A declaration of an inductive family, such as
+/// `data Vec (A : U) : ∏(n : Nat)U where nil : Vec A 0 | cons : ...`.
+///
+/// Declaring it with [`Definitions::declare_data`] defines the family `name`,
+/// each of its constructors, and its eliminator, named as given by [`eliminator_name`].
+#[derive(Clone, Debug)]
+pub struct DataDeclaration {
+    pub name: Identifier,
+    /// The parameters of the family with their types, which are the same for every constructor.
+    pub params: Vec<(Identifier, Expression)>,
+    /// The type of the family applied to its parameters:
+    /// pi types over its indices, ending in a universe.
+    pub arity: Expression,
+    pub constructors: Vec<ConstructorDeclaration>,
+}
+
+/// A constructor in a [`DataDeclaration`].
+#[derive(Clone, Debug)]
+pub struct ConstructorDeclaration {
+    pub name: Identifier,
+    /// The type of the constructor applied to the parameters of its family:
+    /// pi types over its fields, ending in the family applied to the parameters and some indices.
+    pub type_: Expression,
+}
+
+impl DataDeclaration {
+    /// The type of the family itself.
+    pub fn family_type(&self) -> Expression {
+        pi_types(&self.params, self.arity.clone())
+    }
+
+    /// The type of the constructor `constructor` itself.
+    pub fn constructor_type(&self, constructor: &ConstructorDeclaration) -> Expression {
+        pi_types(&self.params, constructor.type_.clone())
+    }
+
+    /// Every name the declaration defines.
+    pub(crate) fn defined_names(&self) -> Vec<Identifier> {
+        let mut names = vec![self.name.clone()];
+        names.extend(self.constructors.iter().map(|c| c.name.clone()));
+        names.push(eliminator_name(&self.name));
+        names
+    }
+
+    /// Every expression in the declaration.
+    pub(crate) fn expressions(&self) -> impl Iterator<Item = &Expression> {
+        self.params
+            .iter()
+            .map(|(_, type_)| type_)
+            .chain([&self.arity])
+            .chain(self.constructors.iter().map(|c| &c.type_))
+    }
+
+    fn param_variables(&self) -> impl Iterator<Item = Expression> + '_ {
+        self.params
+            .iter()
+            .map(|(param, _)| Expression::Variable(param.clone()))
+    }
+
+    /// Whether `args` are exactly the parameters, followed by indices not mentioning the family.
+    fn indices_of<'a>(&self, args: &[&'a Expression]) -> Option<Vec<&'a Expression>> {
+        let (params, indices) = args.split_at_checked(self.params.len())?;
+        let params_uniform = params.iter().zip(&self.params).all(|(arg, (param, _))| {
+            matches!(arg.unlocated(), Expression::Variable(var) if var == param)
+        });
+        let indices_free = indices
+            .iter()
+            .all(|index| !index.has_free_variable(&self.name));
+        (params_uniform && indices_free).then(|| indices.to_vec())
+    }
+
+    /// The indices at which `expr` is the family applied to its parameters, if it is.
+    fn family_indices<'a>(&self, expr: &'a Expression) -> Option<Vec<&'a Expression>> {
+        let (head, args) = split_applications(expr);
+        match head {
+            Expression::Variable(head) if *head == self.name => self.indices_of(&args),
+            _ => None,
+        }
+    }
+}
+
+/// The name of the eliminator generated for the inductive family `family`.
+///
+/// # Examples
+///
+/// ```
+/// use alpha::inductive::eliminator_name;
+/// use alpha::Identifier;
+///
+/// assert_eq!(eliminator_name(&Identifier::new("Vec")).name(), "Vec_elim");
+/// ```
+pub fn eliminator_name(family: &Identifier) -> Identifier {
+    Identifier::new(&format!("{}_elim", family))
+}
+
+/// What evaluation needs to know about a declared inductive family.
+pub(crate) struct Family {
+    pub(crate) params: usize,
+    pub(crate) indices: usize,
+    /// Each constructor with its computation rule for the eliminator,
+    /// a function of the eliminator, the parameters, the motive, the methods,
+    /// and the fields of the constructor.
+    pub(crate) constructors: Vec<(Identifier, Value)>,
+}
+
+impl Family {
+    /// The number of arguments the eliminator takes, ending with the target.
+    pub(crate) fn eliminator_arity(&self) -> usize {
+        self.params + 1 + self.constructors.len() + self.indices + 1
+    }
+}
+
+/// A sequence of binders, each of whose types may refer to those before.
+type Telescope = Vec<(Identifier, Expression)>;
+
+/// A field of a constructor, split into its binders and the indices of the family it ends in,
+/// if it is recursive.
+struct Field {
+    name: Identifier,
+    type_: Expression,
+    recursive: Option<(Telescope, Vec<Expression>)>,
+}
+
+/// A constructor split into its fields, and the indices of the element it constructs.
+pub(crate) struct Constructor {
+    name: Identifier,
+    fields: Vec<Field>,
+    indices: Vec<Expression>,
+}
+
+/// Splits pi types off `type_`, returning their binders and what remains.
+fn split_pi_types(type_: &Expression) -> (Telescope, &Expression) {
+    let mut telescope = Vec::new();
+    let mut type_ = type_.unlocated();
+    while let Expression::PiType {
+        tparam,
+        tparam_type,
+        ret_type,
+        ..
+    } = type_
+    {
+        telescope.push((tparam.clone(), (**tparam_type).clone()));
+        type_ = ret_type.unlocated();
+    }
+    (telescope, type_)
+}
+
+/// Splits applications off `expr`, returning the function and its arguments in order.
+fn split_applications(expr: &Expression) -> (&Expression, Vec<&Expression>) {
+    match expr.unlocated() {
+        Expression::Application { func, arg, .. } => {
+            let (head, mut args) = split_applications(func);
+            args.push(arg);
+            (head, args)
+        }
+        expr => (expr, Vec::new()),
+    }
+}
+
+/// Wraps `body` in pi types over `telescope`.
+pub(crate) fn pi_types(telescope: &[(Identifier, Expression)], body: Expression) -> Expression {
+    telescope
+        .iter()
+        .rev()
+        .fold(body, |ret_type, (tparam, tparam_type)| Expression::PiType {
+            tparam: tparam.clone(),
+            tparam_type: Box::new(tparam_type.clone()),
+            ret_type: Box::new(ret_type),
+            implicit: false,
+        })
+}
+
+/// Wraps `body` in lambdas over `params`.
+pub(crate) fn lambdas<'a>(
+    params: impl DoubleEndedIterator<Item = &'a Identifier>,
+    body: Expression,
+) -> Expression {
+    params
+        .rev()
+        .fold(body, |ret_val, param| Expression::Lambda {
+            param: param.clone(),
+            param_type: None,
+            ret_val: Box::new(ret_val),
+            implicit: false,
+        })
+}
+
+fn variables<'a>(ids: impl IntoIterator<Item = &'a Identifier>) -> Vec<Expression> {
+    ids.into_iter()
+        .map(|id| Expression::Variable(id.clone()))
+        .collect()
+}
+
+/// Checks that each binder of `telescope` is not one of `bound`, nor bound earlier in `telescope`.
+pub(crate) fn check_binders(
+    bound: &[&Identifier],
+    telescope: &[(Identifier, Expression)],
+) -> crate::Result<()> {
+    for (i, (id, _)) in telescope.iter().enumerate() {
+        if bound.contains(&id) || telescope[..i].iter().any(|(earlier, _)| earlier == id) {
+            return Err(TypeError::new(TypeErrorKind::ShadowedBinder(id.clone())));
+        }
+    }
+    Ok(())
+}
+
+/// Checks each type in `telescope` in the context extended by those before it,
+/// then calls `body` in the context extended by all of them.
+///
+/// With a `max_level`, each type must also be in a universe no larger than it.
+pub(crate) fn check_telescope(
+    defs: &Definitions,
+    ctx: &Context,
+    telescope: &[(Identifier, Expression)],
+    max_level: Option<&Level>,
+    body: &mut dyn FnMut(&Context) -> crate::Result<()>,
+) -> crate::Result<()> {
+    let Some(((id, type_), rest)) = telescope.split_first() else {
+        return body(ctx);
+    };
+    let (type_, level) = check_is_type(defs, ctx, type_)?;
+    if let Some(max_level) = max_level {
+        if !defs.is_type_in_type() && !level.is_at_most(max_level) {
+            return Err(TypeError::new(TypeErrorKind::LevelTooLarge {
+                found: level,
+                expected: max_level.clone(),
+            }));
+        }
+    }
+    let type_ = try_evaluate(defs, &Environment::from_context(ctx), &type_)?;
+    check_telescope(
+        defs,
+        &ctx.extend(id, &Type::create_type_from_value(type_)),
+        rest,
+        max_level,
+        body,
+    )
+}
+
+/// Checks the binders of the family and splits its arity into indices and a universe level.
+pub(crate) fn check_arity(decl: &DataDeclaration) -> crate::Result<(Telescope, Level)> {
+    check_binders(&[&decl.name], &decl.params)?;
+    let (indices, universe) = split_pi_types(&decl.arity);
+    let mut bound = vec![&decl.name];
+    bound.extend(decl.params.iter().map(|(param, _)| param));
+    check_binders(&bound, &indices)?;
+    match universe {
+        Expression::Universe(level) => Ok((indices, level.clone())),
+        _ => Err(TypeError::new(TypeErrorKind::NotAnArity(
+            decl.arity.clone(),
+        ))),
+    }
+}
+
+/// Checks a constructor once its family is defined,
+/// where the family is in the universe at `level`.
+pub(crate) fn check_constructor(
+    defs: &Definitions,
+    decl: &DataDeclaration,
+    constructor: &ConstructorDeclaration,
+    level: &Level,
+) -> crate::Result<Constructor> {
+    let (fields, conclusion) = split_pi_types(&constructor.type_);
+    let mut bound = vec![&decl.name];
+    bound.extend(decl.params.iter().map(|(param, _)| param));
+    check_binders(&bound, &fields)?;
+    let Some(indices) = decl.family_indices(conclusion) else {
+        return Err(TypeError::new(TypeErrorKind::NotAConstructorType {
+            family: decl.name.clone(),
+            type_: constructor.type_.clone(),
+        }));
+    };
+    let fields = fields
+        .into_iter()
+        .map(|(name, type_)| {
+            let recursive = match type_.has_free_variable(&decl.name) {
+                false => None,
+                true => Some(check_positive(decl, &bound, &name, &type_)?),
+            };
+            Ok(Field {
+                name,
+                type_,
+                recursive,
+            })
+        })
+        .collect::<crate::Result<Vec<_>>>()?;
+    let telescope: Telescope = fields
+        .iter()
+        .map(|field| (field.name.clone(), field.type_.clone()))
+        .collect();
+    check_telescope(defs, &Context::EMPTY, &decl.params, None, &mut |ctx| {
+        check_telescope(defs, ctx, &telescope, Some(level), &mut |ctx| {
+            check_is_type(defs, ctx, conclusion).map(|_| ())
+        })
+    })?;
+    Ok(Constructor {
+        name: constructor.name.clone(),
+        fields,
+        indices: indices.into_iter().cloned().collect(),
+    })
+}
+
+/// Checks that the family occurs strictly positively in the type of the field `name`,
+/// so that it is pi types not mentioning the family, ending in the family.
+fn check_positive(
+    decl: &DataDeclaration,
+    bound: &[&Identifier],
+    name: &Identifier,
+    type_: &Expression,
+) -> crate::Result<(Telescope, Vec<Expression>)> {
+    let (binders, conclusion) = split_pi_types(type_);
+    let mut bound = bound.to_vec();
+    bound.push(name);
+    check_binders(&bound, &binders)?;
+    let binders_free = binders
+        .iter()
+        .all(|(_, binder_type)| !binder_type.has_free_variable(&decl.name));
+    match decl.family_indices(conclusion) {
+        Some(indices) if binders_free => Ok((binders, indices.into_iter().cloned().collect())),
+        _ => Err(TypeError::new(TypeErrorKind::NotStrictlyPositive {
+            family: decl.name.clone(),
+            type_: type_.clone(),
+        })),
+    }
+}
+
+/// The type of the eliminator of a checked declaration, generic in the universe level it returns
+/// as its second component, along with what evaluation needs to know about the family.
+///
+/// For `Vec` as above, the eliminator is of type
+/// `∏(A : U)∏(C : ∏(n : Nat)∏(x : Vec A n)U.{l})∏(nil_case : C 0 (nil A))∏(cons_case : ...)∏(n : Nat)∏(x : Vec A n)C n x`.
+pub(crate) fn eliminator(
+    defs: &Definitions,
+    decl: &DataDeclaration,
+    indices: &[(Identifier, Expression)],
+    constructors: &[Constructor],
+) -> crate::Result<(Expression, Identifier, Family)> {
+    // generated binders avoid every name in the declaration, so cannot capture any of them
+    let mut used: Vec<Identifier> = defs.names().cloned().collect();
+    used.extend(decl.defined_names());
+    used.extend(decl.params.iter().map(|(param, _)| param.clone()));
+    used.extend(decl.expressions().flat_map(Expression::identifiers));
+    let mut fresh = |base: &str| {
+        let id = fresh_from(base, used.iter());
+        used.push(id.clone());
+        id
+    };
+    let level_param = Identifier::new("l");
+    let motive = fresh("C");
+    let target = fresh("x");
+    let elim = fresh("elim");
+    let methods: Vec<Identifier> = constructors
+        .iter()
+        .map(|c| fresh(&format!("{}_case", c.name)))
+        .collect();
+    let ih_names: Vec<Vec<Option<Identifier>>> = constructors
+        .iter()
+        .map(|c| {
+            c.fields
+                .iter()
+                .map(|field| {
+                    field
+                        .recursive
+                        .as_ref()
+                        .map(|_| fresh(&format!("{}_ih", field.name)))
+                })
+                .collect()
+        })
+        .collect();
+
+    let family_at = |indices: Vec<Expression>| {
+        apply_all(
+            Expression::Variable(decl.name.clone()),
+            decl.param_variables().chain(indices),
+        )
+    };
+    let motive_at = |indices: Vec<Expression>, elem: Expression| {
+        apply_all(
+            Expression::Variable(motive.clone()),
+            indices.into_iter().chain([elem]),
+        )
+    };
+    let index_variables = variables(indices.iter().map(|(index, _)| index));
+
+    let mut telescope = decl.params.clone();
+    let motive_type = pi_types(
+        indices,
+        Expression::PiType {
+            tparam: target.clone(),
+            tparam_type: Box::new(family_at(index_variables.clone())),
+            ret_type: Box::new(Expression::Universe(Level::variable(level_param.clone()))),
+            implicit: false,
+        },
+    );
+    telescope.push((motive.clone(), motive_type));
+    let mut rules = Vec::new();
+    for ((c, method), ih_names) in constructors.iter().zip(&methods).zip(&ih_names) {
+        let mut method_telescope = Vec::new();
+        let mut method_args = Vec::new();
+        for (field, ih) in c.fields.iter().zip(ih_names) {
+            method_telescope.push((field.name.clone(), field.type_.clone()));
+            method_args.push(Expression::Variable(field.name.clone()));
+            let (Some((binders, field_indices)), Some(ih)) = (&field.recursive, ih) else {
+                continue;
+            };
+            let binder_variables = variables(binders.iter().map(|(binder, _)| binder));
+            let field_at = apply_all(
+                Expression::Variable(field.name.clone()),
+                binder_variables.clone(),
+            );
+            let ih_type = pi_types(binders, motive_at(field_indices.clone(), field_at.clone()));
+            method_telescope.push((ih.clone(), ih_type));
+            let recursion = apply_all(
+                Expression::Variable(elim.clone()),
+                decl.param_variables()
+                    .chain(variables([&motive]))
+                    .chain(variables(&methods))
+                    .chain(field_indices.iter().cloned())
+                    .chain([field_at]),
+            );
+            method_args.push(lambdas(binders.iter().map(|(binder, _)| binder), recursion));
+        }
+        let constructed = apply_all(
+            Expression::Variable(c.name.clone()),
+            decl.param_variables()
+                .chain(variables(c.fields.iter().map(|field| &field.name))),
+        );
+        let method_type = pi_types(&method_telescope, motive_at(c.indices.clone(), constructed));
+        telescope.push((method.clone(), method_type));
+
+        let rule = lambdas(
+            [&elim]
+                .into_iter()
+                .chain(decl.params.iter().map(|(param, _)| param))
+                .chain([&motive])
+                .chain(&methods)
+                .chain(c.fields.iter().map(|field| &field.name)),
+            apply_all(Expression::Variable(method.clone()), method_args),
+        );
+        rules.push((
+            c.name.clone(),
+            try_evaluate(defs, &Environment::EMPTY, &rule)?,
+        ));
+    }
+    telescope.extend(indices.iter().cloned());
+    telescope.push((target.clone(), family_at(index_variables.clone())));
+    let type_ = pi_types(
+        &telescope,
+        motive_at(index_variables, Expression::Variable(target)),
+    );
+    let family = Family {
+        params: decl.params.len(),
+        indices: indices.len(),
+        constructors: rules,
+    };
+    Ok((type_, level_param, family))
+}
@@ -0,0 +1,161 @@
+use crate::Identifier;
+use std::fmt;
+
+/// A universe level, built from constants and variables by successors and maxima.
+///
+/// Levels are kept in a normal form, so that two levels are equal for every
+/// assignment of their variables exactly when they are equal as values.
+///
+/// # Examples
+///
+/// ```
+/// use alpha::{Identifier, Level};
+///
+/// let l = Level::variable(Identifier::new("l"));
+/// let one = Level::constant(1);
+/// assert_eq!(l.max(&one).to_string(), "max(l, 1)");
+/// assert_eq!(l.successor().unwrap().max(&one).to_string(), "l+1");
+/// assert_eq!(l.max(&l).max(&Level::ZERO), l);
+/// assert!(one.is_at_most(&l.successor().unwrap()));
+/// assert!(!one.is_at_most(&l));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Level {
+    // the maximum of `constant` and each variable plus its offset;
+    // variables are sorted by name and distinct, and `constant` is `0`
+    // when some offset is at least as large
+    constant: usize,
+    variables: Vec<(Identifier, usize)>,
+}
+
+impl Level {
+    /// The lowest level.
+    pub const ZERO: Level = Level {
+        constant: 0,
+        variables: Vec::new(),
+    };
+
+    /// The level `n`.
+    pub fn constant(n: usize) -> Level {
+        Level {
+            constant: n,
+            variables: Vec::new(),
+        }
+    }
+
+    /// The level variable `id`.
+    pub fn variable(id: Identifier) -> Level {
+        Level {
+            constant: 0,
+            variables: vec![(id, 0)],
+        }
+    }
+
+    /// The level `n`, if this has no variables.
+    pub fn as_constant(&self) -> Option<usize> {
+        self.variables.is_empty().then_some(self.constant)
+    }
+
+    /// The variables occurring in this level.
+    pub fn variables(&self) -> impl Iterator<Item = &Identifier> {
+        self.variables.iter().map(|(id, _)| id)
+    }
+
+    /// The level `n` above this, unless that is too large to represent.
+    pub fn add(&self, n: usize) -> Option<Level> {
+        let constant = match self.constant {
+            0 if !self.variables.is_empty() => 0,
+            constant => constant.checked_add(n)?,
+        };
+        let variables = self
+            .variables
+            .iter()
+            .map(|(id, offset)| Some((id.clone(), offset.checked_add(n)?)))
+            .collect::<Option<_>>()?;
+        Some(Level {
+            constant,
+            variables,
+        })
+    }
+
+    /// The level above this, unless that is too large to represent.
+    pub fn successor(&self) -> Option<Level> {
+        self.add(1)
+    }
+
+    /// The greater of this level and `other`.
+    pub fn max(&self, other: &Level) -> Level {
+        let mut variables = self.variables.clone();
+        for (id, offset) in &other.variables {
+            match variables.iter_mut().find(|(var, _)| var == id) {
+                Some((_, existing)) => *existing = (*existing).max(*offset),
+                None => variables.push((id.clone(), *offset)),
+            }
+        }
+        variables.sort_by(|(lhs, _), (rhs, _)| lhs.name().cmp(rhs.name()));
+        let mut constant = self.constant.max(other.constant);
+        if variables.iter().any(|&(_, offset)| offset >= constant) {
+            constant = 0;
+        }
+        Level {
+            constant,
+            variables,
+        }
+    }
+
+    /// Whether this level is at most `other`, for every assignment of variables.
+    pub fn is_at_most(&self, other: &Level) -> bool {
+        let constant_at_most = self.constant <= other.constant
+            || other
+                .variables
+                .iter()
+                .any(|&(_, offset)| self.constant <= offset);
+        constant_at_most
+            && self.variables.iter().all(|(id, offset)| {
+                other
+                    .variables
+                    .iter()
+                    .any(|(other_id, other_offset)| id == other_id && offset <= other_offset)
+            })
+    }
+
+    /// Replaces each variable with the level `subst` gives for it, if any,
+    /// unless the result is too large to represent.
+    pub fn substitute(&self, subst: impl Fn(&Identifier) -> Option<Level>) -> Option<Level> {
+        let mut out = Level::constant(self.constant);
+        for (id, offset) in &self.variables {
+            let level = match subst(id) {
+                Some(level) => level,
+                None => Level::variable(id.clone()),
+            };
+            out = out.max(&level.add(*offset)?);
+        }
+        Some(out)
+    }
+}
+
+impl From<usize> for Level {
+    fn from(n: usize) -> Self {
+        Level::constant(n)
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut terms: Vec<String> = self
+            .variables
+            .iter()
+            .map(|(id, offset)| match offset {
+                0 => id.to_string(),
+                offset => format!("{}+{}", id, offset),
+            })
+            .collect();
+        if self.constant != 0 || terms.is_empty() {
+            terms.push(self.constant.to_string());
+        }
+        match terms.as_slice() {
+            [term] => f.write_str(term),
+            terms => write!(f, "max({})", terms.join(", ")),
+        }
+    }
+}
@@ -8,14 +8,29 @@ pub mod equivalence;
 pub mod evaluation;
 pub mod expression;
 mod identifier;
+pub mod inductive;
+mod level;
 mod lists;
+pub mod meta;
 pub mod min_excluded;
+pub mod module;
+mod natural;
+pub mod parse;
+pub mod pattern;
 pub mod read_back;
+pub mod record;
+mod span;
+pub mod surface;
+mod termination;
 mod type_error;
 pub mod typing;
+pub mod unify;
 pub mod value;
 
 pub use crate::identifier::Identifier;
+pub use crate::level::Level;
+pub use crate::natural::{Natural, ParseNaturalError};
+pub use crate::span::{Location, Span};
 pub use crate::type_error::*;
 
 /// Types and functions associating information to variables.
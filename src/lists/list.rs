@@ -1,4 +1,4 @@
-use crate::{Identifier, TypeError};
+use crate::{Identifier, TypeError, TypeErrorKind};
 use std::fmt;
 
 #[derive(Default)]
@@ -8,7 +8,7 @@ pub(crate) enum LookupList<'a, T> {
     Extend {
         parent: &'a LookupList<'a, T>,
         var: &'a Identifier,
-        val: &'a T,
+        val: T,
     },
 }
 
@@ -18,7 +18,7 @@ pub struct LookupListIterator<'a, T> {
 
 impl<T> Clone for LookupListIterator<'_, T> {
     fn clone(&self) -> Self {
-        LookupListIterator { ptr: self.ptr }
+        *self
     }
 }
 
@@ -28,7 +28,7 @@ impl<'a, T> Iterator for LookupListIterator<'a, T> {
     type Item = (&'a Identifier, &'a T);
 
     fn next(&mut self) -> Option<Self::Item> {
-        match *self.ptr {
+        match self.ptr {
             LookupList::Empty => None,
             LookupList::Extend { parent, var, val } => {
                 self.ptr = parent;
@@ -68,7 +68,7 @@ fn lookup_list_fmt_helper<T: fmt::Display>(
 
 impl<T: fmt::Display> fmt::Display for LookupList<'_, T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
+        match self {
             LookupList::Empty => write!(f, "\u{00B7}"),
             LookupList::Extend { parent, var, val } => {
                 lookup_list_fmt_helper(parent, f)?;
@@ -79,7 +79,7 @@ impl<T: fmt::Display> fmt::Display for LookupList<'_, T> {
 }
 
 impl<'a, T> LookupList<'a, T> {
-    pub(super) fn extend_list(&'a self, var: &'a Identifier, val: &'a T) -> LookupList<'a, T> {
+    pub(super) fn extend_list(&'a self, var: &'a Identifier, val: T) -> LookupList<'a, T> {
         LookupList::Extend {
             parent: self,
             var,
@@ -92,8 +92,6 @@ impl<'a, T> LookupList<'a, T> {
     pub(super) fn get(&'a self, id: &Identifier) -> crate::Result<&'a T> {
         self.into_iter()
             .find_map(|(var, val)| if var == id { Some(val) } else { None })
-            .ok_or_else(|| TypeError {
-                msg: format!("Variable `{}` not found.", id),
-            })
+            .ok_or_else(|| TypeError::new(TypeErrorKind::UnboundVariable(id.clone())))
     }
 }
@@ -1,12 +1,21 @@
+use crate::dictionaries::check_level_count;
 use crate::environment::Definitions;
 use crate::lists::list::{LookupList, LookupListIterator};
-use crate::value::Type;
-use crate::Identifier;
+use crate::value::{Type, Value};
+use crate::{Identifier, Level};
 
 mod list;
 
+/// What a [`Context`] records about a variable.
+#[derive(Clone, Copy)]
+pub(crate) struct Binding<'a> {
+    type_: &'a Type,
+    /// The value of the variable, if it was bound by a `let`.
+    value: Option<&'a Value>,
+}
+
 /// A typing context.
-pub struct Context<'a>(LookupList<'a, Type>);
+pub struct Context<'a>(LookupList<'a, Binding<'a>>);
 
 impl<'a> Context<'a> {
     /// The empty context.
@@ -14,7 +23,24 @@ impl<'a> Context<'a> {
 
     /// The context extended with a new variable.
     pub fn extend(&'a self, var: &'a Identifier, val: &'a Type) -> Context<'a> {
-        Context(self.0.extend_list(var, val))
+        Context(self.0.extend_list(
+            var,
+            Binding {
+                type_: val,
+                value: None,
+            },
+        ))
+    }
+
+    /// The context extended with a new variable, defined to have a given [`Value`].
+    pub fn define(&'a self, var: &'a Identifier, type_: &'a Type, val: &'a Value) -> Context<'a> {
+        Context(self.0.extend_list(
+            var,
+            Binding {
+                type_,
+                value: Some(val),
+            },
+        ))
     }
 
     /// An iterator over the names of variables in the context.
@@ -23,7 +49,19 @@ impl<'a> Context<'a> {
     }
 
     fn lookup_type(&self, id: &Identifier) -> crate::Result<&Type> {
-        self.0.get(id)
+        self.0.get(id).map(|binding| binding.type_)
+    }
+
+    /// Determines the [`Value`] of a variable, if it was defined rather than assumed.
+    pub(crate) fn lookup_value(&self, id: &Identifier) -> crate::Result<Option<&Value>> {
+        self.0.get(id).map(|binding| binding.value)
+    }
+
+    /// An iterator over the variables in the context and their values, if defined.
+    pub(crate) fn values(&self) -> impl Iterator<Item = (&Identifier, Option<&Value>)> {
+        self.0
+            .into_iter()
+            .map(|(var, binding)| (var, binding.value))
     }
 
     /// Checks whether a variable is in the context.
@@ -32,12 +70,29 @@ impl<'a> Context<'a> {
     }
 }
 
+/// An iterator over the variables in a [`Context`] and their [`Type`]s, most recent first.
+#[derive(Clone, Copy)]
+pub struct ContextIterator<'a>(LookupListIterator<'a, Binding<'a>>);
+
+impl<'a> Iterator for ContextIterator<'a> {
+    type Item = (&'a Identifier, &'a Type);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(var, binding)| (var, binding.type_))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ContextIterator<'_> {}
+
 impl<'a> IntoIterator for &'a Context<'a> {
     type Item = (&'a Identifier, &'a Type);
-    type IntoIter = LookupListIterator<'a, Type>;
+    type IntoIter = ContextIterator<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        ContextIterator(self.0.into_iter())
     }
 }
 
@@ -54,16 +109,22 @@ impl Names<'_> {
         LookupList::Extend {
             parent: self,
             var,
-            val: &(),
+            val: (),
         }
     }
 }
 
 /// Determines the [`Type`] of a variable.
-pub fn type_var<'a>(
-    defs: &'a Definitions,
-    ctx: &'a Context,
+///
+/// A global definition which is polymorphic in universe levels is instantiated at `levels`.
+pub fn type_var(
+    defs: &Definitions,
+    ctx: &Context,
     var: &Identifier,
-) -> crate::Result<&'a Type> {
-    ctx.lookup_type(var).or_else(|_| defs.lookup_type(var))
+    levels: &[Level],
+) -> crate::Result<Type> {
+    match ctx.lookup_type(var) {
+        Ok(type_) => check_level_count(var, 0, levels).map(|()| type_.clone()),
+        Err(_) => defs.instantiate_type(var, levels),
+    }
 }
@@ -0,0 +1,714 @@
+//! Metavariables, standing for terms not yet known, and the goals which holes leave open.
+
+use crate::environment::{Context, Definitions, Environment};
+use crate::evaluation::{
+    try_absurd, try_apply, try_evaluate, try_first, try_if, try_j, try_natrec, try_second,
+};
+use crate::expression::Expression;
+use crate::identifier::fresh_from;
+use crate::inductive::{lambdas, pi_types};
+use crate::pattern::try_match;
+use crate::read_back::{try_read_back_type, try_read_back_typed};
+use crate::record::try_project;
+use crate::typing::apply_all;
+use crate::value::{Neutral, Type, Value};
+use crate::{Identifier, Level, Span, TypeError, TypeErrorKind};
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The name of a metavariable.
+///
+/// Each is created fresh, so metavariables are distinct across all [`Definitions`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MetaVariable(usize);
+
+impl MetaVariable {
+    /// Creates a metavariable distinct from every other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use alpha::meta::MetaVariable;
+    ///
+    /// assert!(MetaVariable::fresh() != MetaVariable::fresh());
+    /// ```
+    pub fn fresh() -> MetaVariable {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        MetaVariable(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for MetaVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "?{}", self.0)
+    }
+}
+
+/// A variable in scope where a hole occurs.
+#[derive(Clone, Debug)]
+pub struct Hypothesis {
+    pub name: Identifier,
+    pub type_: Expression,
+    /// The value of the variable, if it was bound by a `let`.
+    pub value: Option<Expression>,
+}
+
+/// A [`Hole`](Expression::Hole) yet to be filled, with what is known where it occurs.
+#[derive(Clone, Debug)]
+pub struct Goal {
+    pub meta: MetaVariable,
+    /// The variables in scope, outermost first.
+    pub context: Vec<Hypothesis>,
+    /// The type the hole is checked against.
+    pub type_: Expression,
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = self.span {
+            write!(f, "{}: ", span)?;
+        }
+        writeln!(f, "Goal {} : {}", self.meta, self.type_)?;
+        for hypothesis in &self.context {
+            write!(f, "  {} : {}", hypothesis.name, hypothesis.type_)?;
+            if let Some(value) = &hypothesis.value {
+                write!(f, " = {}", value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Why a metavariable was made.
+#[derive(Clone)]
+pub enum Origin {
+    /// A hole, which leaves a goal open.
+    Hole(Goal),
+    /// An implicit argument, inserted for the parameter `binder` of `func`.
+    Implicit {
+        binder: Identifier,
+        func: Expression,
+    },
+    /// The type of the parameter of `lambda`, which was not given, or a part of it.
+    Parameter { lambda: Expression },
+    /// A call to [`fresh_meta`], for unification to solve.
+    Fresh,
+}
+
+impl Origin {
+    /// Whether a metavariable made for this stands for a type which was not given,
+    /// so is in the universe of whichever type it is solved to.
+    pub(crate) fn infers_universe(&self) -> bool {
+        matches!(self, Origin::Parameter { .. })
+    }
+}
+
+/// What is known about a metavariable.
+#[derive(Clone)]
+pub struct Meta {
+    /// The variables in scope where it was created, outermost first,
+    /// to whose values it is applied where it occurs.
+    pub context: Vec<Identifier>,
+    /// Its type, as a function of the variables of `context`.
+    pub type_: Type,
+    pub origin: Origin,
+    /// A closed term it stands for, once unification finds one.
+    pub solution: Option<Expression>,
+}
+
+/// An equation between values which unification could not yet solve,
+/// because it does not yet determine the metavariable it is stuck on.
+#[derive(Clone)]
+pub struct Constraint {
+    /// Numbers the constraints in the order they were first postponed.
+    pub(crate) id: usize,
+    /// The variables in scope, outermost first, with their types,
+    /// and their values if they were bound by a `let`.
+    pub(crate) context: Vec<(Identifier, Type, Option<Value>)>,
+    pub lhs: Value,
+    pub rhs: Value,
+    /// The type of both sides, or `None` if they are types.
+    pub type_: Option<Type>,
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.lhs, self.rhs)
+    }
+}
+
+/// How many metavariables and postponed constraints there were at some point,
+/// to roll back to with [`truncate`](MetaContext::truncate).
+#[derive(Clone, Copy)]
+pub(crate) struct Mark {
+    pub(crate) metas: usize,
+    pub(crate) constraints: usize,
+}
+
+/// The metavariables created while checking, in order,
+/// and the constraints on them which unification postponed.
+#[derive(Default)]
+pub struct MetaContext {
+    entries: Vec<(MetaVariable, Meta)>,
+    postponed: Vec<Constraint>,
+    /// The number of the next constraint to be postponed.
+    next_constraint: usize,
+    /// The number of the constraint being tried again, if one is.
+    retrying: Option<usize>,
+}
+
+impl MetaContext {
+    /// Finds what is known about `meta`.
+    pub fn get(&self, meta: MetaVariable) -> crate::Result<&Meta> {
+        self.entries
+            .iter()
+            .find(|(other, _)| *other == meta)
+            .map(|(_, entry)| entry)
+            .ok_or_else(|| {
+                TypeError::new(TypeErrorKind::Internal(format!(
+                    "Metavariable `{}` was never checked.",
+                    meta
+                )))
+            })
+    }
+
+    /// An iterator over the metavariables, in the order they were created.
+    pub fn iter(&self) -> impl Iterator<Item = (MetaVariable, &Meta)> {
+        self.entries.iter().map(|(meta, entry)| (*meta, entry))
+    }
+
+    /// An iterator over the constraints waiting for a metavariable to be solved.
+    pub fn postponed(&self) -> impl Iterator<Item = &Constraint> {
+        self.postponed.iter()
+    }
+
+    /// Records `entry` for `meta`, replacing what was known of it if it was checked before.
+    pub(crate) fn insert(&mut self, meta: MetaVariable, entry: Meta) {
+        self.entries.retain(|(other, _)| *other != meta);
+        self.entries.push((meta, entry));
+    }
+
+    /// Records that `meta` stands for the closed term `solution`.
+    pub(crate) fn solve(&mut self, meta: MetaVariable, solution: Expression) {
+        if let Some((_, entry)) = self.entries.iter_mut().find(|(other, _)| *other == meta) {
+            entry.solution = Some(solution);
+        }
+    }
+
+    /// Records that `meta` is of the closed type `type_`, as found since it was created.
+    pub(crate) fn retype(&mut self, meta: MetaVariable, type_: Type) {
+        if let Some((_, entry)) = self.entries.iter_mut().find(|(other, _)| *other == meta) {
+            entry.type_ = type_;
+        }
+    }
+
+    /// Records `constraint` to be tried again once more metavariables are solved,
+    /// numbered as the constraint being tried again if it is part of it.
+    pub(crate) fn postpone(&mut self, mut constraint: Constraint) {
+        constraint.id = match self.retrying {
+            Some(id) => id,
+            None => {
+                self.next_constraint += 1;
+                self.next_constraint - 1
+            }
+        };
+        self.postponed.push(constraint);
+    }
+
+    /// Records `constraint` as postponed again, keeping its number.
+    pub(crate) fn restore(&mut self, constraint: Constraint) {
+        self.postponed.push(constraint);
+    }
+
+    /// Removes the postponed constraints, to be tried again.
+    pub(crate) fn take_postponed(&mut self) -> Vec<Constraint> {
+        std::mem::take(&mut self.postponed)
+    }
+
+    /// Sets the number of the constraint being tried again, and gives the one it replaces.
+    pub(crate) fn retry_as(&mut self, id: Option<usize>) -> Option<usize> {
+        std::mem::replace(&mut self.retrying, id)
+    }
+
+    pub(crate) fn mark(&self) -> Mark {
+        Mark {
+            metas: self.entries.len(),
+            constraints: self.next_constraint,
+        }
+    }
+
+    /// Forgets the metavariables and postponed constraints recorded after `mark`.
+    pub(crate) fn truncate(&mut self, mark: Mark) {
+        self.entries.truncate(mark.metas);
+        self.postponed
+            .retain(|constraint| constraint.id < mark.constraints);
+    }
+
+    /// Leaves the variables `hidden` out of the contexts of the goals recorded since `mark`,
+    /// where they are defined, so stand for their values.
+    pub(crate) fn hide_definitions(&mut self, mark: Mark, hidden: &[Identifier]) {
+        for (_, entry) in self.entries.iter_mut().skip(mark.metas) {
+            if let Origin::Hole(goal) = &mut entry.origin {
+                goal.context.retain(|hypothesis| {
+                    hypothesis.value.is_none() || !hidden.contains(&hypothesis.name)
+                });
+            }
+        }
+    }
+
+    /// The goals left open, in the order their holes were checked.
+    pub(crate) fn goals(&self) -> impl Iterator<Item = &Goal> {
+        self.entries
+            .iter()
+            .filter_map(|(_, entry)| match &entry.origin {
+                Origin::Hole(goal) => Some(goal),
+                Origin::Implicit { .. } | Origin::Parameter { .. } | Origin::Fresh => None,
+            })
+    }
+}
+
+/// The variables of `ctx` which may be mentioned where it is innermost, outermost first,
+/// leaving out those bound by a `let` unless `with_definitions`.
+fn hypotheses(
+    defs: &Definitions,
+    ctx: &Context,
+    with_definitions: bool,
+) -> crate::Result<Vec<Hypothesis>> {
+    let mut seen: Vec<&Identifier> = Vec::new();
+    let mut context = Vec::new();
+    for ((name, var_type), (_, value)) in ctx.into_iter().zip(ctx.values()) {
+        // a shadowed variable cannot be mentioned
+        if seen.contains(&name) {
+            continue;
+        }
+        seen.push(name);
+        if value.is_some() && !with_definitions {
+            continue;
+        }
+        let value = value
+            .map(|value| try_read_back_typed(defs, ctx, value, var_type))
+            .transpose()?;
+        context.push(Hypothesis {
+            name: name.clone(),
+            type_: try_read_back_type(defs, ctx, var_type)?,
+            value,
+        });
+    }
+    context.reverse();
+    Ok(context)
+}
+
+/// The variables of `context` with their types, as a telescope.
+fn telescope(context: &[Hypothesis]) -> Vec<(Identifier, Expression)> {
+    context
+        .iter()
+        .map(|hypothesis| (hypothesis.name.clone(), hypothesis.type_.clone()))
+        .collect()
+}
+
+/// The closed type of a metavariable over `telescope` for a term of type `type_`.
+fn meta_type(
+    defs: &Definitions,
+    telescope: &[(Identifier, Expression)],
+    type_: Expression,
+) -> crate::Result<Type> {
+    // the variables of the telescope are free in the types read back,
+    // so are bound again by the type of the metavariable
+    let meta_type = pi_types(telescope, type_);
+    try_evaluate(defs, &Environment::EMPTY, &meta_type).map(Type::create_type_from_value)
+}
+
+/// Records the hole `meta`, checked against `type_` in `ctx` at `span`, as an open goal.
+pub(crate) fn record_goal(
+    defs: &Definitions,
+    ctx: &Context,
+    meta: MetaVariable,
+    type_: &Type,
+    span: Option<Span>,
+) -> crate::Result<()> {
+    let context = hypotheses(defs, ctx, true)?;
+    let goal_type = try_read_back_type(defs, ctx, type_)?;
+    let entry = Meta {
+        context: context
+            .iter()
+            .map(|hypothesis| hypothesis.name.clone())
+            .collect(),
+        type_: meta_type(defs, &telescope(&context), goal_type.clone())?,
+        origin: Origin::Hole(Goal {
+            meta,
+            context,
+            type_: goal_type,
+            span,
+        }),
+        solution: None,
+    };
+    defs.metas_mut().insert(meta, entry);
+    Ok(())
+}
+
+/// Fills in the implicit arguments solved since `goal` was recorded.
+pub(crate) fn zonk_goal(defs: &Definitions, goal: &Goal) -> Goal {
+    let zonked = |expr: &Expression| zonk(defs, expr).unwrap_or_else(|_| expr.clone());
+    Goal {
+        meta: goal.meta,
+        context: goal
+            .context
+            .iter()
+            .map(|hypothesis| Hypothesis {
+                name: hypothesis.name.clone(),
+                type_: zonked(&hypothesis.type_),
+                value: hypothesis.value.as_ref().map(zonked),
+            })
+            .collect(),
+        type_: zonked(&goal.type_),
+        span: goal.span,
+    }
+}
+
+/// Creates a metavariable of type `type_` in `ctx`, for unification to solve,
+/// and gives it applied to the variables in scope.
+pub fn fresh_meta(defs: &Definitions, ctx: &Context, type_: &Type) -> crate::Result<Expression> {
+    new_meta(defs, ctx, type_, Origin::Fresh)
+}
+
+/// Creates a metavariable for the implicit parameter `binder` of `func`, of type `type_` in `ctx`,
+/// and gives it applied to the variables in scope.
+pub(crate) fn implicit_argument(
+    defs: &Definitions,
+    ctx: &Context,
+    type_: &Type,
+    binder: &Identifier,
+    func: &Expression,
+) -> crate::Result<Expression> {
+    let origin = Origin::Implicit {
+        binder: binder.clone(),
+        func: func.unlocated().clone(),
+    };
+    new_meta(defs, ctx, type_, origin)
+}
+
+/// Creates a metavariable for the type of the parameter of `lambda` in `ctx`,
+/// and gives it applied to the variables in scope.
+///
+/// It is in the lowest universe until it is solved, and then in that of its solution.
+pub(crate) fn parameter_type(
+    defs: &Definitions,
+    ctx: &Context,
+    lambda: &Expression,
+) -> crate::Result<Expression> {
+    let origin = Origin::Parameter {
+        lambda: lambda.unlocated().clone(),
+    };
+    new_meta(defs, ctx, &Type::universe(0), origin)
+}
+
+/// Creates a metavariable of type `type_` in `ctx` made for `origin`,
+/// and gives it applied to the variables in scope.
+fn new_meta(
+    defs: &Definitions,
+    ctx: &Context,
+    type_: &Type,
+    origin: Origin,
+) -> crate::Result<Expression> {
+    // a variable bound by a `let` stands for its value, so need not be abstracted over
+    let context = hypotheses(defs, ctx, false)?;
+    let type_ = try_read_back_type(defs, ctx, type_)?;
+    insert_meta(defs, &telescope(&context), type_, origin)
+}
+
+/// Creates a metavariable over the variables of `telescope` of type `type_` made for `origin`,
+/// and gives it applied to those variables.
+fn insert_meta(
+    defs: &Definitions,
+    telescope: &[(Identifier, Expression)],
+    type_: Expression,
+    origin: Origin,
+) -> crate::Result<Expression> {
+    let meta = MetaVariable::fresh();
+    let entry = Meta {
+        context: telescope.iter().map(|(var, _)| var.clone()).collect(),
+        type_: meta_type(defs, telescope, type_)?,
+        origin,
+        solution: None,
+    };
+    let args: Vec<Expression> = entry
+        .context
+        .iter()
+        .cloned()
+        .map(Expression::Variable)
+        .collect();
+    defs.metas_mut().insert(meta, entry);
+    Ok(apply_all(Expression::Meta(meta), args))
+}
+
+/// Makes a type by `former` for `meta`, an unsolved metavariable for a type,
+/// out of a fresh metavariable for a domain and one for a codomain over `param` in the domain,
+/// and gives it as a function of the variables `meta` is over.
+pub(crate) fn refinement(
+    defs: &Definitions,
+    meta: MetaVariable,
+    former: impl FnOnce(Expression, Identifier, Expression) -> Expression,
+) -> crate::Result<Expression> {
+    let (mut telescope, universe) = meta_telescope(defs, meta)?;
+    // the parts are made for what `meta` was, so are reported as it is if they are not solved,
+    // and are only as fixed in their universe as it is
+    let origin = match &defs.metas().get(meta)?.origin {
+        Origin::Hole(_) => Origin::Fresh,
+        origin => origin.clone(),
+    };
+    let vars: Vec<Identifier> = telescope.iter().map(|(var, _)| var.clone()).collect();
+    let domain = insert_meta(defs, &telescope, universe.clone(), origin.clone())?;
+    let param = fresh_from("x", defs.names().chain(&vars));
+    telescope.push((param.clone(), domain.clone()));
+    let codomain = insert_meta(defs, &telescope, universe, origin)?;
+    Ok(lambdas(vars.iter(), former(domain, param, codomain)))
+}
+
+/// Moves `meta`, a metavariable for a type, into the universe at `level`.
+pub(crate) fn move_to_universe(
+    defs: &Definitions,
+    meta: MetaVariable,
+    level: Level,
+) -> crate::Result<()> {
+    let (telescope, _) = meta_telescope(defs, meta)?;
+    let type_ = meta_type(defs, &telescope, Expression::Universe(level))?;
+    defs.metas_mut().retype(meta, type_);
+    Ok(())
+}
+
+/// The variables `meta` is over with their types, and the type of `meta` applied to them.
+fn meta_telescope(
+    defs: &Definitions,
+    meta: MetaVariable,
+) -> crate::Result<(Vec<(Identifier, Expression)>, Expression)> {
+    let entry = defs.metas().get(meta)?.clone();
+    let mut telescope = Vec::new();
+    let mut type_ = try_read_back_type(defs, &Context::EMPTY, &entry.type_)?;
+    while telescope.len() < entry.context.len() {
+        let Expression::PiType {
+            tparam,
+            tparam_type,
+            ret_type,
+            ..
+        } = type_
+        else {
+            return Err(TypeError::new(TypeErrorKind::Internal(format!(
+                "Metavariable `{}` is not over the variables it is applied to.",
+                meta
+            ))));
+        };
+        telescope.push((tparam, *tparam_type));
+        type_ = *ret_type;
+    }
+    Ok((telescope, type_))
+}
+
+/// The type of `meta` applied to `args`.
+pub(crate) fn spine_type(
+    defs: &Definitions,
+    meta: MetaVariable,
+    args: &[Value],
+) -> crate::Result<Type> {
+    let mut type_ = defs.metas().get(meta)?.type_.clone();
+    for arg in args {
+        let Value::PiType { tclosure, .. } = type_.deref() else {
+            return Err(TypeError::new(TypeErrorKind::Internal(format!(
+                "Cannot apply `{}` to `{}` because it is ill-typed.",
+                meta, arg
+            ))));
+        };
+        type_ = Type::create_type_from_value(tclosure.try_call(defs, arg)?);
+    }
+    Ok(type_)
+}
+
+/// Computes `val` further, where it is stuck on a metavariable which has since been solved.
+pub(crate) fn force<'a>(defs: &Definitions, val: &'a Value) -> crate::Result<Cow<'a, Value>> {
+    let Value::Neutral { neu } = val else {
+        return Ok(Cow::Borrowed(val));
+    };
+    match force_neutral(defs, neu)? {
+        Some(val) => Ok(Cow::Owned(force(defs, &val)?.into_owned())),
+        None => Ok(Cow::Borrowed(val)),
+    }
+}
+
+/// Computes the [`Type`] `type_` further, as [`force`] does.
+pub(crate) fn force_type<'a>(defs: &Definitions, type_: &'a Type) -> crate::Result<Cow<'a, Type>> {
+    Ok(match force(defs, type_)? {
+        Cow::Borrowed(_) => Cow::Borrowed(type_),
+        Cow::Owned(val) => Cow::Owned(Type::create_type_from_value(val)),
+    })
+}
+
+/// Computes `neu` again if it is stuck on a solved metavariable, or is `None` if it is not.
+fn force_neutral(defs: &Definitions, neu: &Neutral) -> crate::Result<Option<Value>> {
+    Ok(match neu {
+        Neutral::Variable(_) | Neutral::Instance { .. } => None,
+        Neutral::Meta { meta, args } => {
+            let solution = defs.metas().get(*meta)?.solution.clone();
+            let Some(solution) = solution else {
+                return Ok(None);
+            };
+            let mut val = try_evaluate(defs, &Environment::EMPTY, &solution)?;
+            for arg in args {
+                val = try_apply(defs, &val, arg)?;
+            }
+            Some(val)
+        }
+        Neutral::Application { func, arg } => force_neutral(defs, func)?
+            .map(|func| try_apply(defs, &func, arg))
+            .transpose()?,
+        Neutral::First { pair } => force_neutral(defs, pair)?
+            .map(|pair| try_first(&pair))
+            .transpose()?,
+        Neutral::Second { pair } => force_neutral(defs, pair)?
+            .map(|pair| try_second(&pair))
+            .transpose()?,
+        Neutral::J { motive, base, eq } => force_neutral(defs, eq)?
+            .map(|eq| try_j(defs, motive, base, &eq))
+            .transpose()?,
+        Neutral::NatRec {
+            motive,
+            base,
+            step,
+            target,
+        } => force_neutral(defs, target)?
+            .map(|target| try_natrec(defs, motive, base, step, &target))
+            .transpose()?,
+        Neutral::If {
+            motive,
+            on_true,
+            on_false,
+            target,
+        } => force_neutral(defs, target)?
+            .map(|target| try_if(motive, on_true, on_false, &target))
+            .transpose()?,
+        Neutral::Absurd { motive, target } => force_neutral(defs, target)?
+            .map(|target| try_absurd(motive, &target))
+            .transpose()?,
+        Neutral::Eliminator {
+            family,
+            levels,
+            args,
+            target,
+        } => {
+            let elim = Value::Eliminator {
+                family: family.clone(),
+                levels: levels.clone(),
+                args: args.clone(),
+            };
+            force_neutral(defs, target)?
+                .map(|target| try_apply(defs, &elim, &target))
+                .transpose()?
+        }
+        Neutral::Projection {
+            record,
+            field,
+            args,
+            target,
+        } => force_neutral(defs, target)?
+            .map(|target| try_project(defs, record, field, args, &target))
+            .transpose()?,
+        // the function is stuck on whichever argument it must match next,
+        // which may be nested in a pattern, as in `f (succ ?n)`
+        Neutral::Function { name, args } => force_patterns(defs, args)?
+            .map(|args| try_match(defs, name, &args))
+            .transpose()?,
+    })
+}
+
+/// `vals` with the solved metavariables in them forced wherever a pattern may look,
+/// or `None` if there are none.
+fn force_patterns(defs: &Definitions, vals: &[Value]) -> crate::Result<Option<Vec<Value>>> {
+    let forced: Vec<Option<Value>> = vals
+        .iter()
+        .map(|val| force_pattern(defs, val))
+        .collect::<crate::Result<_>>()?;
+    if forced.iter().all(Option::is_none) {
+        return Ok(None);
+    }
+    Ok(Some(
+        vals.iter()
+            .zip(forced)
+            .map(|(val, forced)| forced.unwrap_or_else(|| val.clone()))
+            .collect(),
+    ))
+}
+
+/// `val` with the solved metavariables in it forced wherever a pattern may look,
+/// or `None` if there are none.
+fn force_pattern(defs: &Definitions, val: &Value) -> crate::Result<Option<Value>> {
+    Ok(match val {
+        Value::Neutral { neu } => match force_neutral(defs, neu)? {
+            Some(forced) => Some(force_pattern(defs, &forced)?.unwrap_or(forced)),
+            None => None,
+        },
+        Value::Succ { pred } => force_pattern(defs, pred)?.map(|pred| Value::Succ {
+            pred: Box::new(pred),
+        }),
+        Value::Refl { elem } => force_pattern(defs, elem)?.map(|elem| Value::Refl {
+            elem: Box::new(elem),
+        }),
+        Value::Pair { fst, snd } => match (force_pattern(defs, fst)?, force_pattern(defs, snd)?) {
+            (None, None) => None,
+            (forced_fst, forced_snd) => Some(Value::Pair {
+                fst: Box::new(forced_fst.unwrap_or_else(|| (**fst).clone())),
+                snd: Box::new(forced_snd.unwrap_or_else(|| (**snd).clone())),
+            }),
+        },
+        Value::Constructor { name, args } => {
+            force_patterns(defs, args)?.map(|args| Value::Constructor {
+                name: name.clone(),
+                args,
+            })
+        }
+        Value::Record { fields } => {
+            force_patterns(defs, fields)?.map(|fields| Value::Record { fields })
+        }
+        _ => None,
+    })
+}
+
+/// Replaces each solved metavariable in `expr` by its solution.
+pub(crate) fn zonk(defs: &Definitions, expr: &Expression) -> crate::Result<Expression> {
+    match expr {
+        Expression::Meta(meta) => {
+            let solution = defs.metas().get(*meta)?.solution.clone();
+            match solution {
+                Some(solution) => zonk(defs, &solution),
+                None => Ok(expr.clone()),
+            }
+        }
+        Expression::Application {
+            func,
+            arg,
+            implicit,
+        } => {
+            let func = zonk(defs, func)?;
+            let arg = zonk(defs, arg)?;
+            match (func, arg) {
+                // a solution applied to the variables it abstracts over is its body
+                (
+                    Expression::Lambda {
+                        param,
+                        param_type: None,
+                        ret_val,
+                        ..
+                    },
+                    Expression::Variable(var),
+                ) if param == var => Ok(*ret_val),
+                (func, arg) => Ok(Expression::Application {
+                    func: Box::new(func),
+                    arg: Box::new(arg),
+                    implicit: *implicit,
+                }),
+            }
+        }
+        _ => expr.try_map_subexpressions(|expr| zonk(defs, expr)),
+    }
+}
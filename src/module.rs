@@ -0,0 +1,201 @@
+//! Loading source files of top-level declarations into [`Definitions`].
+//!
+//! A source file is a sequence of declarations, each beginning with a keyword at the start of a line
+//! and continuing over the indented lines after it:
+//!
+//! | Declaration | Syntax                                 |
+//! |-------------|----------------------------------------|
+//! | definition  | `def x : A = a`, `def x.{l, k} : A = a` |
+//! | postulate   | `postulate x : A`, `postulate x.{l} : A` |
+//! | check       | `check a : A`, `check a`               |
+//! | evaluation  | `eval a`                               |
+//!
+//! The expressions in them are written as for [`parse`](crate::parse::parse).
+//! Declarations are checked in order, so each may refer to the names declared before it.
+
+use crate::environment::{Context, Definitions, Environment};
+use crate::evaluation::try_evaluate;
+use crate::expression::Expression;
+use crate::parse::{parse_module, ParseError};
+use crate::read_back::{try_read_back_type, try_read_back_typed};
+use crate::typing::{check_is_type, elaborate, elaborate_synth};
+use crate::value::Type;
+use crate::{Identifier, Span, TypeError};
+use std::fmt;
+
+/// A top-level declaration of a source file.
+#[derive(Clone, Debug)]
+pub enum Declaration {
+    /// Defines `name` to be `value` of type `type_`, generically in the universe levels `level_params`.
+    Define {
+        name: Identifier,
+        level_params: Vec<Identifier>,
+        type_: Expression,
+        value: Expression,
+    },
+    /// Assumes `name` to be of type `type_`, generically in the universe levels `level_params`.
+    Postulate {
+        name: Identifier,
+        level_params: Vec<Identifier>,
+        type_: Expression,
+    },
+    /// Checks `expr` against `type_`, or synthesizes its type if there is none.
+    Check {
+        expr: Expression,
+        type_: Option<Expression>,
+    },
+    /// Evaluates `expr` to its normal form.
+    Eval { expr: Expression },
+}
+
+/// What a declaration gives once it is checked.
+#[derive(Clone, Debug)]
+pub enum Output {
+    Defined(Identifier),
+    Postulated(Identifier),
+    /// The expression of a [`Check`](Declaration::Check) elaborated, and its type.
+    Checked {
+        expr: Expression,
+        type_: Expression,
+    },
+    /// The normal form of the expression of an [`Eval`](Declaration::Eval), and its type.
+    Evaluated {
+        value: Expression,
+        type_: Expression,
+    },
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Defined(name) => write!(f, "Defined `{}`.", name),
+            Output::Postulated(name) => write!(f, "Postulated `{}`.", name),
+            Output::Checked { expr, type_ } => write!(f, "{} : {}", expr, type_),
+            Output::Evaluated { value, type_ } => write!(f, "{} : {}", value, type_),
+        }
+    }
+}
+
+/// The error type for loading a declaration.
+#[derive(Debug)]
+pub enum LoadError {
+    Parse(ParseError),
+    Type(TypeError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Parse(e) => e.fmt(f),
+            LoadError::Type(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// What loading a declaration gave, with the span of source text of the declaration.
+#[derive(Debug)]
+pub struct Outcome {
+    pub span: Span,
+    pub result: Result<Output, LoadError>,
+}
+
+/// Loads the declarations of a source file into `defs`, in order.
+///
+/// A declaration that fails to parse or check is reported, and loading continues with the next.
+/// Those after it that refer to a name it would have declared fail in turn.
+/// Each type error is located at least at the declaration it occurred in.
+///
+/// # Examples
+///
+/// ```
+/// use alpha::environment::Definitions;
+/// use alpha::module::load;
+///
+/// let source = r"
+/// def id.{l} : (A : U.{l}) → A → A = λ A x. x
+/// def three : Nat = id.{0} Nat true
+/// def two : Nat = id.{0} Nat 2
+/// check three
+/// eval id.{0} Nat (succ two)
+/// ";
+/// let mut defs = Definitions::new();
+/// let outcomes = load(&mut defs, source);
+/// let reports: Vec<String> = outcomes
+///     .iter()
+///     .map(|outcome| match &outcome.result {
+///         Ok(output) => output.to_string(),
+///         Err(e) => e.to_string(),
+///     })
+///     .collect();
+/// assert_eq!(reports[0], "Defined `id`.");
+/// assert!(reports[1].starts_with("3:30-3:34: "));
+/// assert_eq!(reports[2], "Defined `two`.");
+/// assert!(reports[3].starts_with("5:7-5:12: "));
+/// assert_eq!(reports[4], "3 : Nat");
+/// ```
+pub fn load(defs: &mut Definitions, source: &str) -> Vec<Outcome> {
+    parse_module(source)
+        .into_iter()
+        .map(|(span, declaration)| {
+            let result = match declaration {
+                Ok(declaration) => declare(defs, &declaration).map_err(|e| match e.span {
+                    Some(_) => LoadError::Type(e),
+                    None => LoadError::Type(e.located(span)),
+                }),
+                Err(e) => Err(LoadError::Parse(e)),
+            };
+            Outcome { span, result }
+        })
+        .collect()
+}
+
+/// Checks a [`Declaration`] into `defs`, which is unchanged if it fails.
+pub fn declare(defs: &mut Definitions, declaration: &Declaration) -> crate::Result<Output> {
+    match declaration {
+        Declaration::Define {
+            name,
+            level_params,
+            type_,
+            value,
+        } => {
+            defs.define_polymorphic(name.clone(), level_params, value, type_)?;
+            Ok(Output::Defined(name.clone()))
+        }
+        Declaration::Postulate {
+            name,
+            level_params,
+            type_,
+        } => {
+            defs.postulate_polymorphic(name.clone(), level_params, type_)?;
+            Ok(Output::Postulated(name.clone()))
+        }
+        Declaration::Check {
+            expr,
+            type_: Some(type_expr),
+        } => {
+            let (type_expr, _) = check_is_type(defs, &Context::EMPTY, type_expr)?;
+            let type_ = try_evaluate(defs, &Environment::EMPTY, &type_expr)?;
+            let type_ = Type::create_type_from_value(type_);
+            let expr = elaborate(defs, &Context::EMPTY, expr, &type_)?;
+            Ok(Output::Checked {
+                expr,
+                type_: type_expr,
+            })
+        }
+        Declaration::Check { expr, type_: None } => {
+            let (expr, type_) = elaborate_synth(defs, &Context::EMPTY, expr)?;
+            let type_ = try_read_back_type(defs, &Context::EMPTY, &type_)?;
+            Ok(Output::Checked { expr, type_ })
+        }
+        Declaration::Eval { expr } => {
+            let (expr, type_) = elaborate_synth(defs, &Context::EMPTY, expr)?;
+            let val = try_evaluate(defs, &Environment::EMPTY, &expr)?;
+            Ok(Output::Evaluated {
+                value: try_read_back_typed(defs, &Context::EMPTY, &val, &type_)?,
+                type_: try_read_back_type(defs, &Context::EMPTY, &type_)?,
+            })
+        }
+    }
+}
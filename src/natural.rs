@@ -0,0 +1,169 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// An arbitrarily large natural number.
+///
+/// # Examples
+///
+/// ```
+/// use alpha::Natural;
+///
+/// let n: Natural = "18446744073709551615".parse().unwrap();
+/// assert_eq!(n.successor().to_string(), "18446744073709551616");
+/// assert_eq!(n.successor().predecessor(), Some(n));
+/// assert_eq!(Natural::ZERO.predecessor(), None);
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Natural {
+    // little-endian base-2^32 digits, without trailing zeros
+    digits: Vec<u32>,
+}
+
+impl Natural {
+    /// The number `0`.
+    pub const ZERO: Natural = Natural { digits: Vec::new() };
+
+    /// Whether this is `0`.
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// The number one greater than this.
+    pub fn successor(&self) -> Natural {
+        let mut digits = self.digits.clone();
+        for digit in digits.iter_mut() {
+            let (sum, carry) = digit.overflowing_add(1);
+            *digit = sum;
+            if !carry {
+                return Natural { digits };
+            }
+        }
+        digits.push(1);
+        Natural { digits }
+    }
+
+    /// This number as a `usize`, unless it is too large.
+    pub fn to_usize(&self) -> Option<usize> {
+        let mut out: usize = 0;
+        for &digit in self.digits.iter().rev() {
+            out = out
+                .checked_mul(1 << 16)?
+                .checked_mul(1 << 16)?
+                .checked_add(usize::try_from(digit).ok()?)?;
+        }
+        Some(out)
+    }
+
+    /// The number one less than this, unless this is `0`.
+    pub fn predecessor(&self) -> Option<Natural> {
+        if self.is_zero() {
+            return None;
+        }
+        let mut digits = self.digits.clone();
+        for digit in digits.iter_mut() {
+            let (difference, borrow) = digit.overflowing_sub(1);
+            *digit = difference;
+            if !borrow {
+                break;
+            }
+        }
+        if digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Some(Natural { digits })
+    }
+
+    /// Multiplies by `factor` and adds `addend`, in place.
+    fn multiply_add(&mut self, factor: u32, addend: u32) {
+        let mut carry = u64::from(addend);
+        for digit in self.digits.iter_mut() {
+            let product = u64::from(*digit) * u64::from(factor) + carry;
+            *digit = product as u32;
+            carry = product >> 32;
+        }
+        if carry != 0 {
+            self.digits.push(carry as u32);
+        }
+    }
+
+    /// Divides by `divisor` in place, returning the remainder.
+    fn divide(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0;
+        for digit in self.digits.iter_mut().rev() {
+            let dividend = (remainder << 32) | u64::from(*digit);
+            *digit = (dividend / u64::from(divisor)) as u32;
+            remainder = dividend % u64::from(divisor);
+        }
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        remainder as u32
+    }
+}
+
+impl From<u64> for Natural {
+    fn from(value: u64) -> Self {
+        let mut digits = vec![value as u32, (value >> 32) as u32];
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Natural { digits }
+    }
+}
+
+/// The error type for parsing a [`Natural`] from a string which is not a decimal numeral.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseNaturalError;
+
+impl fmt::Display for ParseNaturalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid decimal numeral")
+    }
+}
+
+impl std::error::Error for ParseNaturalError {}
+
+impl FromStr for Natural {
+    type Err = ParseNaturalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseNaturalError);
+        }
+        let mut out = Natural::ZERO;
+        for c in s.chars() {
+            let digit = c.to_digit(10).ok_or(ParseNaturalError)?;
+            out.multiply_add(10, digit);
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for Natural {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // split into base-10^9 chunks, least significant first
+        const CHUNK: u32 = 1_000_000_000;
+        let mut rest = self.clone();
+        let mut chunks = Vec::new();
+        while !rest.is_zero() {
+            chunks.push(rest.divide(CHUNK));
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{}", first)?;
+        }
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Natural {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
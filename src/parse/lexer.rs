@@ -0,0 +1,268 @@
+use crate::parse::ParseError;
+use crate::{Location, Natural};
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq)]
+pub(super) enum TokenKind {
+    Identifier(String),
+    Numeral(Natural),
+    Pi,
+    Lambda,
+    Sigma,
+    First,
+    Second,
+    IdType,
+    Refl,
+    J,
+    Nat,
+    Zero,
+    Succ,
+    NatRec,
+    Bool,
+    True,
+    False,
+    If,
+    Unit,
+    Star,
+    Empty,
+    Absurd,
+    Universe,
+    Let,
+    In,
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    Colon,
+    Comma,
+    Dot,
+    Equals,
+    Plus,
+    Question,
+    Underscore,
+    Arrow,
+    End,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TokenKind::*;
+        match self {
+            Identifier(name) => write!(f, "`{}`", name),
+            Numeral(n) => write!(f, "`{}`", n),
+            Pi => write!(f, "`\u{220F}`"),
+            Lambda => write!(f, "`\u{03BB}`"),
+            Sigma => write!(f, "`\u{03A3}`"),
+            First => write!(f, "`fst`"),
+            Second => write!(f, "`snd`"),
+            IdType => write!(f, "`Id`"),
+            Refl => write!(f, "`refl`"),
+            J => write!(f, "`J`"),
+            Nat => write!(f, "`Nat`"),
+            Zero => write!(f, "`zero`"),
+            Succ => write!(f, "`succ`"),
+            NatRec => write!(f, "`natrec`"),
+            Bool => write!(f, "`Bool`"),
+            True => write!(f, "`true`"),
+            False => write!(f, "`false`"),
+            If => write!(f, "`if`"),
+            Unit => write!(f, "`Unit`"),
+            Star => write!(f, "`tt`"),
+            Empty => write!(f, "`Empty`"),
+            Absurd => write!(f, "`absurd`"),
+            Universe => write!(f, "`U`"),
+            Let => write!(f, "`let`"),
+            In => write!(f, "`in`"),
+            LeftParen => write!(f, "`(`"),
+            RightParen => write!(f, "`)`"),
+            LeftBrace => write!(f, "`{{`"),
+            RightBrace => write!(f, "`}}`"),
+            Colon => write!(f, "`:`"),
+            Comma => write!(f, "`,`"),
+            Dot => write!(f, "`.`"),
+            Equals => write!(f, "`=`"),
+            Plus => write!(f, "`+`"),
+            Question => write!(f, "`?`"),
+            Underscore => write!(f, "`_`"),
+            Arrow => write!(f, "`\u{2192}`"),
+            End => write!(f, "end of input"),
+        }
+    }
+}
+
+impl TokenKind {
+    /// Whether the token begins a binder, which extends as far to the right as possible.
+    pub(super) fn starts_binder(&self) -> bool {
+        use TokenKind::*;
+        matches!(self, Pi | Lambda | Sigma | Let)
+    }
+
+    /// Whether the token begins an atom, which may be an argument in an application.
+    pub(super) fn starts_atom(&self) -> bool {
+        use TokenKind::*;
+        matches!(
+            self,
+            Identifier(_)
+                | Numeral(_)
+                | First
+                | Second
+                | IdType
+                | Refl
+                | J
+                | Nat
+                | Zero
+                | Succ
+                | NatRec
+                | Bool
+                | True
+                | False
+                | If
+                | Unit
+                | Star
+                | Empty
+                | Absurd
+                | Universe
+                | LeftParen
+                | LeftBrace
+                | Question
+        )
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct Token {
+    pub(super) kind: TokenKind,
+    pub(super) start: Location,
+    pub(super) end: Location,
+}
+
+fn is_reserved(c: char) -> bool {
+    matches!(c, '\u{03BB}' | '\u{03A0}' | '\u{220F}' | '\u{03A3}')
+}
+
+fn is_identifier_start(c: char) -> bool {
+    (c.is_alphabetic() || c == '_') && !is_reserved(c)
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    (c.is_alphanumeric() || c == '_' || c == '\'') && !is_reserved(c)
+}
+
+/// Splits source text into tokens, ending with a single [`TokenKind::End`],
+/// where the text begins at line `first_line` of the source it is from.
+pub(super) fn tokenize(source: &str, first_line: usize) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let (mut line, mut column) = (first_line, 1);
+    while let Some(&c) = chars.peek() {
+        let start = Location { line, column };
+        chars.next();
+        if c == '\n' {
+            line += 1;
+            column = 1;
+            continue;
+        }
+        column += 1;
+        if c.is_whitespace() {
+            continue;
+        }
+        let kind = match c {
+            '\u{220F}' | '\u{03A0}' => TokenKind::Pi,
+            '\u{03BB}' | '\\' => TokenKind::Lambda,
+            '\u{03A3}' => TokenKind::Sigma,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '{' => TokenKind::LeftBrace,
+            '}' => TokenKind::RightBrace,
+            ':' => TokenKind::Colon,
+            ',' => TokenKind::Comma,
+            '.' => TokenKind::Dot,
+            '=' => TokenKind::Equals,
+            '+' => TokenKind::Plus,
+            '?' => TokenKind::Question,
+            '\u{2192}' => TokenKind::Arrow,
+            '-' if chars.peek() == Some(&'-') => {
+                // a comment runs to the end of the line
+                while chars.next_if(|&c| c != '\n').is_some() {
+                    column += 1;
+                }
+                continue;
+            }
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                column += 1;
+                TokenKind::Arrow
+            }
+            c if c.is_ascii_digit() => {
+                let mut digits = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !c.is_ascii_digit() {
+                        break;
+                    }
+                    digits.push(c);
+                    chars.next();
+                    column += 1;
+                }
+                TokenKind::Numeral(digits.parse().unwrap_or_else(|_| {
+                    unreachable!("a nonempty string of digits is a natural number")
+                }))
+            }
+            c if is_identifier_start(c) => {
+                let mut name = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !is_identifier_continue(c) {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                    column += 1;
+                }
+                match name.as_str() {
+                    "Pi" => TokenKind::Pi,
+                    "Sigma" => TokenKind::Sigma,
+                    "fst" => TokenKind::First,
+                    "snd" => TokenKind::Second,
+                    "Id" => TokenKind::IdType,
+                    "refl" => TokenKind::Refl,
+                    "J" => TokenKind::J,
+                    "Nat" => TokenKind::Nat,
+                    "zero" => TokenKind::Zero,
+                    "succ" => TokenKind::Succ,
+                    "natrec" => TokenKind::NatRec,
+                    "Bool" => TokenKind::Bool,
+                    "true" => TokenKind::True,
+                    "false" => TokenKind::False,
+                    "if" => TokenKind::If,
+                    "Unit" => TokenKind::Unit,
+                    "tt" => TokenKind::Star,
+                    "Empty" => TokenKind::Empty,
+                    "absurd" => TokenKind::Absurd,
+                    "U" => TokenKind::Universe,
+                    "let" => TokenKind::Let,
+                    "in" => TokenKind::In,
+                    "_" => TokenKind::Underscore,
+                    _ => TokenKind::Identifier(name),
+                }
+            }
+            _ => {
+                return Err(ParseError {
+                    line: start.line,
+                    column: start.column,
+                    msg: format!("Unexpected character `{}`.", c),
+                })
+            }
+        };
+        tokens.push(Token {
+            kind,
+            start,
+            end: Location { line, column },
+        });
+    }
+    let end = Location { line, column };
+    tokens.push(Token {
+        kind: TokenKind::End,
+        start: end,
+        end,
+    });
+    Ok(tokens)
+}
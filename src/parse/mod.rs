@@ -0,0 +1,828 @@
+//! Functions for parsing source text to [`Expression`]s.
+//!
+//! Source text is parsed to a [`Term`] of the [surface syntax](crate::surface),
+//! which is then lowered to an [`Expression`].
+//! The accepted syntax is that produced by displaying an [`Expression`],
+//! along with ASCII alternatives for its non-ASCII symbols and the sugar of the surface syntax:
+//!
+//! | Form        | Syntax                               |
+//! |-------------|--------------------------------------|
+//! | pi type     | `∏(x : A)B`, `Π(x : A)B`, `Pi(x : A)B`, `Pi(x y : A)B` |
+//! | telescope   | `(x y : A) {z : C} → B`, `(x : A) -> B` |
+//! | arrow       | `A → B`, `A -> B`                    |
+//! | lambda      | `λ(x : A)b`, `λx.b`, `\(x : A)b`, `\x.b` |
+//! | binders     | `λx y (z : A) {w}. b`, `λ(x y : A). b` |
+//! | wildcards   | `λ_. b`, `λ(x : _). b`, `(_ : A) → B` |
+//! | application | `f a`, `(f)(a)`                      |
+//! | implicits   | `Pi{x : A}B`, `λ{x}b`, `λ{x : A}b`, `f {a}` |
+//! | sigma type  | `Σ(x : A)B`, `Sigma(x : A)B`         |
+//! | pair        | `(a, b)`                             |
+//! | record      | `{a, b}`, `{}`                       |
+//! | projections | `fst p`, `snd p`                     |
+//! | identity    | `Id A a b`, `refl a`, `J C d p`      |
+//! | naturals    | `Nat`, `zero`, `42`, `succ n`, `natrec C z s n` |
+//! | booleans    | `Bool`, `true`, `false`, `if C t f b` |
+//! | unit        | `Unit`, `tt`                         |
+//! | empty       | `Empty`, `absurd C e`                |
+//! | universe    | `U`, `U 1`, `U.{l}`                  |
+//! | instance    | `id.{0, max(l+1, k)}`                |
+//! | annotation  | `(e : A)`                            |
+//! | let         | `let x : A = a in b`                 |
+//! | hole        | `?`                                  |
+//!
+//! Binders and `let`s extend as far to the right as possible, and application associates to the left.
+//! Arrows associate to the right, and bind less tightly than application.
+//! A wildcard `_` binds a parameter that cannot be mentioned,
+//! and as the type of a parameter of a lambda leaves it to be inferred.
+//! An argument in braces is implicit, so a record of one field given as an argument
+//! must be in parentheses, as in `f ({a})`.
+//!
+//! Universe levels are numerals and level variables, combined by `l+1` and `max(l, k)`.
+//!
+//! A comment runs from `--` to the end of the line.
+//! The [declarations](crate::module) of a source file are parsed by [`parse_module`].
+
+use crate::expression::Expression;
+use crate::meta::MetaVariable;
+use crate::module::Declaration;
+use crate::parse::lexer::{tokenize, Token, TokenKind};
+use crate::surface::{lower, Binder, Term};
+use crate::{Identifier, Level, Location, Natural, Span};
+use std::fmt;
+
+mod lexer;
+
+/// The error type for parsing.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.msg)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses source text to an [`Expression`].
+///
+/// Every term written in the source is [`Located`](Expression::Located) in the result
+/// at the span it was parsed from.
+///
+/// # Examples
+///
+/// Displaying an expression and parsing it back gives an alpha-equivalent expression.
+///
+/// ```
+/// use alpha::equivalence::alpha_equivalent;
+/// use alpha::parse::parse;
+///
+/// let expr = parse(r"\(A : U) Pi(a : A) (\x.x : Pi(y : A) A) a").unwrap();
+/// let printed = expr.to_string();
+/// assert_eq!(printed, "\u{03BB}(A : U)\u{220F}(a : A)((\u{03BB}x.x : \u{220F}(y : A)A))(a)");
+/// assert!(alpha_equivalent(&parse(&printed).unwrap(), &expr).is_ok());
+///
+/// let error = parse("\u{03BB}x.(x").err().unwrap();
+/// assert_eq!((error.line, error.column), (1, 6));
+/// ```
+pub fn parse(source: &str) -> Result<Expression, ParseError> {
+    lower(&parse_term(source)?)
+}
+
+/// Parses source text to a [`Term`] of the surface syntax, without lowering it.
+pub fn parse_term(source: &str) -> Result<Term, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(source, 1)?,
+        pos: 0,
+    };
+    let term = parser.expression()?;
+    parser.expect(TokenKind::End)?;
+    Ok(term)
+}
+
+/// Parses source text to the top-level [`Declaration`]s it consists of, each with its span.
+///
+/// Each declaration begins at the start of a line, and continues over the lines after it
+/// which are indented, so one that does not parse does not affect those after it.
+/// The words beginning declarations are keywords only there, so may name variables elsewhere.
+///
+/// # Examples
+///
+/// ```
+/// use alpha::parse::parse_module;
+///
+/// let source = "def two : Nat = 2 -- a comment
+/// check (
+/// eval two
+/// def check : Nat =
+///   (λeval. eval) 3";
+/// let declarations = parse_module(source);
+/// assert_eq!(declarations.len(), 4);
+/// assert!(declarations[0].1.is_ok());
+/// assert_eq!(declarations[1].0.to_string(), "2:1-2:8");
+/// assert!(declarations[1].1.is_err());
+/// assert!(declarations[2].1.is_ok());
+/// assert_eq!(declarations[3].0.to_string(), "4:1-5:18");
+/// assert!(declarations[3].1.is_ok());
+///
+/// // even a character which begins no token
+/// let declarations = parse_module("def one : Nat = 1\ndef bad : Nat = @\ndef two : Nat = 2");
+/// assert_eq!(declarations.len(), 3);
+/// assert!(declarations[0].1.is_ok());
+/// let error = declarations[1].1.as_ref().err().unwrap();
+/// assert_eq!((error.line, error.column), (2, 17));
+/// assert!(declarations[2].1.is_ok());
+/// ```
+pub fn parse_module(source: &str) -> Vec<(Span, Result<Declaration, ParseError>)> {
+    let mut declarations = Vec::new();
+    for (first_line, source) in declaration_sources(source) {
+        let mut tokens = match tokenize(source, first_line) {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                let at = Location {
+                    line: e.line,
+                    column: e.column,
+                };
+                declarations.push((Span { start: at, end: at }, Err(e)));
+                continue;
+            }
+        };
+        tokens.pop();
+        let (Some(first), Some(last)) = (tokens.first(), tokens.last()) else {
+            continue;
+        };
+        let span = Span {
+            start: first.start,
+            end: last.end,
+        };
+        tokens.push(Token {
+            kind: TokenKind::End,
+            start: span.end,
+            end: span.end,
+        });
+        let mut parser = Parser { tokens, pos: 0 };
+        declarations.push((span, parser.declaration()));
+    }
+    declarations
+}
+
+/// Splits source text at the lines which begin with a token, which begin the declarations,
+/// and gives each part with the number of its first line.
+///
+/// The parts are split before they are tokenized,
+/// so that a character which begins no token is an error only in the part it is in.
+fn declaration_sources(source: &str) -> Vec<(usize, &str)> {
+    let mut sources = Vec::new();
+    let (mut start, mut first_line, mut offset) = (0, 1, 0);
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let begins = line.starts_with(|c: char| !c.is_whitespace()) && !line.starts_with("--");
+        if begins && offset > start {
+            sources.push((first_line, &source[start..offset]));
+            (start, first_line) = (offset, index + 1);
+        }
+        offset += line.len();
+    }
+    sources.push((first_line, &source[start..]));
+    sources
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error(&self, msg: String) -> ParseError {
+        let token = self.peek();
+        ParseError {
+            line: token.start.line,
+            column: token.start.column,
+            msg,
+        }
+    }
+
+    /// Records that `term` spans from `start` to the end of the last token consumed.
+    fn located(&self, start: Location, term: Term) -> Term {
+        Term::Located {
+            span: Span {
+                start,
+                end: self.tokens[self.pos - 1].end,
+            },
+            term: Box::new(term),
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        self.error(format!(
+            "Expected {}, found {}.",
+            expected,
+            self.peek().kind
+        ))
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<(), ParseError> {
+        if self.peek().kind == kind {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.unexpected(&kind.to_string()))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<Identifier, ParseError> {
+        let TokenKind::Identifier(name) = &self.peek().kind else {
+            return Err(self.unexpected("an identifier"));
+        };
+        let id = Identifier::new(name);
+        self.advance();
+        Ok(id)
+    }
+
+    /// Parses a top-level declaration, which must be all of the tokens.
+    fn declaration(&mut self) -> Result<Declaration, ParseError> {
+        let keyword = match &self.peek().kind {
+            TokenKind::Identifier(name) => name.as_str(),
+            _ => "",
+        };
+        let declaration = match keyword {
+            "def" => {
+                self.advance();
+                let (name, level_params) = self.declared_name()?;
+                self.expect(TokenKind::Colon)?;
+                let type_ = lower(&self.expression()?)?;
+                self.expect(TokenKind::Equals)?;
+                let value = lower(&self.expression()?)?;
+                Declaration::Define {
+                    name,
+                    level_params,
+                    type_,
+                    value,
+                }
+            }
+            "postulate" => {
+                self.advance();
+                let (name, level_params) = self.declared_name()?;
+                self.expect(TokenKind::Colon)?;
+                let type_ = lower(&self.expression()?)?;
+                Declaration::Postulate {
+                    name,
+                    level_params,
+                    type_,
+                }
+            }
+            "check" => {
+                self.advance();
+                let expr = lower(&self.expression()?)?;
+                let type_ = if self.peek().kind == TokenKind::Colon {
+                    self.advance();
+                    Some(lower(&self.expression()?)?)
+                } else {
+                    None
+                };
+                Declaration::Check { expr, type_ }
+            }
+            "eval" => {
+                self.advance();
+                let expr = lower(&self.expression()?)?;
+                Declaration::Eval { expr }
+            }
+            _ => return Err(self.unexpected("`def`, `postulate`, `check` or `eval`")),
+        };
+        if self.peek().kind != TokenKind::End {
+            return Err(self.unexpected("the end of the declaration"));
+        }
+        Ok(declaration)
+    }
+
+    /// Parses the name declared by a top-level declaration,
+    /// followed by the universe level variables it is generic in, as in `id.{l, k}`.
+    fn declared_name(&mut self) -> Result<(Identifier, Vec<Identifier>), ParseError> {
+        let name = self.identifier()?;
+        let mut level_params = Vec::new();
+        if self.at_level_arguments() {
+            self.advance();
+            self.advance();
+            if self.peek().kind != TokenKind::RightBrace {
+                level_params.push(self.identifier()?);
+                while self.peek().kind == TokenKind::Comma {
+                    self.advance();
+                    level_params.push(self.identifier()?);
+                }
+            }
+            self.expect(TokenKind::RightBrace)?;
+        }
+        Ok((name, level_params))
+    }
+
+    /// Parses the name bound by a binder, which is an identifier or a wildcard `_`.
+    fn binder_name(&mut self) -> Result<Identifier, ParseError> {
+        if self.peek().kind == TokenKind::Underscore {
+            self.advance();
+            return Ok(Binder::wildcard());
+        }
+        self.identifier()
+    }
+
+    /// Whether the token at `pos` is the name bound by a binder.
+    fn is_binder_name(&self, pos: usize) -> bool {
+        matches!(
+            self.tokens[pos].kind,
+            TokenKind::Identifier(_) | TokenKind::Underscore
+        )
+    }
+
+    /// Parses `(x : A)`, as found after a binder.
+    fn typed_binder(&mut self) -> Result<(Identifier, Term), ParseError> {
+        self.expect(TokenKind::LeftParen)?;
+        let id = self.binder_name()?;
+        self.expect(TokenKind::Colon)?;
+        let type_ = self.expression()?;
+        self.expect(TokenKind::RightParen)?;
+        Ok((id, type_))
+    }
+
+    /// Parses `(x y : A)` or `{x y : A}`, binding parameters of a type.
+    ///
+    /// The parameters of a lambda may be left without a type,
+    /// as in `{x y}`, or with a wildcard for it, as in `(x y : _)`.
+    fn binder_group(&mut self, lambda: bool) -> Result<Binder, ParseError> {
+        let (implicit, close) = match self.peek().kind {
+            TokenKind::LeftParen => (false, TokenKind::RightParen),
+            TokenKind::LeftBrace => (true, TokenKind::RightBrace),
+            _ => return Err(self.unexpected("`(` or `{`")),
+        };
+        self.advance();
+        let mut names = vec![self.binder_name()?];
+        while self.is_binder_name(self.pos) {
+            names.push(self.binder_name()?);
+        }
+        let type_ = if lambda && implicit && self.peek().kind == close {
+            None
+        } else {
+            self.expect(TokenKind::Colon)?;
+            if lambda
+                && self.peek().kind == TokenKind::Underscore
+                && self.tokens[self.pos + 1].kind == close
+            {
+                self.advance();
+                None
+            } else {
+                Some(self.expression()?)
+            }
+        };
+        self.expect(close)?;
+        Ok(Binder {
+            names,
+            type_,
+            implicit,
+        })
+    }
+
+    /// Parses the binders of a lambda, either names `x y` or a group in parentheses or braces.
+    fn lambda_binder(&mut self) -> Result<Binder, ParseError> {
+        if !self.is_binder_name(self.pos) {
+            return self.binder_group(true);
+        }
+        let mut names = Vec::new();
+        while self.is_binder_name(self.pos) {
+            names.push(self.binder_name()?);
+        }
+        Ok(Binder {
+            names,
+            type_: None,
+            implicit: false,
+        })
+    }
+
+    /// The position just after the bracket matching the one at `pos`,
+    /// or of the end of input if there is none.
+    fn after_brackets(&self, mut pos: usize) -> usize {
+        let mut depth = 0usize;
+        loop {
+            match self.tokens[pos].kind {
+                TokenKind::LeftParen | TokenKind::LeftBrace => depth += 1,
+                TokenKind::RightParen | TokenKind::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return pos + 1;
+                    }
+                }
+                TokenKind::End => return pos,
+                _ => {}
+            }
+            pos += 1;
+        }
+    }
+
+    /// Whether the next tokens are groups `(x y : A)` or `{x y : A}` followed by `→`,
+    /// binding the parameters of a pi type.
+    fn at_pi_binders(&self) -> bool {
+        let mut pos = self.pos;
+        loop {
+            match self.tokens[pos].kind {
+                TokenKind::LeftParen | TokenKind::LeftBrace => {
+                    let mut names_end = pos + 1;
+                    while self.is_binder_name(names_end) {
+                        names_end += 1;
+                    }
+                    if names_end == pos + 1 || self.tokens[names_end].kind != TokenKind::Colon {
+                        return false;
+                    }
+                    pos = self.after_brackets(pos);
+                }
+                TokenKind::Arrow => return pos > self.pos,
+                _ => return false,
+            }
+        }
+    }
+
+    /// Whether the next tokens are binders of a lambda followed by `.`, as in `x (y : A) {z}.`,
+    /// rather than a single group in parentheses or braces followed directly by the body.
+    fn at_lambda_binders(&self) -> bool {
+        let mut pos = self.pos;
+        loop {
+            match self.tokens[pos].kind {
+                TokenKind::Identifier(_) | TokenKind::Underscore => pos += 1,
+                TokenKind::LeftParen | TokenKind::LeftBrace => pos = self.after_brackets(pos),
+                TokenKind::Dot => {
+                    // in `λ{l}id.{l}`, the body is an instance after a single group
+                    let instance = self.tokens[pos + 1].kind == TokenKind::LeftBrace
+                        && self.is_binder_name(pos - 1)
+                        && pos - 1 > self.pos
+                        && !self.is_binder_name(self.pos)
+                        && self.after_brackets(self.pos) == pos - 1;
+                    return !instance;
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    fn expression(&mut self) -> Result<Term, ParseError> {
+        let start = self.peek().start;
+        if self.peek().kind.starts_binder() {
+            return self.binder();
+        }
+        if self.at_pi_binders() {
+            let mut binders = Vec::new();
+            while self.peek().kind != TokenKind::Arrow {
+                binders.push(self.binder_group(false)?);
+            }
+            self.advance();
+            let ret_type = Box::new(self.expression()?);
+            return Ok(self.located(start, Term::PiType { binders, ret_type }));
+        }
+        let domain = self.application()?;
+        if self.peek().kind != TokenKind::Arrow {
+            return Ok(domain);
+        }
+        self.advance();
+        let arrow = Term::Arrow {
+            domain: Box::new(domain),
+            codomain: Box::new(self.expression()?),
+        };
+        Ok(self.located(start, arrow))
+    }
+
+    fn binder(&mut self) -> Result<Term, ParseError> {
+        let start = self.peek().start;
+        let term = match self.advance().kind {
+            TokenKind::Pi => {
+                let binders = vec![self.binder_group(false)?];
+                Term::PiType {
+                    binders,
+                    ret_type: Box::new(self.expression()?),
+                }
+            }
+            TokenKind::Sigma => {
+                let (tparam, tparam_type) = self.typed_binder()?;
+                Term::SigmaType {
+                    tparam,
+                    tparam_type: Box::new(tparam_type),
+                    snd_type: Box::new(self.expression()?),
+                }
+            }
+            TokenKind::Lambda if !self.is_binder_name(self.pos) && !self.at_lambda_binders() => {
+                let binders = vec![self.binder_group(true)?];
+                Term::Lambda {
+                    binders,
+                    body: Box::new(self.expression()?),
+                }
+            }
+            TokenKind::Lambda => {
+                let mut binders = vec![self.lambda_binder()?];
+                while matches!(
+                    self.peek().kind,
+                    TokenKind::Identifier(_)
+                        | TokenKind::Underscore
+                        | TokenKind::LeftParen
+                        | TokenKind::LeftBrace
+                ) {
+                    binders.push(self.lambda_binder()?);
+                }
+                self.expect(TokenKind::Dot)?;
+                Term::Lambda {
+                    binders,
+                    body: Box::new(self.expression()?),
+                }
+            }
+            TokenKind::Let => {
+                let name = self.identifier()?;
+                self.expect(TokenKind::Colon)?;
+                let type_ = self.expression()?;
+                self.expect(TokenKind::Equals)?;
+                let value = self.expression()?;
+                self.expect(TokenKind::In)?;
+                Term::Let {
+                    name,
+                    type_: Box::new(type_),
+                    value: Box::new(value),
+                    body: Box::new(self.expression()?),
+                }
+            }
+            _ => unreachable!("`binder` is only called at a binder"),
+        };
+        Ok(self.located(start, term))
+    }
+
+    fn application(&mut self) -> Result<Term, ParseError> {
+        let start = self.peek().start;
+        let mut expr = self.atom()?;
+        loop {
+            let kind = &self.peek().kind;
+            let (arg, implicit) = if *kind == TokenKind::LeftBrace {
+                // a single expression in braces is an implicit argument, not a record
+                let arg = self.atom()?;
+                match arg.unlocated() {
+                    Term::Record { fields } if fields.len() == 1 => (fields[0].clone(), true),
+                    _ => (arg, false),
+                }
+            } else if kind.starts_atom() {
+                (self.atom()?, false)
+            } else if kind.starts_binder() {
+                // a binder as the last argument needs no parentheses
+                (self.binder()?, false)
+            } else {
+                return Ok(expr);
+            };
+            let app = Term::Application {
+                func: Box::new(expr),
+                arg: Box::new(arg),
+                implicit,
+            };
+            expr = self.located(start, app);
+        }
+    }
+
+    /// Parses a keyword followed by a fixed number of atoms as arguments.
+    fn keyword_arguments<const N: usize>(&mut self) -> Result<[Box<Term>; N], ParseError> {
+        self.advance();
+        let mut args = Vec::with_capacity(N);
+        for _ in 0..N {
+            args.push(Box::new(self.atom()?));
+        }
+        Ok(args
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("exactly `N` arguments are parsed")))
+    }
+
+    /// Whether the next tokens are `.{`, beginning a list of universe levels.
+    fn at_level_arguments(&self) -> bool {
+        self.peek().kind == TokenKind::Dot
+            && self.tokens.get(self.pos + 1).map(|token| &token.kind) == Some(&TokenKind::LeftBrace)
+    }
+
+    /// Parses `.{l, k}`, a list of universe levels.
+    fn level_arguments(&mut self) -> Result<Vec<Level>, ParseError> {
+        self.expect(TokenKind::Dot)?;
+        self.expect(TokenKind::LeftBrace)?;
+        let mut levels = Vec::new();
+        if self.peek().kind != TokenKind::RightBrace {
+            levels.push(self.level()?);
+            while self.peek().kind == TokenKind::Comma {
+                self.advance();
+                levels.push(self.level()?);
+            }
+        }
+        self.expect(TokenKind::RightBrace)?;
+        Ok(levels)
+    }
+
+    /// Parses a numeral small enough to be a universe level.
+    fn level_constant(&mut self) -> Result<usize, ParseError> {
+        let TokenKind::Numeral(n) = &self.peek().kind else {
+            return Err(self.unexpected("a numeral"));
+        };
+        let Some(n) = n.to_usize() else {
+            return Err(self.error(format!("Universe level `{}` is too large.", n)));
+        };
+        self.advance();
+        Ok(n)
+    }
+
+    fn level(&mut self) -> Result<Level, ParseError> {
+        let mut level = match &self.peek().kind {
+            TokenKind::Numeral(_) => Level::constant(self.level_constant()?),
+            TokenKind::Identifier(name)
+                if name == "max"
+                    && self.tokens.get(self.pos + 1).map(|token| &token.kind)
+                        == Some(&TokenKind::LeftParen) =>
+            {
+                self.advance();
+                self.advance();
+                let mut level = self.level()?;
+                self.expect(TokenKind::Comma)?;
+                level = level.max(&self.level()?);
+                while self.peek().kind == TokenKind::Comma {
+                    self.advance();
+                    level = level.max(&self.level()?);
+                }
+                self.expect(TokenKind::RightParen)?;
+                level
+            }
+            TokenKind::Identifier(_) => Level::variable(self.identifier()?),
+            TokenKind::LeftParen => {
+                self.advance();
+                let level = self.level()?;
+                self.expect(TokenKind::RightParen)?;
+                level
+            }
+            _ => return Err(self.unexpected("a universe level")),
+        };
+        while self.peek().kind == TokenKind::Plus {
+            self.advance();
+            let start = self.peek().start;
+            let n = self.level_constant()?;
+            level = level.add(n).ok_or_else(|| ParseError {
+                line: start.line,
+                column: start.column,
+                msg: format!("Universe level `{}+{}` is too large.", level, n),
+            })?;
+        }
+        Ok(level)
+    }
+
+    /// Parses a keyword standing for `expr` by itself.
+    fn constant(&mut self, expr: Expression) -> Result<Term, ParseError> {
+        let start = self.advance().start;
+        Ok(self.located(start, Term::Core(expr)))
+    }
+
+    fn atom(&mut self) -> Result<Term, ParseError> {
+        let start = self.peek().start;
+        match self.peek().kind {
+            TokenKind::Identifier(_) => {
+                let id = self.identifier()?;
+                if !self.at_level_arguments() {
+                    return Ok(self.located(start, Term::Variable(id)));
+                }
+                let levels = self.level_arguments()?;
+                let instance = Term::Core(Expression::Instance { name: id, levels });
+                Ok(self.located(start, instance))
+            }
+            TokenKind::First => {
+                let [pair] = self.keyword_arguments()?;
+                Ok(self.located(start, Term::First { pair }))
+            }
+            TokenKind::Second => {
+                let [pair] = self.keyword_arguments()?;
+                Ok(self.located(start, Term::Second { pair }))
+            }
+            TokenKind::IdType => {
+                let [type_, lhs, rhs] = self.keyword_arguments()?;
+                Ok(self.located(start, Term::IdType { type_, lhs, rhs }))
+            }
+            TokenKind::Refl => {
+                let [elem] = self.keyword_arguments()?;
+                Ok(self.located(start, Term::Refl { elem }))
+            }
+            TokenKind::J => {
+                let [motive, base, eq] = self.keyword_arguments()?;
+                Ok(self.located(start, Term::J { motive, base, eq }))
+            }
+            TokenKind::Numeral(ref n) => {
+                let n = n.clone();
+                self.advance();
+                Ok(self.located(start, Term::Core(Expression::Numeral(n))))
+            }
+            TokenKind::Nat => self.constant(Expression::Nat),
+            TokenKind::Zero => self.constant(Expression::Numeral(Natural::ZERO)),
+            TokenKind::Succ => {
+                let [pred] = self.keyword_arguments()?;
+                Ok(self.located(start, Term::Succ { pred }))
+            }
+            TokenKind::NatRec => {
+                let [motive, base, step, target] = self.keyword_arguments()?;
+                let natrec = Term::NatRec {
+                    motive,
+                    base,
+                    step,
+                    target,
+                };
+                Ok(self.located(start, natrec))
+            }
+            TokenKind::Bool => self.constant(Expression::Bool),
+            TokenKind::True => self.constant(Expression::True),
+            TokenKind::False => self.constant(Expression::False),
+            TokenKind::If => {
+                let [motive, on_true, on_false, target] = self.keyword_arguments()?;
+                let if_ = Term::If {
+                    motive,
+                    on_true,
+                    on_false,
+                    target,
+                };
+                Ok(self.located(start, if_))
+            }
+            TokenKind::Question => self.constant(Expression::Hole(MetaVariable::fresh())),
+            TokenKind::Unit => self.constant(Expression::Unit),
+            TokenKind::Star => self.constant(Expression::Star),
+            TokenKind::Empty => self.constant(Expression::Empty),
+            TokenKind::Absurd => {
+                let [motive, target] = self.keyword_arguments()?;
+                Ok(self.located(start, Term::Absurd { motive, target }))
+            }
+            TokenKind::Universe => {
+                self.advance();
+                let level = if let TokenKind::Numeral(_) = self.peek().kind {
+                    Level::constant(self.level_constant()?)
+                } else if self.at_level_arguments() {
+                    let levels_start = self.peek().start;
+                    match <[Level; 1]>::try_from(self.level_arguments()?) {
+                        Ok([level]) => level,
+                        Err(_) => {
+                            return Err(ParseError {
+                                line: levels_start.line,
+                                column: levels_start.column,
+                                msg: "Expected exactly one universe level.".to_string(),
+                            })
+                        }
+                    }
+                } else {
+                    Level::ZERO
+                };
+                Ok(self.located(start, Term::Core(Expression::Universe(level))))
+            }
+            TokenKind::LeftBrace => {
+                self.advance();
+                let mut fields = Vec::new();
+                if self.peek().kind != TokenKind::RightBrace {
+                    fields.push(self.expression()?);
+                    while self.peek().kind == TokenKind::Comma {
+                        self.advance();
+                        fields.push(self.expression()?);
+                    }
+                }
+                self.expect(TokenKind::RightBrace)?;
+                Ok(self.located(start, Term::Record { fields }))
+            }
+            TokenKind::LeftParen => {
+                self.advance();
+                let term = self.expression()?;
+                match self.peek().kind {
+                    TokenKind::Colon => {
+                        self.advance();
+                        let type_ = self.expression()?;
+                        self.expect(TokenKind::RightParen)?;
+                        let ascription = Term::Ascription {
+                            term: Box::new(term),
+                            type_: Box::new(type_),
+                        };
+                        Ok(self.located(start, ascription))
+                    }
+                    TokenKind::Comma => {
+                        self.advance();
+                        let snd = self.expression()?;
+                        self.expect(TokenKind::RightParen)?;
+                        let pair = Term::Pair {
+                            fst: Box::new(term),
+                            snd: Box::new(snd),
+                        };
+                        Ok(self.located(start, pair))
+                    }
+                    _ => {
+                        self.expect(TokenKind::RightParen)?;
+                        Ok(term)
+                    }
+                }
+            }
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+}
@@ -0,0 +1,1305 @@
+//! Definitions by dependent pattern matching, and the case trees they compile to.
+
+use crate::environment::{Context, Definitions, Environment};
+use crate::equivalence::{alpha_equivalent, judgmentally_equal};
+use crate::evaluation::{try_apply, try_evaluate, try_first, try_second, try_succ};
+use crate::expression::Expression;
+use crate::identifier::fresh_from;
+use crate::inductive::lambdas;
+use crate::read_back::{try_read_back_type, try_read_back_typed};
+use crate::typing::{apply_all, elaborate};
+use crate::value::{Neutral, Type, Value};
+use crate::{Identifier, Natural, TypeError, TypeErrorKind, Warning};
+use std::fmt;
+use std::ops::Deref;
+
+/// A pattern on the left of a [`Clause`].
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// Matches anything, binding it to a variable.
+    Variable(Identifier),
+    /// Matches an element of a declared inductive family built by the constructor `name`,
+    /// whose fields match `args`; the parameters of the family are left out.
+    Constructor {
+        name: Identifier,
+        args: Vec<Pattern>,
+    },
+    Zero,
+    Succ(Box<Pattern>),
+    True,
+    False,
+    /// Matches a pair whose components match the two patterns.
+    Pair(Box<Pattern>, Box<Pattern>),
+    Star,
+    /// Matches the proof of an equality, solving for a variable of the patterns on one side
+    /// by the other, and otherwise leaving both sides as if they were inaccessible.
+    Refl,
+    /// Matches anything, as the other patterns force it to be equal to the expression.
+    Inaccessible(Expression),
+    /// Matches nothing, at a type none of whose constructors could build what is matched.
+    Absurd,
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Pattern::*;
+        match self {
+            Variable(id) => id.fmt(f),
+            Constructor { name, args } => {
+                name.fmt(f)?;
+                for arg in args {
+                    write!(f, " {}", Atomic(arg))?;
+                }
+                Ok(())
+            }
+            Zero => write!(f, "zero"),
+            Succ(pred) => write!(f, "succ {}", Atomic(pred)),
+            True => write!(f, "true"),
+            False => write!(f, "false"),
+            Pair(fst, snd) => write!(f, "({}, {})", fst, snd),
+            Star => write!(f, "tt"),
+            Refl => write!(f, "refl"),
+            Inaccessible(expr) => match expr.unlocated() {
+                Expression::Variable(id) => write!(f, ".{}", id),
+                expr => write!(f, ".({})", expr),
+            },
+            Absurd => write!(f, "()"),
+        }
+    }
+}
+
+/// Displays a pattern in parentheses if it has arguments.
+struct Atomic<'a>(&'a Pattern);
+
+impl fmt::Display for Atomic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Pattern::Constructor { args, .. } if !args.is_empty() => write!(f, "({})", self.0),
+            Pattern::Succ(_) => write!(f, "({})", self.0),
+            pattern => pattern.fmt(f),
+        }
+    }
+}
+
+/// Displays a function applied to patterns, as on the left of a clause.
+pub(crate) struct LeftHandSide<'a>(pub(crate) &'a Identifier, pub(crate) &'a [Pattern]);
+
+impl fmt::Display for LeftHandSide<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)?;
+        for pattern in self.1 {
+            write!(f, " {}", Atomic(pattern))?;
+        }
+        Ok(())
+    }
+}
+
+/// One equation defining a function by pattern matching, such as `add (succ m) n = succ (add m n)`.
+#[derive(Clone, Debug)]
+pub struct Clause {
+    /// A pattern for each argument the function matches on.
+    pub patterns: Vec<Pattern>,
+    /// The value of the function on arguments matching the patterns,
+    /// left out exactly when one of the patterns is absurd.
+    pub body: Option<Expression>,
+}
+
+/// A declaration of a function of type `type_` by a list of clauses.
+///
+/// Declaring it with [`Definitions::define_function`] checks that the clauses cover
+/// every argument, and defines `name` to compute by the first clause that matches.
+/// The clauses may call `name` itself, which does not compute while they are checked,
+/// but only on structurally smaller arguments unless the function is `partial`.
+#[derive(Clone, Debug)]
+pub struct FunctionDeclaration {
+    pub name: Identifier,
+    pub type_: Expression,
+    pub clauses: Vec<Clause>,
+    /// Whether to skip checking that the function terminates,
+    /// marking it and everything defined in terms of it as partial.
+    pub partial: bool,
+}
+
+impl FunctionDeclaration {
+    /// Every identifier occurring in the declaration.
+    fn identifiers(&self) -> Vec<Identifier> {
+        let mut out = vec![self.name.clone()];
+        out.extend(self.type_.identifiers());
+        for clause in &self.clauses {
+            for pattern in &clause.patterns {
+                pattern.identifiers(&mut out);
+            }
+            out.extend(clause.body.iter().flat_map(Expression::identifiers));
+        }
+        out
+    }
+
+    /// The number of arguments the function matches on.
+    pub(crate) fn arity(&self) -> usize {
+        self.clauses
+            .first()
+            .map_or(0, |clause| clause.patterns.len())
+    }
+
+    /// Every expression in the declaration.
+    pub(crate) fn expressions(&self) -> Vec<&Expression> {
+        let mut out = vec![&self.type_];
+        for clause in &self.clauses {
+            for pattern in &clause.patterns {
+                pattern.expressions(&mut out);
+            }
+            out.extend(&clause.body);
+        }
+        out
+    }
+}
+
+impl Pattern {
+    /// The constructor this pattern matches, and the patterns for its fields, if it is one.
+    fn head(&self) -> Option<(Head, Vec<&Pattern>)> {
+        match self {
+            Pattern::Constructor { name, args } => {
+                Some((Head::Constructor(name.clone()), args.iter().collect()))
+            }
+            Pattern::Zero => Some((Head::Zero, Vec::new())),
+            Pattern::Succ(pred) => Some((Head::Succ, vec![pred])),
+            Pattern::True => Some((Head::True, Vec::new())),
+            Pattern::False => Some((Head::False, Vec::new())),
+            Pattern::Pair(fst, snd) => Some((Head::Pair, vec![fst, snd])),
+            Pattern::Star => Some((Head::Star, Vec::new())),
+            Pattern::Refl => Some((Head::Refl, Vec::new())),
+            Pattern::Variable(_) | Pattern::Inaccessible(_) | Pattern::Absurd => None,
+        }
+    }
+
+    /// Every pattern nested in this one, including itself.
+    pub(crate) fn subpatterns(&self) -> Vec<&Pattern> {
+        let mut out = vec![self];
+        if let Some((_, args)) = self.head() {
+            out.extend(args.into_iter().flat_map(Pattern::subpatterns));
+        }
+        out
+    }
+
+    fn identifiers(&self, out: &mut Vec<Identifier>) {
+        for pattern in self.subpatterns() {
+            match pattern {
+                Pattern::Variable(id) => out.push(id.clone()),
+                Pattern::Inaccessible(expr) => out.extend(expr.identifiers()),
+                _ => {}
+            }
+        }
+    }
+
+    fn expressions<'a>(&'a self, out: &mut Vec<&'a Expression>) {
+        for pattern in self.subpatterns() {
+            if let Pattern::Inaccessible(expr) = pattern {
+                out.push(expr);
+            }
+        }
+    }
+}
+
+/// What builds an element of an inductive type.
+///
+/// The built-in pairs, unit and equalities are built with no parameters,
+/// as what they build does not record the types they are built at.
+#[derive(Clone, PartialEq)]
+enum Head {
+    Constructor(Identifier),
+    Zero,
+    Succ,
+    True,
+    False,
+    Pair,
+    Star,
+    Refl,
+}
+
+impl Head {
+    /// The pattern matching this head with `fields`.
+    fn pattern(&self, mut fields: Vec<Pattern>) -> Pattern {
+        match self {
+            Head::Constructor(name) => Pattern::Constructor {
+                name: name.clone(),
+                args: fields,
+            },
+            Head::Zero => Pattern::Zero,
+            Head::Succ => Pattern::Succ(Box::new(fields.remove(0))),
+            Head::True => Pattern::True,
+            Head::False => Pattern::False,
+            Head::Pair => {
+                let fst = fields.remove(0);
+                Pattern::Pair(Box::new(fst), Box::new(fields.remove(0)))
+            }
+            Head::Star => Pattern::Star,
+            Head::Refl => Pattern::Refl,
+        }
+    }
+
+    /// The type of this head building an element of `type_` in `ctx`,
+    /// as a function of the parameters of `type_` and its fields.
+    fn type_(&self, defs: &Definitions, ctx: &Context, type_: &Type) -> crate::Result<Type> {
+        Ok(match self {
+            Head::Constructor(name) => defs.lookup_type(name)?.clone(),
+            Head::Zero => Type::create_type_from_value(Value::Nat),
+            Head::Succ => Type::pi_type(
+                &Context::EMPTY,
+                Identifier::new("n"),
+                Type::create_type_from_value(Value::Nat),
+                Expression::Nat,
+            ),
+            Head::True | Head::False => Type::create_type_from_value(Value::Bool),
+            Head::Pair => {
+                let sigma_type = try_read_back_type(defs, ctx, type_)?;
+                let Expression::SigmaType {
+                    tparam,
+                    tparam_type,
+                    snd_type,
+                } = &sigma_type
+                else {
+                    return Err(not_built_by(type_));
+                };
+                // the second component is named apart from what the sigma type mentions
+                let snd = fresh_from("y", sigma_type.identifiers().iter());
+                let pair_type = Expression::PiType {
+                    tparam: tparam.clone(),
+                    tparam_type: tparam_type.clone(),
+                    ret_type: Box::new(Expression::PiType {
+                        tparam: snd,
+                        tparam_type: snd_type.clone(),
+                        ret_type: Box::new(sigma_type.clone()),
+                        implicit: false,
+                    }),
+                    implicit: false,
+                };
+                let env = Environment::from_context(ctx);
+                Type::create_type_from_value(try_evaluate(defs, &env, &pair_type)?)
+            }
+            Head::Star => Type::create_type_from_value(Value::Unit),
+            Head::Refl => {
+                let Value::IdType { type_, lhs, .. } = type_.deref() else {
+                    return Err(not_built_by(type_));
+                };
+                Type::create_type_from_value(Value::IdType {
+                    type_: type_.clone(),
+                    lhs: lhs.clone(),
+                    rhs: lhs.clone(),
+                })
+            }
+        })
+    }
+
+    /// This head building an element of `type_`, applied to `args`.
+    fn construct(&self, type_: &Type, mut args: Vec<Value>) -> crate::Result<Value> {
+        match self {
+            Head::Constructor(name) => Ok(Value::Constructor {
+                name: name.clone(),
+                args,
+            }),
+            Head::Zero => Ok(Value::Numeral(Natural::ZERO)),
+            Head::Succ => try_succ(&args.remove(0)),
+            Head::True => Ok(Value::True),
+            Head::False => Ok(Value::False),
+            Head::Pair => {
+                let fst = args.remove(0);
+                Ok(Value::Pair {
+                    fst: Box::new(fst),
+                    snd: Box::new(args.remove(0)),
+                })
+            }
+            Head::Star => Ok(Value::Star),
+            Head::Refl => match type_.deref() {
+                Value::IdType { lhs, .. } => Ok(Value::Refl { elem: lhs.clone() }),
+                _ => Err(not_built_by(type_)),
+            },
+        }
+    }
+}
+
+/// The error for a head asked to build an element of `type_`, which it does not build.
+fn not_built_by(type_: &Type) -> TypeError {
+    TypeError::new(TypeErrorKind::Internal(format!(
+        "No pattern of a built-in type builds an element of `{}`.",
+        type_
+    )))
+}
+
+/// The head `val` is built by, with all of its arguments, if it is built by one.
+fn arguments(val: &Value) -> Option<(Head, Vec<Value>)> {
+    match val {
+        Value::Constructor { name, args } => Some((Head::Constructor(name.clone()), args.clone())),
+        Value::Numeral(n) => Some(match n.predecessor() {
+            None => (Head::Zero, Vec::new()),
+            Some(pred) => (Head::Succ, vec![Value::Numeral(pred)]),
+        }),
+        Value::Succ { pred } => Some((Head::Succ, vec![(**pred).clone()])),
+        Value::True => Some((Head::True, Vec::new())),
+        Value::False => Some((Head::False, Vec::new())),
+        Value::Pair { fst, snd } => Some((Head::Pair, vec![(**fst).clone(), (**snd).clone()])),
+        Value::Star => Some((Head::Star, Vec::new())),
+        Value::Refl { .. } => Some((Head::Refl, Vec::new())),
+        _ => None,
+    }
+}
+
+/// The fields of the neutral `val`, if `heads` has a single head
+/// whose type has eta, so that every element of it is built by the head.
+fn eta_arguments(val: &Value, heads: &[Head]) -> crate::Result<Option<(Head, Vec<Value>)>> {
+    Ok(match (val, heads) {
+        (Value::Neutral { .. }, [Head::Pair]) => {
+            Some((Head::Pair, vec![try_first(val)?, try_second(val)?]))
+        }
+        (Value::Neutral { .. }, [Head::Star]) => Some((Head::Star, Vec::new())),
+        _ => None,
+    })
+}
+
+/// The heads which may build an element of `type_`, with the parameters of the type,
+/// if it is an inductive type.
+fn heads(defs: &Definitions, type_: &Value) -> crate::Result<Option<(Vec<Head>, Vec<Value>)>> {
+    Ok(match type_ {
+        Value::Data { name, args } => {
+            let family = defs.family(name)?;
+            let heads = family
+                .constructors
+                .iter()
+                .map(|(constructor, _)| Head::Constructor(constructor.clone()))
+                .collect();
+            Some((heads, args[..family.params].to_vec()))
+        }
+        Value::Nat => Some((vec![Head::Zero, Head::Succ], Vec::new())),
+        Value::Bool => Some((vec![Head::True, Head::False], Vec::new())),
+        Value::SigmaType { .. } => Some((vec![Head::Pair], Vec::new())),
+        Value::Unit => Some((vec![Head::Star], Vec::new())),
+        Value::IdType { .. } => Some((vec![Head::Refl], Vec::new())),
+        Value::Empty => Some((Vec::new(), Vec::new())),
+        _ => None,
+    })
+}
+
+/// The type of each of `args` as arguments of something of type `type_`,
+/// and the type of its application to all of them.
+fn spine_types(
+    defs: &Definitions,
+    type_: &Type,
+    args: &[Value],
+) -> crate::Result<(Vec<Type>, Type)> {
+    let mut type_ = type_.clone();
+    let mut types = Vec::new();
+    for arg in args {
+        let Value::PiType {
+            param_type,
+            tclosure,
+            ..
+        } = type_.deref()
+        else {
+            return Err(TypeError::new(TypeErrorKind::Internal(format!(
+                "Cannot apply something of type `{}` to `{}`.",
+                type_, arg
+            ))));
+        };
+        types.push((**param_type).clone());
+        type_ = Type::create_type_from_value(tclosure.try_call(defs, arg)?);
+    }
+    Ok((types, type_))
+}
+
+/// What evaluation needs to know about a function defined by pattern matching.
+pub(crate) struct Function {
+    /// The number of arguments the function matches on.
+    pub(crate) arity: usize,
+    tree: CaseTree,
+}
+
+/// Decides which clause of a function applies to its arguments.
+///
+/// The arguments, followed by the fields of each value matched so far, are numbered in order.
+enum CaseTree {
+    /// Computes the body of a clause, as a closed function of every value numbered so far.
+    Leaf(Value),
+    /// Matches the value numbered `index` against the head of each branch,
+    /// numbering the fields of the head it is built by.
+    Split {
+        index: usize,
+        branches: Vec<(Head, usize, CaseTree)>,
+    },
+}
+
+/// Applies a function defined by pattern matching to all the arguments it matches on.
+///
+/// This computes by the first clause matching `args`, or is neutral
+/// if a value which must be matched to decide which clause applies is neutral.
+pub(crate) fn try_match(
+    defs: &Definitions,
+    name: &Identifier,
+    args: &[Value],
+) -> crate::Result<Value> {
+    let mut values = args.to_vec();
+    let mut tree = &defs.function(name)?.tree;
+    loop {
+        match tree {
+            CaseTree::Leaf(body) => {
+                let mut result = body.clone();
+                for val in &values {
+                    result = try_apply(defs, &result, val)?;
+                }
+                return Ok(result);
+            }
+            CaseTree::Split { index, branches } => {
+                let val = &values[*index];
+                let heads: Vec<Head> = branches.iter().map(|(head, _, _)| head.clone()).collect();
+                let built = match arguments(val) {
+                    Some(built) => Some(built),
+                    None => eta_arguments(val, &heads)?,
+                };
+                let found = built.and_then(|(head, args)| {
+                    branches
+                        .iter()
+                        .find(|(branch, _, _)| *branch == head)
+                        .map(|(_, params, subtree)| (args[*params..].to_vec(), subtree))
+                });
+                match (found, val) {
+                    (Some((fields, subtree)), _) => {
+                        values.extend(fields);
+                        tree = subtree;
+                    }
+                    (None, Value::Neutral { .. }) => {
+                        return Ok(Value::Neutral {
+                            neu: Neutral::Function {
+                                name: name.clone(),
+                                args: args.to_vec(),
+                            },
+                        })
+                    }
+                    (None, _) => {
+                        return Err(TypeError::new(TypeErrorKind::Internal(format!(
+                            "No clause of `{}` matches `{}`.",
+                            name, val
+                        ))))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A variable bound while matching, with its type and, if matching has solved for it, its value,
+/// each of which may mention the variables before it.
+#[derive(Clone)]
+struct Entry {
+    name: Identifier,
+    type_: Expression,
+    value: Option<Expression>,
+}
+
+impl Entry {
+    fn mentions(&self, id: &Identifier) -> bool {
+        self.type_.has_free_variable(id)
+            || self
+                .value
+                .as_ref()
+                .is_some_and(|value| value.has_free_variable(id))
+    }
+}
+
+/// Calls `body` in `ctx` extended by each entry of `telescope` in turn.
+fn with_telescope<R>(
+    defs: &Definitions,
+    ctx: &Context,
+    telescope: &[Entry],
+    body: &mut dyn FnMut(&Context) -> crate::Result<R>,
+) -> crate::Result<R> {
+    let Some((entry, rest)) = telescope.split_first() else {
+        return body(ctx);
+    };
+    let env = Environment::from_context(ctx);
+    let type_ = Type::create_type_from_value(try_evaluate(defs, &env, &entry.type_)?);
+    match &entry.value {
+        None => with_telescope(defs, &ctx.extend(&entry.name, &type_), rest, body),
+        Some(value) => {
+            let value = try_evaluate(defs, &env, value)?;
+            with_telescope(defs, &ctx.define(&entry.name, &type_, &value), rest, body)
+        }
+    }
+}
+
+/// The variables in scope in the body of a clause, bound while matching in `telescope`
+/// and by the patterns of the clause in `bindings`.
+///
+/// A variable of a pattern which matched a variable of the telescope takes its place,
+/// which is then defined to be it, so that the body is checked in terms of the patterns.
+fn clause_scope(telescope: &[Entry], bindings: &[Entry]) -> Vec<Entry> {
+    let mut scope = Vec::new();
+    let mut rest: Vec<&Entry> = bindings.iter().collect();
+    for entry in telescope {
+        let matched = rest.iter().position(|binding| {
+            entry.value.is_none()
+                && matches!(&binding.value, Some(Expression::Variable(var)) if *var == entry.name)
+        });
+        let Some(index) = matched else {
+            scope.push(entry.clone());
+            continue;
+        };
+        let binding = rest.remove(index);
+        scope.push(Entry {
+            name: binding.name.clone(),
+            type_: entry.type_.clone(),
+            value: None,
+        });
+        scope.push(Entry {
+            name: entry.name.clone(),
+            type_: entry.type_.clone(),
+            value: Some(Expression::Variable(binding.name.clone())),
+        });
+    }
+    scope.extend(rest.into_iter().cloned());
+    scope
+}
+
+/// Orders `telescope` so that each entry comes after those it mentions,
+/// keeping the order it has where possible, or fails if entries mention each other in a cycle.
+fn sort_telescope(mut telescope: Vec<Entry>) -> Option<Vec<Entry>> {
+    let mut sorted = Vec::new();
+    while !telescope.is_empty() {
+        let ready = telescope
+            .iter()
+            .position(|entry| telescope.iter().all(|other| !entry.mentions(&other.name)))?;
+        sorted.push(telescope.remove(ready));
+    }
+    Some(sorted)
+}
+
+/// A head, the number of parameters of the type it builds, the names of its fields,
+/// and the telescope refined by the case, or `None` if the head can never build what is split.
+type Case = (Head, usize, Vec<Identifier>, Option<Vec<Entry>>);
+
+/// A continuation given a context, the type which remains of a pi type, and entries for its parameters.
+type ParameterScope<'a, R> = dyn FnMut(&Context, &Type, &[Entry]) -> crate::Result<R> + 'a;
+
+/// Binds a fresh variable for each parameter of the pi type `type_`, up to `count` of them
+/// if given, then calls `body` with the type which remains and the entries for the variables.
+///
+/// Returns `None` if `type_` has fewer than `count` parameters.
+fn bind_parameters<R>(
+    defs: &Definitions,
+    ctx: &Context,
+    type_: &Type,
+    count: Option<usize>,
+    fresh: &mut dyn FnMut(&str) -> Identifier,
+    mut entries: Vec<Entry>,
+    body: &mut ParameterScope<'_, R>,
+) -> crate::Result<Option<(Vec<Entry>, R)>> {
+    let pi_type = match type_.deref() {
+        Value::PiType {
+            param_type,
+            tclosure,
+            ..
+        } if count != Some(0) => Some((param_type, tclosure)),
+        _ => None,
+    };
+    let Some((param_type, tclosure)) = pi_type else {
+        if count.is_some_and(|count| count > 0) {
+            return Ok(None);
+        }
+        let result = body(ctx, type_, &entries)?;
+        return Ok(Some((entries, result)));
+    };
+    let name = fresh(tclosure.param.name());
+    entries.push(Entry {
+        name: name.clone(),
+        type_: try_read_back_type(defs, ctx, param_type)?,
+        value: None,
+    });
+    let var = Value::Neutral {
+        neu: Neutral::Variable(name.clone()),
+    };
+    let ret_type = Type::create_type_from_value(tclosure.try_call(defs, &var)?);
+    bind_parameters(
+        defs,
+        &ctx.extend(&name, param_type),
+        &ret_type,
+        count.map(|count| count - 1),
+        fresh,
+        entries,
+        body,
+    )
+}
+
+/// The variable `val` is, if it is one bound while matching which has not been solved for.
+fn flexible(telescope: &[Entry], val: &Value) -> Option<Identifier> {
+    match val {
+        Value::Neutral {
+            neu: Neutral::Variable(id),
+        } => telescope
+            .iter()
+            .any(|entry| entry.name == *id && entry.value.is_none())
+            .then(|| id.clone()),
+        _ => None,
+    }
+}
+
+/// Whether the variable `id` occurs in `val` under constructors alone.
+fn occurs_under_constructors(val: &Value, id: &Identifier) -> bool {
+    arguments(val).is_some_and(|(_, args)| {
+        args.iter().any(|arg| {
+            matches!(arg, Value::Neutral { neu: Neutral::Variable(var) } if var == id)
+                || occurs_under_constructors(arg, id)
+        })
+    })
+}
+
+/// The first step towards making two values equal by solving for variables bound while matching.
+enum Unification {
+    /// The values are already equal.
+    Equal,
+    /// The values are equal only if the variable is.
+    Solved(Identifier, Expression),
+    /// The values are built by different constructors, so can never be equal.
+    Conflict,
+}
+
+/// Finds the first step towards making `lhs` and `rhs` of type `type_` equal.
+fn unify(
+    defs: &Definitions,
+    ctx: &Context,
+    telescope: &[Entry],
+    lhs: &Value,
+    rhs: &Value,
+    type_: &Type,
+) -> crate::Result<Unification> {
+    let lhs_expr = try_read_back_typed(defs, ctx, lhs, type_)?;
+    let rhs_expr = try_read_back_typed(defs, ctx, rhs, type_)?;
+    if alpha_equivalent(&lhs_expr, &rhs_expr).is_ok() {
+        return Ok(Unification::Equal);
+    }
+    for (var, other, other_expr) in [(lhs, rhs, &rhs_expr), (rhs, lhs, &lhs_expr)] {
+        if let Some(id) = flexible(telescope, var) {
+            if !other_expr.has_free_variable(&id) {
+                return Ok(Unification::Solved(id, other_expr.clone()));
+            }
+            if occurs_under_constructors(other, &id) {
+                return Ok(Unification::Conflict);
+            }
+        }
+    }
+    match (arguments(lhs), arguments(rhs)) {
+        (Some((lhs_head, lhs_args)), Some((rhs_head, rhs_args))) => {
+            if lhs_head != rhs_head {
+                return Ok(Unification::Conflict);
+            }
+            unify_spine(
+                defs,
+                ctx,
+                telescope,
+                &lhs_head.type_(defs, ctx, type_)?,
+                &lhs_args,
+                &rhs_args,
+            )
+        }
+        _ => Err(TypeError::new(TypeErrorKind::UnificationStuck {
+            lhs: lhs_expr,
+            rhs: rhs_expr,
+        })),
+    }
+}
+
+/// Finds the first step towards making `lhs` and `rhs` equal as arguments of something of type `type_`.
+fn unify_spine(
+    defs: &Definitions,
+    ctx: &Context,
+    telescope: &[Entry],
+    type_: &Type,
+    lhs: &[Value],
+    rhs: &[Value],
+) -> crate::Result<Unification> {
+    let (types, _) = spine_types(defs, type_, lhs)?;
+    for ((lhs, rhs), type_) in lhs.iter().zip(rhs).zip(&types) {
+        match unify(defs, ctx, telescope, lhs, rhs, type_)? {
+            Unification::Equal => {}
+            unification => return Ok(unification),
+        }
+    }
+    Ok(Unification::Equal)
+}
+
+/// A pattern yet to be matched against `value` of type `type_`.
+#[derive(Clone)]
+struct Constraint<'p> {
+    value: Expression,
+    type_: Expression,
+    pattern: &'p Pattern,
+}
+
+/// A clause as it is matched against the variables bound so far.
+#[derive(Clone)]
+struct Row<'p> {
+    /// The position of the clause in the declaration.
+    index: usize,
+    constraints: Vec<Constraint<'p>>,
+    /// The variables of the patterns matched so far, with their types and values.
+    bindings: Vec<Entry>,
+    /// Each inaccessible pattern matched so far, with the value and type it is matched against.
+    inaccessible: Vec<(Expression, Expression, &'p Expression)>,
+}
+
+/// The result of matching a clause as far as the variables bound so far allow.
+enum Outcome<'p> {
+    /// The clause matches, with every pattern a variable or inaccessible.
+    Matched(Row<'p>),
+    /// The clause matches nothing here.
+    Mismatched,
+    /// The clause matches only once the variable is split into the cases for each constructor.
+    Split(Identifier),
+}
+
+struct Compiler<'a> {
+    defs: &'a Definitions,
+    decl: &'a FunctionDeclaration,
+    /// Every name in use, which bound variables must avoid so as not to capture them.
+    used: Vec<Identifier>,
+    /// The variables bound for the arguments, and the type of the result in terms of them.
+    args: Vec<Entry>,
+    target: Expression,
+    /// Whether each clause has been used in some case.
+    reached: Vec<bool>,
+    /// The body of each clause, elaborated where it was first used.
+    bodies: Vec<Option<Expression>>,
+    /// The arguments of each case no clause covers.
+    missing: Vec<Vec<Pattern>>,
+}
+
+/// Checks the clauses of a declaration, and compiles them to a case tree,
+/// once the function itself has been assumed in `defs`.
+///
+/// Also returns the declaration with the body of each clause a case needs elaborated,
+/// and a warning for each clause which no case needs.
+pub(crate) fn compile(
+    defs: &Definitions,
+    decl: &FunctionDeclaration,
+) -> crate::Result<(Function, FunctionDeclaration, Vec<Warning>)> {
+    let arity = decl.arity();
+    for clause in &decl.clauses {
+        check_clause(clause, arity)?;
+    }
+    let mut used: Vec<Identifier> = defs.names().cloned().collect();
+    used.extend(decl.identifiers());
+    let mut fresh = |base: &str| {
+        let id = fresh_from(base, used.iter());
+        used.push(id.clone());
+        id
+    };
+    let type_ = Type::create_type_from_value(try_evaluate(defs, &Environment::EMPTY, &decl.type_)?);
+    let Some((args, target)) = bind_parameters(
+        defs,
+        &Context::EMPTY,
+        &type_,
+        Some(arity),
+        &mut fresh,
+        Vec::new(),
+        &mut |ctx, type_, _| try_read_back_type(defs, ctx, type_),
+    )?
+    else {
+        return Err(TypeError::new(TypeErrorKind::TooManyPatterns {
+            type_: decl.type_.clone(),
+            found: arity,
+        }));
+    };
+    let rows: Vec<Row> = decl
+        .clauses
+        .iter()
+        .enumerate()
+        .map(|(index, clause)| Row {
+            index,
+            constraints: args
+                .iter()
+                .zip(&clause.patterns)
+                .map(|(arg, pattern)| Constraint {
+                    value: Expression::Variable(arg.name.clone()),
+                    type_: arg.type_.clone(),
+                    pattern,
+                })
+                .collect(),
+            bindings: Vec::new(),
+            inaccessible: Vec::new(),
+        })
+        .collect();
+    let mut compiler = Compiler {
+        defs,
+        decl,
+        used,
+        args: args.clone(),
+        target,
+        reached: vec![false; decl.clauses.len()],
+        bodies: vec![None; decl.clauses.len()],
+        missing: Vec::new(),
+    };
+    let numbered = args.iter().map(|arg| arg.name.clone()).collect();
+    let tree = compiler.compile(args, numbered, &rows)?;
+    let tree = match (tree, compiler.missing.is_empty()) {
+        (Some(tree), true) => tree,
+        _ => {
+            return Err(TypeError::new(TypeErrorKind::MissingCases {
+                function: decl.name.clone(),
+                cases: compiler.missing,
+            }))
+        }
+    };
+    let warnings = decl
+        .clauses
+        .iter()
+        .zip(&compiler.reached)
+        .filter(|(_, reached)| !**reached)
+        .map(|(clause, _)| Warning::UnreachableClause {
+            function: decl.name.clone(),
+            patterns: clause.patterns.clone(),
+        })
+        .collect();
+    let mut elaborated = decl.clone();
+    for (clause, body) in elaborated.clauses.iter_mut().zip(compiler.bodies) {
+        if body.is_some() {
+            clause.body = body;
+        }
+    }
+    Ok((Function { arity, tree }, elaborated, warnings))
+}
+
+/// Checks that a clause has `arity` patterns, binds each variable once,
+/// and has a body exactly when it has no absurd pattern.
+fn check_clause(clause: &Clause, arity: usize) -> crate::Result<()> {
+    if clause.patterns.len() != arity {
+        return Err(TypeError::new(TypeErrorKind::WrongPatternCount {
+            expected: arity,
+            found: clause.patterns.len(),
+        }));
+    }
+    let subpatterns: Vec<&Pattern> = clause
+        .patterns
+        .iter()
+        .flat_map(Pattern::subpatterns)
+        .collect();
+    let mut bound = Vec::new();
+    for pattern in &subpatterns {
+        if let Pattern::Variable(id) = pattern {
+            if bound.contains(&id) {
+                return Err(TypeError::new(TypeErrorKind::ShadowedBinder(id.clone())));
+            }
+            bound.push(id);
+        }
+    }
+    let absurd = subpatterns
+        .iter()
+        .any(|pattern| matches!(pattern, Pattern::Absurd));
+    match (absurd, &clause.body) {
+        (true, Some(_)) => Err(TypeError::new(TypeErrorKind::AbsurdClauseBody(
+            clause.patterns.clone(),
+        ))),
+        (false, None) => Err(TypeError::new(TypeErrorKind::MissingClauseBody(
+            clause.patterns.clone(),
+        ))),
+        _ => Ok(()),
+    }
+}
+
+impl<'p> Compiler<'p> {
+    fn fresh(&mut self, base: &str) -> Identifier {
+        let id = fresh_from(base, self.used.iter());
+        self.used.push(id.clone());
+        id
+    }
+
+    /// Compiles `rows` to a case tree, once the variables of `telescope` are bound,
+    /// numbered at runtime as in `numbered`.
+    ///
+    /// Returns `None` if some case is missing, after recording it.
+    fn compile(
+        &mut self,
+        telescope: Vec<Entry>,
+        numbered: Vec<Identifier>,
+        mut rows: &[Row<'p>],
+    ) -> crate::Result<Option<CaseTree>> {
+        loop {
+            let Some((row, rest)) = rows.split_first() else {
+                if let Some(index) = self.empty_variable(&telescope, &numbered) {
+                    return Ok(Some(CaseTree::Split {
+                        index,
+                        branches: Vec::new(),
+                    }));
+                }
+                let case = self.missing_case(&telescope)?;
+                self.missing.push(case);
+                return Ok(None);
+            };
+            match self.match_row(&telescope, row)? {
+                Outcome::Matched(row) => return self.leaf(&telescope, &numbered, row).map(Some),
+                Outcome::Mismatched => rows = rest,
+                Outcome::Split(var) => {
+                    return self.split(telescope, numbered, rows, &var).map(Some)
+                }
+            }
+        }
+    }
+
+    /// Matches the patterns of `row` against what is known of the variables of `telescope`.
+    fn match_row(&self, telescope: &[Entry], row: &Row<'p>) -> crate::Result<Outcome<'p>> {
+        let defs = self.defs;
+        with_telescope(defs, &Context::EMPTY, telescope, &mut |ctx| {
+            let env = Environment::from_context(ctx);
+            let mut row = row.clone();
+            let mut constraints = std::mem::take(&mut row.constraints);
+            constraints.reverse();
+            while let Some(Constraint {
+                value,
+                type_,
+                pattern,
+            }) = constraints.pop()
+            {
+                let (head, args) = match pattern {
+                    Pattern::Variable(id) => {
+                        row.bindings.push(Entry {
+                            name: id.clone(),
+                            type_,
+                            value: Some(value),
+                        });
+                        continue;
+                    }
+                    Pattern::Inaccessible(expr) => {
+                        row.inaccessible.push((value, type_, expr));
+                        continue;
+                    }
+                    Pattern::Absurd => (None, Vec::new()),
+                    _ => match pattern.head() {
+                        Some((head, args)) => (Some(head), args),
+                        None => unreachable!("other patterns have heads"),
+                    },
+                };
+                let val = try_evaluate(defs, &env, &value)?;
+                let type_ = Type::create_type_from_value(try_evaluate(defs, &env, &type_)?);
+                let Some((heads, params)) = heads(defs, &type_)? else {
+                    return Err(TypeError::new(TypeErrorKind::CannotSplit {
+                        expr: try_read_back_typed(defs, ctx, &val, &type_)?,
+                        type_: try_read_back_type(defs, ctx, &type_)?,
+                    }));
+                };
+                let mismatch = || -> crate::Result<TypeError> {
+                    Ok(TypeError::new(TypeErrorKind::PatternMismatch {
+                        pattern: pattern.clone(),
+                        type_: try_read_back_type(defs, ctx, &type_)?,
+                    }))
+                };
+                if head.as_ref().is_some_and(|head| !heads.contains(head)) {
+                    return Err(mismatch()?);
+                }
+                let Some((val_head, val_args)) = arguments(&val) else {
+                    return match flexible(telescope, &val) {
+                        Some(var) => Ok(Outcome::Split(var)),
+                        None => Err(TypeError::new(TypeErrorKind::CannotSplit {
+                            expr: try_read_back_typed(defs, ctx, &val, &type_)?,
+                            type_: try_read_back_type(defs, ctx, &type_)?,
+                        })),
+                    };
+                };
+                let Some(head) = head else {
+                    return Err(TypeError::new(TypeErrorKind::NotEmpty {
+                        type_: try_read_back_type(defs, ctx, &type_)?,
+                    }));
+                };
+                if head != val_head {
+                    return Ok(Outcome::Mismatched);
+                }
+                let (types, _) = spine_types(defs, &head.type_(defs, ctx, &type_)?, &val_args)?;
+                let fields = val_args[params.len()..].iter().zip(&types[params.len()..]);
+                if fields.len() != args.len() {
+                    return Err(mismatch()?);
+                }
+                for ((field, field_type), arg) in fields.zip(args).rev() {
+                    constraints.push(Constraint {
+                        value: try_read_back_typed(defs, ctx, field, field_type)?,
+                        type_: try_read_back_type(defs, ctx, field_type)?,
+                        pattern: arg,
+                    });
+                }
+            }
+            Ok(Outcome::Matched(row))
+        })
+    }
+
+    /// Checks the body of a clause which matches, giving the leaf which computes it.
+    fn leaf(
+        &mut self,
+        telescope: &[Entry],
+        numbered: &[Identifier],
+        row: Row<'p>,
+    ) -> crate::Result<CaseTree> {
+        self.reached[row.index] = true;
+        let Some(body) = &self.decl.clauses[row.index].body else {
+            unreachable!("an absurd pattern never matches")
+        };
+        let defs = self.defs;
+        let target = &self.target;
+        let scope = clause_scope(telescope, &row.bindings);
+        let mark = defs.metas().mark();
+        let body = with_telescope(defs, &Context::EMPTY, &scope, &mut |ctx| {
+            let env = Environment::from_context(ctx);
+            for (value, type_, expr) in &row.inaccessible {
+                let type_ = Type::create_type_from_value(try_evaluate(defs, &env, type_)?);
+                let expr = elaborate(defs, ctx, expr, &type_)?;
+                judgmentally_equal(
+                    defs,
+                    ctx,
+                    &try_evaluate(defs, &env, &expr)?,
+                    &try_evaluate(defs, &env, value)?,
+                    &type_,
+                )?;
+            }
+            let target = Type::create_type_from_value(try_evaluate(defs, &env, target)?);
+            elaborate(defs, ctx, body, &target)
+        })?;
+        // the variables bound while matching are named by the compiler,
+        // and those with values need not be shown in goals
+        let internal: Vec<Identifier> = telescope.iter().map(|entry| entry.name.clone()).collect();
+        defs.metas_mut().hide_definitions(mark, &internal);
+        self.bodies[row.index].get_or_insert_with(|| body.clone());
+        // the variables of the patterns are given by the values they matched,
+        // in terms of the values numbered so far
+        let body = apply_all(
+            lambdas(row.bindings.iter().map(|entry| &entry.name), body.clone()),
+            row.bindings.iter().filter_map(|entry| entry.value.clone()),
+        );
+        let leaf = try_evaluate(defs, &Environment::EMPTY, &lambdas(numbered.iter(), body))?;
+        Ok(CaseTree::Leaf(leaf))
+    }
+
+    /// Splits the variable `var` into a case for each constructor which could build it.
+    fn split(
+        &mut self,
+        telescope: Vec<Entry>,
+        numbered: Vec<Identifier>,
+        rows: &[Row<'p>],
+        var: &Identifier,
+    ) -> crate::Result<CaseTree> {
+        let Some(index) = numbered.iter().position(|id| id == var) else {
+            unreachable!("every variable bound while matching is numbered")
+        };
+        let mut branches = Vec::new();
+        let mut possible = false;
+        for (head, params, names, telescope) in self.cases(&telescope, var)? {
+            let Some(telescope) = telescope else {
+                continue;
+            };
+            possible = true;
+            let mut numbered = numbered.clone();
+            numbered.extend(names);
+            if let Some(tree) = self.compile(telescope, numbered, rows)? {
+                branches.push((head, params, tree));
+            }
+        }
+        if !possible {
+            // the clause whose pattern needed the split covers the case, though it has no cases
+            self.reached[rows[0].index] = true;
+        }
+        Ok(CaseTree::Split { index, branches })
+    }
+
+    /// Each head which could build `var`, refining `telescope` by the case.
+    fn cases(&mut self, telescope: &[Entry], var: &Identifier) -> crate::Result<Vec<Case>> {
+        let defs = self.defs;
+        let Some(position) = telescope.iter().position(|entry| entry.name == *var) else {
+            unreachable!("only bound variables are split")
+        };
+        // each head with the parameters of the type, its fields, the type it constructs and its value
+        let mut fresh = |base: &str| self.fresh(base);
+        let cases = with_telescope(defs, &Context::EMPTY, telescope, &mut |ctx| {
+            let env = Environment::from_context(ctx);
+            let type_ = try_evaluate(defs, &env, &telescope[position].type_)?;
+            let type_ = Type::create_type_from_value(type_);
+            let Some((heads, params)) = heads(defs, &type_)? else {
+                return Err(TypeError::new(TypeErrorKind::CannotSplit {
+                    expr: Expression::Variable(var.clone()),
+                    type_: try_read_back_type(defs, ctx, &type_)?,
+                }));
+            };
+            let mut cases = Vec::new();
+            for head in heads {
+                let (_, head_type) = spine_types(defs, &head.type_(defs, ctx, &type_)?, &params)?;
+                let bound = bind_parameters(
+                    defs,
+                    ctx,
+                    &head_type,
+                    None,
+                    &mut fresh,
+                    Vec::new(),
+                    &mut |ctx, conclusion, fields| {
+                        let args = params.iter().cloned().chain(fields.iter().map(|field| {
+                            Value::Neutral {
+                                neu: Neutral::Variable(field.name.clone()),
+                            }
+                        }));
+                        let value = head.construct(&type_, args.collect())?;
+                        Ok((
+                            try_read_back_type(defs, ctx, conclusion)?,
+                            try_read_back_typed(defs, ctx, &value, conclusion)?,
+                        ))
+                    },
+                )?;
+                let Some((fields, (conclusion, value))) = bound else {
+                    unreachable!("any number of parameters may be bound when none is given")
+                };
+                cases.push((head, params.len(), fields, conclusion, value));
+            }
+            Ok(cases)
+        })?;
+        let mut refined = Vec::new();
+        for (head, params, fields, conclusion, value) in cases {
+            let mut telescope = telescope.to_vec();
+            telescope[position].value = Some(value);
+            let names = fields.iter().map(|field| field.name.clone()).collect();
+            telescope.splice(position..position, fields);
+            let telescope = self.unify_indices(telescope, var, &conclusion)?;
+            refined.push((head, params, names, telescope));
+        }
+        Ok(refined)
+    }
+
+    /// The index of a numbered variable which no constructor could build,
+    /// so that a case no clause covers need not be.
+    fn empty_variable(&mut self, telescope: &[Entry], numbered: &[Identifier]) -> Option<usize> {
+        numbered.iter().position(|var| {
+            let undefined = telescope
+                .iter()
+                .any(|entry| entry.name == *var && entry.value.is_none());
+            // a variable whose cases cannot be worked out is not known to be empty
+            undefined
+                && self
+                    .cases(telescope, var)
+                    .is_ok_and(|cases| cases.iter().all(|(_, _, _, telescope)| telescope.is_none()))
+        })
+    }
+
+    /// Solves for variables of `telescope` so that the type of `var` is `conclusion`,
+    /// giving `None` if they can never be equal.
+    fn unify_indices(
+        &self,
+        mut telescope: Vec<Entry>,
+        var: &Identifier,
+        conclusion: &Expression,
+    ) -> crate::Result<Option<Vec<Entry>>> {
+        let defs = self.defs;
+        loop {
+            let unification = with_telescope(defs, &Context::EMPTY, &telescope, &mut |ctx| {
+                let env = Environment::from_context(ctx);
+                let Some(entry) = telescope.iter().find(|entry| entry.name == *var) else {
+                    unreachable!("only bound variables are split")
+                };
+                let expected = try_evaluate(defs, &env, &entry.type_)?;
+                let found = try_evaluate(defs, &env, conclusion)?;
+                match (&found, &expected) {
+                    (
+                        Value::Data {
+                            name,
+                            args: found_args,
+                        },
+                        Value::Data {
+                            args: expected_args,
+                            ..
+                        },
+                    ) => unify_spine(
+                        defs,
+                        ctx,
+                        &telescope,
+                        defs.lookup_type(name)?,
+                        found_args,
+                        expected_args,
+                    ),
+                    (
+                        Value::IdType {
+                            type_,
+                            lhs: found_lhs,
+                            rhs: found_rhs,
+                        },
+                        Value::IdType {
+                            lhs: expected_lhs,
+                            rhs: expected_rhs,
+                            ..
+                        },
+                    ) => {
+                        // a side which no variable can be solved for is left as it is
+                        for (found, expected) in
+                            [(found_lhs, expected_lhs), (found_rhs, expected_rhs)]
+                        {
+                            match unify(defs, ctx, &telescope, found, expected, type_) {
+                                Ok(Unification::Equal) => {}
+                                Err(e)
+                                    if matches!(
+                                        *e.kind,
+                                        TypeErrorKind::UnificationStuck { .. }
+                                    ) => {}
+                                unification => return unification,
+                            }
+                        }
+                        Ok(Unification::Equal)
+                    }
+                    _ => Ok(Unification::Equal),
+                }
+            })?;
+            match unification {
+                Unification::Equal => return Ok(Some(telescope)),
+                Unification::Conflict => return Ok(None),
+                Unification::Solved(id, value) => {
+                    for entry in &mut telescope {
+                        if entry.name == id {
+                            entry.value = Some(value.clone());
+                        }
+                    }
+                    telescope = sort_telescope(telescope).ok_or_else(|| {
+                        TypeError::new(TypeErrorKind::UnificationStuck {
+                            lhs: Expression::Variable(id),
+                            rhs: value,
+                        })
+                    })?;
+                }
+            }
+        }
+    }
+
+    /// The arguments of a case which no clause covers, as patterns.
+    fn missing_case(&self, telescope: &[Entry]) -> crate::Result<Vec<Pattern>> {
+        let defs = self.defs;
+        with_telescope(defs, &Context::EMPTY, telescope, &mut |ctx| {
+            let env = Environment::from_context(ctx);
+            self.args
+                .iter()
+                .map(|arg| {
+                    let val = try_evaluate(defs, &env, &Expression::Variable(arg.name.clone()))?;
+                    let type_ = Type::create_type_from_value(try_evaluate(defs, &env, &arg.type_)?);
+                    to_pattern(defs, ctx, &val, &type_)
+                })
+                .collect()
+        })
+    }
+}
+
+/// A pattern matching exactly `val` of type `type_`, up to its neutral parts.
+fn to_pattern(
+    defs: &Definitions,
+    ctx: &Context,
+    val: &Value,
+    type_: &Type,
+) -> crate::Result<Pattern> {
+    if let Value::Neutral {
+        neu: Neutral::Variable(id),
+    } = val
+    {
+        return Ok(Pattern::Variable(id.clone()));
+    }
+    if let (Some((head, args)), Some((_, params))) = (arguments(val), heads(defs, type_)?) {
+        let (types, _) = spine_types(defs, &head.type_(defs, ctx, type_)?, &args)?;
+        let fields = args[params.len()..]
+            .iter()
+            .zip(&types[params.len()..])
+            .map(|(field, field_type)| to_pattern(defs, ctx, field, field_type))
+            .collect::<crate::Result<_>>()?;
+        return Ok(head.pattern(fields));
+    }
+    try_read_back_typed(defs, ctx, val, type_).map(Pattern::Inaccessible)
+}
@@ -1,90 +1,495 @@
 //! Functions for reading back [`Value`]s as [`Expression`]s.
 
 use crate::environment::{Context, Definitions};
-use crate::evaluation::do_apply;
+use crate::evaluation::{try_apply, try_first, try_second};
 use crate::expression::Expression;
 use crate::identifier::fresh_identifier;
-use crate::typing::synth_type;
-use crate::value::{Neutral, Type, Value};
+use crate::inductive::eliminator_name;
+use crate::meta::{force, force_type, spine_type};
+use crate::record::{projection_name, try_project};
+use crate::surface::Binder;
+use crate::typing::{j_base_type, j_motive_type, motive_type, natrec_step_type, synth_type};
+use crate::value::{Closure, Neutral, Type, Value};
+use crate::{Identifier, Level, Natural, TypeError, TypeErrorKind};
 use std::ops::Deref;
 
+fn internal_error(msg: String) -> TypeError {
+    TypeError::new(TypeErrorKind::Internal(msg))
+}
+
 /// Reads back a [`Type`]d [`Value`] to an [`Expression`] in beta-normal, eta-long form.
+///
+/// # Panics
+///
+/// Panics if `val` is not of type `type_`;
+/// see [`try_read_back_typed`] for a version which does not panic.
+///
+/// # Examples
+///
+/// ```
+/// use alpha::environment::{Context, Definitions, Environment};
+/// use alpha::evaluation::evaluate;
+/// use alpha::parse::parse;
+/// use alpha::read_back::read_back_typed;
+/// use alpha::typing::synth_type;
+///
+/// let defs = Definitions::new();
+/// let normal_form = |expr: &str| {
+///     let expr = parse(expr).unwrap();
+///     let type_ = synth_type(&defs, &Context::EMPTY, &expr).unwrap();
+///     let val = evaluate(&defs, &Environment::EMPTY, &expr);
+///     read_back_typed(&defs, &Context::EMPTY, &val, &type_).to_string()
+/// };
+///
+/// // eta: every element of the unit type is the single one
+/// assert_eq!(normal_form(r"(\x. x : (x : Unit) → Unit)"), "\u{03BB}x.tt");
+///
+/// // eliminations of a variable are stuck, but computed on a constructor
+/// assert_eq!(
+///     normal_form(r"(\b. if (\_. Nat) 1 0 b : (b : Bool) → Nat)"),
+///     "\u{03BB}b.if(\u{03BB}x.Nat)(1)(0)(b)"
+/// );
+/// assert_eq!(normal_form(r"if (\_. Nat) 1 0 false"), "0");
+/// assert_eq!(
+///     normal_form(r"(\e. absurd (\_. Nat) e : (e : Empty) → Nat)"),
+///     "\u{03BB}e.absurd(\u{03BB}x.Nat)(e)"
+/// );
+///
+/// // a wildcard parameter is named only where it is used
+/// assert_eq!(
+///     normal_form(r"(\x y. x : Nat → Nat → Nat)"),
+///     "\u{03BB}x.\u{03BB}_.x"
+/// );
+/// ```
 pub fn read_back_typed(defs: &Definitions, ctx: &Context, val: &Value, type_: &Type) -> Expression {
+    try_read_back_typed(defs, ctx, val, type_).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Reads back a [`Type`]d [`Value`] to an [`Expression`] in beta-normal, eta-long form,
+/// failing if `val` is not of type `type_`.
+pub fn try_read_back_typed(
+    defs: &Definitions,
+    ctx: &Context,
+    val: &Value,
+    type_: &Type,
+) -> crate::Result<Expression> {
+    let type_ = force_type(defs, type_)?;
+    let type_: &Type = &type_;
+    let val = force(defs, val)?;
+    let val = val.as_ref();
     match type_.deref() {
         Value::PiType {
             param_type,
             tclosure,
+            implicit,
         } => {
             let fresh_id = fresh_identifier(defs, ctx, tclosure);
             let fresh_var = Value::Neutral {
                 neu: Neutral::Variable(fresh_id.clone()),
             };
-            let ret_val = read_back_typed(
+            let ret_val = try_read_back_typed(
                 defs,
                 &ctx.extend(&fresh_id, param_type),
-                &do_apply(defs, val, &fresh_var),
-                &Type::create_type_from_value(tclosure.call(defs, &fresh_var)),
-            );
-            Expression::Lambda {
-                param: fresh_id,
+                &try_apply(defs, val, &fresh_var)?,
+                &Type::create_type_from_value(tclosure.try_call(defs, &fresh_var)?),
+            )?;
+            Ok(Expression::Lambda {
+                param: binder_name(tclosure, fresh_id, &ret_val),
                 param_type: None,
                 ret_val: Box::new(ret_val),
-            }
+                implicit: *implicit,
+            })
         }
-        Value::Universe => match val {
-            Value::PiType {
-                param_type,
-                tclosure,
-            } => {
-                let fresh_id = fresh_identifier(defs, ctx, tclosure);
-                let fresh_var = Value::Neutral {
-                    neu: Neutral::Variable(fresh_id.clone()),
-                };
-                let ret_type = read_back_typed(
+        Value::SigmaType { fst_type, tclosure } => {
+            let fst = try_first(val)?;
+            let snd_type = Type::create_type_from_value(tclosure.try_call(defs, &fst)?);
+            Ok(Expression::Pair {
+                fst: Box::new(try_read_back_typed(defs, ctx, &fst, fst_type)?),
+                snd: Box::new(try_read_back_typed(
                     defs,
-                    &ctx.extend(&fresh_id, param_type),
-                    &tclosure.call(defs, &fresh_var),
-                    &Type::UNIVERSE,
-                );
-                let param_type = read_back_typed(defs, ctx, param_type, &Type::UNIVERSE);
-                Expression::PiType {
-                    tparam: fresh_id,
-                    tparam_type: Box::new(param_type),
-                    ret_type: Box::new(ret_type),
-                }
+                    ctx,
+                    &try_second(val)?,
+                    &snd_type,
+                )?),
+            })
+        }
+        Value::Universe(_) => read_back_type_value(defs, ctx, val),
+        Value::IdType { type_, .. } => match val {
+            Value::Refl { elem } => Ok(Expression::Refl {
+                elem: Box::new(try_read_back_typed(defs, ctx, elem, type_)?),
+            }),
+            Value::Neutral { neu } => try_read_back_neutral(defs, ctx, neu),
+            _ => Err(internal_error(format!(
+                "Cannot read back `{}` as a `{}` because it is not of that type.",
+                val, type_
+            ))),
+        },
+        Value::Nat => match val {
+            Value::Numeral(n) => Ok(Expression::Numeral(n.clone())),
+            Value::Succ { pred } => Ok(Expression::Succ {
+                pred: Box::new(try_read_back_typed(defs, ctx, pred, type_)?),
+            }),
+            Value::Neutral { neu } => try_read_back_neutral(defs, ctx, neu),
+            _ => Err(internal_error(format!(
+                "Cannot read back `{}` as a `{}` because it is not of that type.",
+                val, type_
+            ))),
+        },
+        Value::Bool => match val {
+            Value::True => Ok(Expression::True),
+            Value::False => Ok(Expression::False),
+            Value::Neutral { neu } => try_read_back_neutral(defs, ctx, neu),
+            _ => Err(internal_error(format!(
+                "Cannot read back `{}` as a `{}` because it is not of that type.",
+                val, type_
+            ))),
+        },
+        Value::Data { .. } => match val {
+            Value::Constructor { name, args } => {
+                read_back_spine(defs, ctx, Expression::Variable(name.clone()), args)
             }
-            Value::Universe => Expression::Universe,
-            Value::Neutral { neu } => read_back_neutral(defs, ctx, neu),
-            _ => panic!(
-                "Cannot read back `{}` as a type because it is not a type.",
-                val
-            ),
+            Value::Neutral { neu } => try_read_back_neutral(defs, ctx, neu),
+            _ => Err(internal_error(format!(
+                "Cannot read back `{}` as a `{}` because it is not of that type.",
+                val, type_
+            ))),
         },
-        Value::Neutral { .. } => match val {
-            Value::Neutral { neu } => read_back_neutral(defs, ctx, neu),
-            _ => panic!(
+        // eta: every element of a record type is the record of its projections
+        Value::RecordType { name, args } => {
+            let record = defs.record(name)?;
+            let mut fields = Vec::new();
+            let mut exprs = Vec::new();
+            for (index, field) in record.fields.iter().enumerate() {
+                let field_val = try_project(defs, name, field, args, val)?;
+                let field_type = record.field_type(defs, index, args, &fields)?;
+                exprs.push(try_read_back_typed(defs, ctx, &field_val, &field_type)?);
+                fields.push(field_val);
+            }
+            Ok(Expression::Record { fields: exprs })
+        }
+        // eta: every element of the unit type is the single one
+        Value::Unit => Ok(Expression::Star),
+        Value::Empty | Value::Neutral { .. } => match val {
+            Value::Neutral { neu } => try_read_back_neutral(defs, ctx, neu),
+            _ => Err(internal_error(format!(
                 "Cannot read back `{}` as a `{}` because it is not of that type.",
                 val, type_
-            ),
+            ))),
         },
-        _ => panic!("Cannot read back as `{}` because it is not a type.", type_),
+        _ => Err(internal_error(format!(
+            "Cannot read back as `{}` because it is not a type.",
+            type_
+        ))),
+    }
+}
+
+/// Reads back a [`Type`] to an [`Expression`] in beta-normal, eta-long form.
+///
+/// # Panics
+///
+/// Panics if `type_` is ill-formed;
+/// see [`try_read_back_type`] for a version which does not panic.
+pub fn read_back_type(defs: &Definitions, ctx: &Context, type_: &Type) -> Expression {
+    try_read_back_type(defs, ctx, type_).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Reads back a [`Type`] to an [`Expression`] in beta-normal, eta-long form,
+/// failing if `type_` is ill-formed.
+pub fn try_read_back_type(
+    defs: &Definitions,
+    ctx: &Context,
+    type_: &Type,
+) -> crate::Result<Expression> {
+    read_back_type_value(defs, ctx, type_)
+}
+
+/// Reads back a [`Value`] in some universe; the level of the universe does not matter.
+fn read_back_type_value(
+    defs: &Definitions,
+    ctx: &Context,
+    val: &Value,
+) -> crate::Result<Expression> {
+    match force(defs, val)?.as_ref() {
+        Value::PiType {
+            param_type,
+            tclosure,
+            implicit,
+        } => {
+            let (tparam, tparam_type, ret_type) =
+                read_back_family(defs, ctx, param_type, tclosure)?;
+            Ok(Expression::PiType {
+                tparam,
+                tparam_type: Box::new(tparam_type),
+                ret_type: Box::new(ret_type),
+                implicit: *implicit,
+            })
+        }
+        Value::SigmaType { fst_type, tclosure } => {
+            let (tparam, tparam_type, snd_type) = read_back_family(defs, ctx, fst_type, tclosure)?;
+            Ok(Expression::SigmaType {
+                tparam,
+                tparam_type: Box::new(tparam_type),
+                snd_type: Box::new(snd_type),
+            })
+        }
+        Value::IdType { type_, lhs, rhs } => Ok(Expression::IdType {
+            type_: Box::new(try_read_back_type(defs, ctx, type_)?),
+            lhs: Box::new(try_read_back_typed(defs, ctx, lhs, type_)?),
+            rhs: Box::new(try_read_back_typed(defs, ctx, rhs, type_)?),
+        }),
+        Value::Nat => Ok(Expression::Nat),
+        Value::Bool => Ok(Expression::Bool),
+        Value::Unit => Ok(Expression::Unit),
+        Value::Empty => Ok(Expression::Empty),
+        Value::Universe(level) => Ok(Expression::Universe(level.clone())),
+        Value::Data { name, args } | Value::RecordType { name, args } => {
+            read_back_spine(defs, ctx, Expression::Variable(name.clone()), args)
+        }
+        Value::Neutral { neu } => try_read_back_neutral(defs, ctx, neu),
+        _ => Err(internal_error(format!(
+            "Cannot read back `{}` as a type because it is not a type.",
+            val
+        ))),
+    }
+}
+
+/// Reads back a type `param_type` and a family of types over it,
+/// as found in pi and sigma types.
+fn read_back_family(
+    defs: &Definitions,
+    ctx: &Context,
+    param_type: &Type,
+    tclosure: &Closure,
+) -> crate::Result<(Identifier, Expression, Expression)> {
+    let fresh_id = fresh_identifier(defs, ctx, tclosure);
+    let fresh_var = Value::Neutral {
+        neu: Neutral::Variable(fresh_id.clone()),
+    };
+    let family = read_back_type_value(
+        defs,
+        &ctx.extend(&fresh_id, param_type),
+        &tclosure.try_call(defs, &fresh_var)?,
+    )?;
+    let param_type = try_read_back_type(defs, ctx, param_type)?;
+    Ok((binder_name(tclosure, fresh_id, &family), param_type, family))
+}
+
+/// The name binding `fresh_id` in `body`, read back from the parameter of `closure`,
+/// which stays a wildcard if the parameter was one and `body` does not mention it,
+/// nor a variable the wildcard would hide.
+fn binder_name(closure: &Closure, fresh_id: Identifier, body: &Expression) -> Identifier {
+    let wildcard = Binder::wildcard();
+    if closure.param == wildcard
+        && !body.has_free_variable(&fresh_id)
+        && !body.has_free_variable(&wildcard)
+    {
+        wildcard
+    } else {
+        fresh_id
+    }
+}
+
+/// Reads back a global constant `head` applied to `args`, reading back each argument
+/// at the type the constant expects.
+fn read_back_spine(
+    defs: &Definitions,
+    ctx: &Context,
+    head: Expression,
+    args: &[Value],
+) -> crate::Result<Expression> {
+    let mut type_ = match &head {
+        Expression::Instance { name, levels } => defs.instantiate_type(name, levels)?,
+        Expression::Variable(name) => defs.instantiate_type(name, &[])?,
+        Expression::Meta(meta) => defs.metas().get(*meta)?.type_.clone(),
+        _ => unreachable!("only constants head a spine"),
+    };
+    let mut expr = head;
+    for arg in args {
+        let Value::PiType {
+            param_type,
+            tclosure,
+            implicit,
+        } = type_.deref()
+        else {
+            return Err(internal_error(format!(
+                "Cannot read back `{}` applied to `{}` because it is ill-typed.",
+                expr, arg
+            )));
+        };
+        let arg_expr = try_read_back_typed(defs, ctx, arg, param_type)?;
+        let ret_type = Type::create_type_from_value(tclosure.try_call(defs, arg)?);
+        expr = Expression::Application {
+            func: Box::new(expr),
+            arg: Box::new(arg_expr),
+            implicit: *implicit,
+        };
+        type_ = ret_type;
     }
+    Ok(expr)
 }
 
 /// Reads back a [`Neutral`] value to an [`Expression`] in beta-normal, eta-long form.
+///
+/// # Panics
+///
+/// Panics if `neu` is ill-typed;
+/// see [`try_read_back_neutral`] for a version which does not panic.
 pub fn read_back_neutral(defs: &Definitions, ctx: &Context, neu: &Neutral) -> Expression {
+    try_read_back_neutral(defs, ctx, neu).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Reads back a [`Neutral`] value to an [`Expression`] in beta-normal, eta-long form,
+/// failing if `neu` is ill-typed.
+pub fn try_read_back_neutral(
+    defs: &Definitions,
+    ctx: &Context,
+    neu: &Neutral,
+) -> crate::Result<Expression> {
     match neu {
-        Neutral::Variable(id) => Expression::Variable(id.clone()),
+        Neutral::Variable(id) => Ok(Expression::Variable(id.clone())),
+        Neutral::Instance { name, levels } => Ok(Expression::Instance {
+            name: name.clone(),
+            levels: levels.clone(),
+        }),
         Neutral::Application { func, arg } => {
-            let func = read_back_neutral(defs, ctx, func);
-            let arg_type = match synth_type(defs, ctx, &func).map(Into::into) {
-                Ok(Value::PiType { param_type, .. }) => param_type,
-                _ => panic!("Cannot read back `{}` because it is ill-typed.", neu),
+            let func = try_read_back_neutral(defs, ctx, func)?;
+            let (arg_type, implicit) = match synth_type(defs, ctx, &func).map(Into::into) {
+                Ok(Value::PiType {
+                    param_type,
+                    implicit,
+                    ..
+                }) => (param_type, implicit),
+                _ => {
+                    return Err(internal_error(format!(
+                        "Cannot read back `{}` because it is ill-typed.",
+                        neu
+                    )))
+                }
             };
-            Expression::Application {
+            Ok(Expression::Application {
                 func: Box::new(func),
-                arg: Box::new(read_back_typed(defs, ctx, arg, &arg_type)),
+                arg: Box::new(try_read_back_typed(defs, ctx, arg, &arg_type)?),
+                implicit,
+            })
+        }
+        Neutral::J { motive, base, eq } => {
+            let eq = try_read_back_neutral(defs, ctx, eq)?;
+            let Ok(Value::IdType { type_, .. }) = synth_type(defs, ctx, &eq).map(Into::into) else {
+                return Err(internal_error(format!(
+                    "Cannot read back `{}` because it is ill-typed.",
+                    neu
+                )));
+            };
+            // any level of universe will do for reading back a motive
+            let motive_type = j_motive_type(defs, ctx, &type_, Level::ZERO)?;
+            let base_type = j_base_type(defs, ctx, &type_, motive)?;
+            Ok(Expression::J {
+                motive: Box::new(try_read_back_typed(defs, ctx, motive, &motive_type)?),
+                base: Box::new(try_read_back_typed(defs, ctx, base, &base_type)?),
+                eq: Box::new(eq),
+            })
+        }
+        Neutral::NatRec {
+            motive,
+            base,
+            step,
+            target,
+        } => {
+            let motive_type = motive_type(defs, ctx, Expression::Nat, Level::ZERO)?;
+            let base_type = try_apply(defs, motive, &Value::Numeral(Natural::ZERO))?;
+            let step_type = natrec_step_type(defs, ctx, motive)?;
+            Ok(Expression::NatRec {
+                motive: Box::new(try_read_back_typed(defs, ctx, motive, &motive_type)?),
+                base: Box::new(try_read_back_typed(
+                    defs,
+                    ctx,
+                    base,
+                    &Type::create_type_from_value(base_type),
+                )?),
+                step: Box::new(try_read_back_typed(defs, ctx, step, &step_type)?),
+                target: Box::new(try_read_back_neutral(defs, ctx, target)?),
+            })
+        }
+        Neutral::If {
+            motive,
+            on_true,
+            on_false,
+            target,
+        } => {
+            let motive_type = motive_type(defs, ctx, Expression::Bool, Level::ZERO)?;
+            let true_type = try_apply(defs, motive, &Value::True)?;
+            let false_type = try_apply(defs, motive, &Value::False)?;
+            Ok(Expression::If {
+                motive: Box::new(try_read_back_typed(defs, ctx, motive, &motive_type)?),
+                on_true: Box::new(try_read_back_typed(
+                    defs,
+                    ctx,
+                    on_true,
+                    &Type::create_type_from_value(true_type),
+                )?),
+                on_false: Box::new(try_read_back_typed(
+                    defs,
+                    ctx,
+                    on_false,
+                    &Type::create_type_from_value(false_type),
+                )?),
+                target: Box::new(try_read_back_neutral(defs, ctx, target)?),
+            })
+        }
+        Neutral::Absurd { motive, target } => {
+            let motive_type = motive_type(defs, ctx, Expression::Empty, Level::ZERO)?;
+            Ok(Expression::Absurd {
+                motive: Box::new(try_read_back_typed(defs, ctx, motive, &motive_type)?),
+                target: Box::new(try_read_back_neutral(defs, ctx, target)?),
+            })
+        }
+        Neutral::Eliminator {
+            family,
+            levels,
+            args,
+            target,
+        } => {
+            let elim = Expression::Instance {
+                name: eliminator_name(family),
+                levels: levels.clone(),
+            };
+            Ok(Expression::Application {
+                func: Box::new(read_back_spine(defs, ctx, elim, args)?),
+                arg: Box::new(try_read_back_neutral(defs, ctx, target)?),
+                implicit: false,
+            })
+        }
+        Neutral::Projection {
+            record,
+            field,
+            args,
+            target,
+        } => {
+            let projection = Expression::Variable(projection_name(record, field));
+            Ok(Expression::Application {
+                func: Box::new(read_back_spine(defs, ctx, projection, args)?),
+                arg: Box::new(try_read_back_neutral(defs, ctx, target)?),
+                implicit: false,
+            })
+        }
+        Neutral::Function { name, args } => {
+            read_back_spine(defs, ctx, Expression::Variable(name.clone()), args)
+        }
+        Neutral::Meta { meta, args } => {
+            let solved = defs.metas().get(*meta)?.solution.is_some();
+            if solved {
+                // the metavariable was solved after this value was computed
+                let type_ = spine_type(defs, *meta, args)?;
+                try_read_back_typed(defs, ctx, &Value::Neutral { neu: neu.clone() }, &type_)
+            } else {
+                read_back_spine(defs, ctx, Expression::Meta(*meta), args)
             }
         }
+        Neutral::First { pair } => Ok(Expression::First {
+            pair: Box::new(try_read_back_neutral(defs, ctx, pair)?),
+        }),
+        Neutral::Second { pair } => Ok(Expression::Second {
+            pair: Box::new(try_read_back_neutral(defs, ctx, pair)?),
+        }),
     }
 }
@@ -0,0 +1,225 @@
+//! Declarations of record types, and the constants they generate.
+
+use crate::environment::{Context, Definitions, Environment};
+use crate::evaluation::{try_apply, try_evaluate};
+use crate::expression::Expression;
+use crate::identifier::fresh_from;
+use crate::inductive::{check_binders, check_telescope, lambdas, pi_types};
+use crate::typing::{apply_all, check_is_type};
+use crate::value::{Neutral, Type, Value};
+use crate::{Identifier, Level, TypeError, TypeErrorKind};
+
+/// A declaration of a record type, such as
+/// `record Point (A : U) where x : A; y : A`.
+///
+/// Declaring it with [`Definitions::declare_record`] defines the record type `name`,
+/// and a projection function for each field, named as given by [`projection_name`].
+/// Elements of the record type are written `{a, b}`, giving each field in order.
+#[derive(Clone, Debug)]
+pub struct RecordDeclaration {
+    pub name: Identifier,
+    /// The parameters of the record type with their types.
+    pub params: Vec<(Identifier, Expression)>,
+    /// The fields with their types, each of which may refer to the parameters and earlier fields.
+    pub fields: Vec<(Identifier, Expression)>,
+}
+
+impl RecordDeclaration {
+    /// Every name the declaration defines.
+    pub(crate) fn defined_names(&self) -> Vec<Identifier> {
+        let mut names = vec![self.name.clone()];
+        names.extend(
+            self.fields
+                .iter()
+                .map(|(field, _)| projection_name(&self.name, field)),
+        );
+        names
+    }
+
+    /// Every expression in the declaration.
+    pub(crate) fn expressions(&self) -> impl Iterator<Item = &Expression> {
+        self.params
+            .iter()
+            .chain(&self.fields)
+            .map(|(_, type_)| type_)
+    }
+}
+
+/// The name of the function projecting `field` from the record type `record`.
+///
+/// # Examples
+///
+/// ```
+/// use alpha::record::projection_name;
+/// use alpha::Identifier;
+///
+/// let name = projection_name(&Identifier::new("Point"), &Identifier::new("x"));
+/// assert_eq!(name.name(), "Point_x");
+/// ```
+pub fn projection_name(record: &Identifier, field: &Identifier) -> Identifier {
+    Identifier::new(&format!("{}_{}", record, field))
+}
+
+/// What evaluation needs to know about a declared record type.
+pub(crate) struct Record {
+    pub(crate) params: usize,
+    pub(crate) fields: Vec<Identifier>,
+    /// The type of each field, as a function of the parameters and the fields before it.
+    field_types: Vec<Value>,
+}
+
+impl Record {
+    /// The position of the field `field`.
+    pub(crate) fn field_index(
+        &self,
+        record: &Identifier,
+        field: &Identifier,
+    ) -> crate::Result<usize> {
+        self.fields
+            .iter()
+            .position(|id| id == field)
+            .ok_or_else(|| {
+                TypeError::new(TypeErrorKind::Internal(format!(
+                    "Record type `{}` has no field `{}`.",
+                    record, field
+                )))
+            })
+    }
+
+    /// The type of the field at `index`, given the parameters and at least the fields before it.
+    pub(crate) fn field_type(
+        &self,
+        defs: &Definitions,
+        index: usize,
+        params: &[Value],
+        fields: &[Value],
+    ) -> crate::Result<Type> {
+        params
+            .iter()
+            .chain(&fields[..index])
+            .try_fold(self.field_types[index].clone(), |func, arg| {
+                try_apply(defs, &func, arg)
+            })
+            .map(Type::create_type_from_value)
+    }
+}
+
+/// Projects `field` from an element `target` of the record type `record`,
+/// failing if `target` is not a record.
+pub(crate) fn try_project(
+    defs: &Definitions,
+    record: &Identifier,
+    field: &Identifier,
+    params: &[Value],
+    target: &Value,
+) -> crate::Result<Value> {
+    match target {
+        Value::Record { fields } => {
+            let index = defs.record(record)?.field_index(record, field)?;
+            Ok(fields[index].clone())
+        }
+        Value::Neutral { neu } => Ok(Value::Neutral {
+            neu: Neutral::Projection {
+                record: record.clone(),
+                field: field.clone(),
+                args: params.to_vec(),
+                target: Box::new(neu.clone()),
+            },
+        }),
+        _ => Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot project `{}` from `{}` because it is not a record.",
+            field, target
+        )))),
+    }
+}
+
+/// Checks the types of `fields` in turn, returning the largest level of their universes.
+fn check_fields(
+    defs: &Definitions,
+    ctx: &Context,
+    fields: &[(Identifier, Expression)],
+) -> crate::Result<Level> {
+    let Some(((id, type_), rest)) = fields.split_first() else {
+        return Ok(Level::ZERO);
+    };
+    let (type_, level) = check_is_type(defs, ctx, type_)?;
+    let type_ = try_evaluate(defs, &Environment::from_context(ctx), &type_)?;
+    let rest_level = check_fields(
+        defs,
+        &ctx.extend(id, &Type::create_type_from_value(type_)),
+        rest,
+    )?;
+    Ok(level.max(&rest_level))
+}
+
+/// Checks a record declaration, returning the level of the universe its type is in.
+pub(crate) fn check_record(defs: &Definitions, decl: &RecordDeclaration) -> crate::Result<Level> {
+    check_binders(&[&decl.name], &decl.params)?;
+    let mut bound = vec![&decl.name];
+    bound.extend(decl.params.iter().map(|(param, _)| param));
+    check_binders(&bound, &decl.fields)?;
+    let mut level = Level::ZERO;
+    check_telescope(defs, &Context::EMPTY, &decl.params, None, &mut |ctx| {
+        level = check_fields(defs, ctx, &decl.fields)?;
+        Ok(())
+    })?;
+    Ok(level)
+}
+
+/// The types of the projection functions of a checked declaration, in order,
+/// along with what evaluation needs to know about the record type.
+///
+/// For `Point` as above, `Point_y` is of type
+/// `∏(A : U)∏(r : Point A)let x : A = Point_x A r in A`.
+pub(crate) fn projections(
+    defs: &Definitions,
+    decl: &RecordDeclaration,
+) -> crate::Result<(Vec<Expression>, Record)> {
+    // the generated binder avoids every name in the declaration, so cannot capture any of them
+    let mut used: Vec<Identifier> = defs.names().cloned().collect();
+    used.extend(decl.defined_names());
+    used.extend(decl.params.iter().map(|(param, _)| param.clone()));
+    used.extend(decl.expressions().flat_map(Expression::identifiers));
+    let target = fresh_from("r", used.iter());
+
+    let param_variables = || {
+        decl.params
+            .iter()
+            .map(|(param, _)| Expression::Variable(param.clone()))
+    };
+    let record_type = apply_all(Expression::Variable(decl.name.clone()), param_variables());
+    let mut telescope = decl.params.clone();
+    telescope.push((target.clone(), record_type));
+    let mut types = Vec::new();
+    let mut field_types = Vec::new();
+    for (index, (_, field_type)) in decl.fields.iter().enumerate() {
+        let earlier = &decl.fields[..index];
+        let type_ = earlier
+            .iter()
+            .rev()
+            .fold(field_type.clone(), |body, (field, type_)| {
+                let projection = apply_all(
+                    Expression::Variable(projection_name(&decl.name, field)),
+                    param_variables().chain([Expression::Variable(target.clone())]),
+                );
+                Expression::Let {
+                    name: field.clone(),
+                    type_: Box::new(type_.clone()),
+                    value: Box::new(projection),
+                    body: Box::new(body),
+                }
+            });
+        types.push(pi_types(&telescope, type_));
+        let function = lambdas(
+            decl.params.iter().chain(earlier).map(|(id, _)| id),
+            field_type.clone(),
+        );
+        field_types.push(try_evaluate(defs, &Environment::EMPTY, &function)?);
+    }
+    let record = Record {
+        params: decl.params.len(),
+        fields: decl.fields.iter().map(|(field, _)| field.clone()).collect(),
+        field_types,
+    };
+    Ok((types, record))
+}
@@ -0,0 +1,373 @@
+//! The surface syntax, as parsed from source text, and its lowering to [`Expression`]s.
+//!
+//! The surface syntax has conveniences that the type checker does not need to know about:
+//! lambdas and pi types binding several parameters at once, as in `λ x y. b` and `(x y : A) → B`,
+//! non-dependent function types `A → B`, and wildcards `_` for parameters never mentioned.
+//! Lowering writes them out in the core syntax, keeping the spans of source text they came from,
+//! so that type errors are reported where the sugar was written.
+
+use crate::expression::Expression;
+use crate::parse::ParseError;
+use crate::{Identifier, Location, Span};
+use std::fmt;
+
+/// A term of the surface syntax, which [`lower`]s to an [`Expression`].
+#[derive(Clone, Debug)]
+pub enum Term {
+    Variable(Identifier),
+    /// A pi type over each of the parameters its `binders` bind, in order.
+    PiType {
+        binders: Vec<Binder>,
+        ret_type: Box<Term>,
+    },
+    /// The type of functions from `domain` to `codomain`, which does not depend on the argument.
+    Arrow {
+        domain: Box<Term>,
+        codomain: Box<Term>,
+    },
+    /// A function of each of the parameters its `binders` bind, in order.
+    Lambda {
+        binders: Vec<Binder>,
+        body: Box<Term>,
+    },
+    /// An application, which is `implicit` if it gives an implicit argument explicitly.
+    Application {
+        func: Box<Term>,
+        arg: Box<Term>,
+        implicit: bool,
+    },
+    SigmaType {
+        tparam: Identifier,
+        tparam_type: Box<Term>,
+        snd_type: Box<Term>,
+    },
+    Pair {
+        fst: Box<Term>,
+        snd: Box<Term>,
+    },
+    Record {
+        fields: Vec<Term>,
+    },
+    First {
+        pair: Box<Term>,
+    },
+    Second {
+        pair: Box<Term>,
+    },
+    IdType {
+        type_: Box<Term>,
+        lhs: Box<Term>,
+        rhs: Box<Term>,
+    },
+    Refl {
+        elem: Box<Term>,
+    },
+    J {
+        motive: Box<Term>,
+        base: Box<Term>,
+        eq: Box<Term>,
+    },
+    Succ {
+        pred: Box<Term>,
+    },
+    NatRec {
+        motive: Box<Term>,
+        base: Box<Term>,
+        step: Box<Term>,
+        target: Box<Term>,
+    },
+    If {
+        motive: Box<Term>,
+        on_true: Box<Term>,
+        on_false: Box<Term>,
+        target: Box<Term>,
+    },
+    Absurd {
+        motive: Box<Term>,
+        target: Box<Term>,
+    },
+    /// A `term` ascribed a `type_`, as in `(a : A)`.
+    Ascription {
+        term: Box<Term>,
+        type_: Box<Term>,
+    },
+    Let {
+        name: Identifier,
+        type_: Box<Term>,
+        value: Box<Term>,
+        body: Box<Term>,
+    },
+    /// An expression of the core syntax, such as `Nat` or `U 1`, which lowers to itself.
+    Core(Expression),
+    /// A term together with the span of source text it came from.
+    Located {
+        span: Span,
+        term: Box<Term>,
+    },
+}
+
+/// Parameters bound together, as in `x y`, `(x y : A)` or `{x y}`.
+///
+/// A wildcard `_` binds a parameter that cannot be mentioned.
+#[derive(Clone, Debug)]
+pub struct Binder {
+    pub names: Vec<Identifier>,
+    /// The type of each of the parameters, which a lambda may leave to be inferred.
+    pub type_: Option<Term>,
+    pub implicit: bool,
+}
+
+impl Binder {
+    /// The name bound by a wildcard, which no variable can refer to.
+    pub fn wildcard() -> Identifier {
+        Identifier::new("_")
+    }
+}
+
+impl Term {
+    /// This term without any [`Located`](Term::Located) wrappers at the top.
+    pub fn unlocated(&self) -> &Term {
+        let mut term = self;
+        while let Term::Located { term: inner, .. } = term {
+            term = inner;
+        }
+        term
+    }
+}
+
+/// Lowers a [`Term`] to the [`Expression`] it abbreviates.
+///
+/// Each [`Located`](Term::Located) term lowers to an expression located at the same span.
+///
+/// # Errors
+///
+/// Parameters bound together share a type, which is lowered once for each of them.
+/// If that type mentions one of the parameters, it would refer to a different variable
+/// for the later ones, so this is rejected.
+///
+/// # Examples
+///
+/// ```
+/// use alpha::environment::{Context, Definitions};
+/// use alpha::parse::{parse, parse_term};
+/// use alpha::surface::lower;
+/// use alpha::typing::synth_type;
+///
+/// let term = parse_term(r"\A (x y : A) {_}. x").unwrap();
+/// let expr = lower(&term).unwrap();
+/// assert_eq!(expr.to_string(), "\u{03BB}A.\u{03BB}(x : A)\u{03BB}(y : A)\u{03BB}{_}x");
+///
+/// let pi = parse("(A B : U) → A → B").unwrap();
+/// assert_eq!(pi.to_string(), "\u{220F}(A : U)\u{220F}(B : U)\u{220F}(_ : A)B");
+///
+/// let error = parse("Pi(A : U) (A a : A) → A").err().unwrap();
+/// assert_eq!((error.line, error.column), (1, 18));
+///
+/// // type errors are reported where the sugar was written
+/// let defs = Definitions::new();
+/// let id = parse(r"(λ A x. x : (A : U) → A → Bool)").unwrap();
+/// let error = synth_type(&defs, &Context::EMPTY, &id).err().unwrap();
+/// assert!(error.to_string().starts_with("1:9-1:10: "));
+/// ```
+pub fn lower(term: &Term) -> Result<Expression, ParseError> {
+    lower_at(term, Location { line: 1, column: 1 })
+}
+
+/// Lowers a [`Term`] found at `start`, which locates the errors outside any span within it.
+fn lower_at(term: &Term, start: Location) -> Result<Expression, ParseError> {
+    use Term::*;
+    let lower = |term: &Term| lower_at(term, start);
+    let lower_box = |term: &Term| lower(term).map(Box::new);
+    Ok(match term {
+        Variable(id) => Expression::Variable(id.clone()),
+        PiType { binders, ret_type } => {
+            let mut expr = lower(ret_type)?;
+            for binder in binders.iter().rev() {
+                let Some(tparam_type) = binder_type(binder, start)? else {
+                    return Err(error_at(
+                        start,
+                        format!(
+                            "The parameters `{}` of a pi type must be given a type.",
+                            Names(&binder.names)
+                        ),
+                    ));
+                };
+                for tparam in binder.names.iter().rev() {
+                    expr = Expression::PiType {
+                        tparam: tparam.clone(),
+                        tparam_type: Box::new(tparam_type.clone()),
+                        ret_type: Box::new(expr),
+                        implicit: binder.implicit,
+                    };
+                }
+            }
+            expr
+        }
+        Arrow { domain, codomain } => Expression::PiType {
+            tparam: Binder::wildcard(),
+            tparam_type: lower_box(domain)?,
+            ret_type: lower_box(codomain)?,
+            implicit: false,
+        },
+        Lambda { binders, body } => {
+            let mut expr = lower(body)?;
+            for binder in binders.iter().rev() {
+                let param_type = binder_type(binder, start)?;
+                for param in binder.names.iter().rev() {
+                    expr = Expression::Lambda {
+                        param: param.clone(),
+                        param_type: param_type.clone().map(Box::new),
+                        ret_val: Box::new(expr),
+                        implicit: binder.implicit,
+                    };
+                }
+            }
+            expr
+        }
+        Application {
+            func,
+            arg,
+            implicit,
+        } => Expression::Application {
+            func: lower_box(func)?,
+            arg: lower_box(arg)?,
+            implicit: *implicit,
+        },
+        SigmaType {
+            tparam,
+            tparam_type,
+            snd_type,
+        } => Expression::SigmaType {
+            tparam: tparam.clone(),
+            tparam_type: lower_box(tparam_type)?,
+            snd_type: lower_box(snd_type)?,
+        },
+        Pair { fst, snd } => Expression::Pair {
+            fst: lower_box(fst)?,
+            snd: lower_box(snd)?,
+        },
+        Record { fields } => Expression::Record {
+            fields: fields.iter().map(lower).collect::<Result<_, _>>()?,
+        },
+        First { pair } => Expression::First {
+            pair: lower_box(pair)?,
+        },
+        Second { pair } => Expression::Second {
+            pair: lower_box(pair)?,
+        },
+        IdType { type_, lhs, rhs } => Expression::IdType {
+            type_: lower_box(type_)?,
+            lhs: lower_box(lhs)?,
+            rhs: lower_box(rhs)?,
+        },
+        Refl { elem } => Expression::Refl {
+            elem: lower_box(elem)?,
+        },
+        J { motive, base, eq } => Expression::J {
+            motive: lower_box(motive)?,
+            base: lower_box(base)?,
+            eq: lower_box(eq)?,
+        },
+        Succ { pred } => Expression::Succ {
+            pred: lower_box(pred)?,
+        },
+        NatRec {
+            motive,
+            base,
+            step,
+            target,
+        } => Expression::NatRec {
+            motive: lower_box(motive)?,
+            base: lower_box(base)?,
+            step: lower_box(step)?,
+            target: lower_box(target)?,
+        },
+        If {
+            motive,
+            on_true,
+            on_false,
+            target,
+        } => Expression::If {
+            motive: lower_box(motive)?,
+            on_true: lower_box(on_true)?,
+            on_false: lower_box(on_false)?,
+            target: lower_box(target)?,
+        },
+        Absurd { motive, target } => Expression::Absurd {
+            motive: lower_box(motive)?,
+            target: lower_box(target)?,
+        },
+        Ascription { term, type_ } => Expression::Annotation {
+            expr: lower_box(term)?,
+            type_: lower_box(type_)?,
+        },
+        Let {
+            name,
+            type_,
+            value,
+            body,
+        } => Expression::Let {
+            name: name.clone(),
+            type_: lower_box(type_)?,
+            value: lower_box(value)?,
+            body: lower_box(body)?,
+        },
+        Core(expr) => expr.clone(),
+        Located { span, term } => Expression::Located {
+            span: *span,
+            expr: Box::new(lower_at(term, span.start)?),
+        },
+    })
+}
+
+/// Lowers the type shared by the parameters of `binder`, found at `start`,
+/// checking that it does not mention any of them but the last.
+fn binder_type(binder: &Binder, start: Location) -> Result<Option<Expression>, ParseError> {
+    let Some(type_) = &binder.type_ else {
+        return Ok(None);
+    };
+    let start = match type_ {
+        Term::Located { span, .. } => span.start,
+        _ => start,
+    };
+    let expr = lower_at(type_, start)?;
+    let bound_before = &binder.names[..binder.names.len().saturating_sub(1)];
+    if let Some(name) = bound_before
+        .iter()
+        .find(|&name| expr.has_free_variable(name))
+    {
+        return Err(error_at(
+            start,
+            format!(
+                "The type `{}` of `{}` mentions `{}`, so they must be bound separately.",
+                expr,
+                Names(&binder.names),
+                name
+            ),
+        ));
+    }
+    Ok(Some(expr))
+}
+
+fn error_at(start: Location, msg: String) -> ParseError {
+    ParseError {
+        line: start.line,
+        column: start.column,
+        msg,
+    }
+}
+
+/// Displays names separated by spaces, as they are bound together.
+struct Names<'a>(&'a [Identifier]);
+
+impl fmt::Display for Names<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, name) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            name.fmt(f)?;
+        }
+        Ok(())
+    }
+}
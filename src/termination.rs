@@ -0,0 +1,333 @@
+//! Checking that functions defined by pattern matching terminate,
+//! by the size-change principle on structural order.
+
+use crate::expression::Expression;
+use crate::pattern::{FunctionDeclaration, Pattern};
+use crate::{Identifier, Span, TypeError, TypeErrorKind};
+
+/// How an argument of a call relates to a parameter of the caller.
+///
+/// Ordered so that the larger is the more informative.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Order {
+    Unknown,
+    Equal,
+    Smaller,
+}
+
+impl Order {
+    /// The relation given by following this relation, then `then`.
+    fn compose(self, then: Order) -> Order {
+        match (self, then) {
+            (Order::Unknown, _) | (_, Order::Unknown) => Order::Unknown,
+            (Order::Equal, Order::Equal) => Order::Equal,
+            _ => Order::Smaller,
+        }
+    }
+}
+
+/// A chain of calls from `caller` to `callee`, where each argument `j` of the last call
+/// relates to each parameter `i` of the first as `matrix[j][i]`.
+#[derive(Clone, PartialEq, Eq)]
+struct Call {
+    caller: usize,
+    /// The number of parameters of the caller.
+    params: usize,
+    callee: usize,
+    matrix: Vec<Vec<Order>>,
+}
+
+impl Call {
+    /// The chain of calls given by following this chain, then `then`.
+    fn compose(&self, then: &Call) -> Call {
+        let matrix = then
+            .matrix
+            .iter()
+            .map(|row| {
+                (0..self.params)
+                    .map(|i| {
+                        row.iter()
+                            .zip(&self.matrix)
+                            .map(|(&order, first)| first[i].compose(order))
+                            .max()
+                            .unwrap_or(Order::Unknown)
+                    })
+                    .collect()
+            })
+            .collect();
+        Call {
+            caller: self.caller,
+            params: self.params,
+            callee: then.callee,
+            matrix,
+        }
+    }
+
+    /// Whether some parameter of a call to the function itself always gets smaller.
+    fn decreases(&self) -> bool {
+        (0..self.matrix.len()).any(|i| self.matrix[i][i] == Order::Smaller)
+    }
+}
+
+/// A call as written in a clause, with where it was written.
+struct Site {
+    call: Call,
+    expr: Expression,
+    span: Option<Span>,
+}
+
+/// Checks that the function `group[member]`, where the functions of `group` may call each other,
+/// terminates on every argument.
+///
+/// Each recursive call must, after following any chain of calls which returns to the
+/// same function, be on an argument structurally smaller than the same parameter,
+/// where the variables bound inside a constructor pattern are smaller than what it matches.
+pub(crate) fn check_termination(
+    group: &[&FunctionDeclaration],
+    member: usize,
+) -> crate::Result<()> {
+    let names: Vec<&Identifier> = group.iter().map(|decl| &decl.name).collect();
+    let mut sites = Vec::new();
+    for (caller, decl) in group.iter().enumerate() {
+        for clause in &decl.clauses {
+            if let Some(body) = &clause.body {
+                let mut collector = Collector {
+                    group,
+                    names: &names,
+                    caller,
+                    patterns: &clause.patterns,
+                    bound: Vec::new(),
+                    sites: &mut sites,
+                };
+                collector.collect(body, None);
+            }
+        }
+    }
+    // every chain of calls, each remembering the written call it starts with
+    let mut chains: Vec<(Call, usize)> = sites
+        .iter()
+        .enumerate()
+        .map(|(index, site)| (site.call.clone(), index))
+        .collect();
+    let mut next = 0;
+    while next < chains.len() {
+        let (chain, origin) = chains[next].clone();
+        next += 1;
+        for site in &sites {
+            if site.call.caller != chain.callee {
+                continue;
+            }
+            let longer = chain.compose(&site.call);
+            if !chains.iter().any(|(other, _)| *other == longer) {
+                chains.push((longer, origin));
+            }
+        }
+    }
+    for (chain, origin) in &chains {
+        // only a chain which composes with itself to itself need decrease,
+        // as every infinite sequence of calls eventually repeats one
+        if chain.caller == member
+            && chain.callee == member
+            && chain.compose(chain) == *chain
+            && !chain.decreases()
+        {
+            let site = &sites[*origin];
+            let error = TypeError::new(TypeErrorKind::NotTerminating {
+                function: group[site.call.caller].name.clone(),
+                call: site.expr.clone(),
+            });
+            return Err(match site.span {
+                Some(span) => error.located(span),
+                None => error,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Finds the calls in the body of a clause to functions of the group.
+struct Collector<'a> {
+    group: &'a [&'a FunctionDeclaration],
+    names: &'a [&'a Identifier],
+    caller: usize,
+    patterns: &'a [Pattern],
+    /// The variables bound by binders enclosing the current subexpression.
+    bound: Vec<Identifier>,
+    sites: &'a mut Vec<Site>,
+}
+
+impl Collector<'_> {
+    /// Collects the calls in `expr`, which occurs within `span`.
+    fn collect(&mut self, expr: &Expression, span: Option<Span>) {
+        use Expression::*;
+        match expr {
+            Located { span, expr } => self.collect(expr, Some(*span)),
+            Variable(_) | Application { .. } => {
+                let mut args = Vec::new();
+                let mut func = expr;
+                loop {
+                    match func {
+                        Application {
+                            func: inner, arg, ..
+                        } => {
+                            args.push(&**arg);
+                            func = inner;
+                        }
+                        Located { expr: inner, .. } => func = inner,
+                        _ => break,
+                    }
+                }
+                args.reverse();
+                match func {
+                    Variable(id) => self.call(id, &args, expr, span),
+                    _ => self.collect(func, span),
+                }
+                for arg in args {
+                    self.collect(arg, span);
+                }
+            }
+            PiType {
+                tparam: id,
+                tparam_type: type_,
+                ret_type: body,
+                ..
+            }
+            | SigmaType {
+                tparam: id,
+                tparam_type: type_,
+                snd_type: body,
+            } => {
+                self.collect(type_, span);
+                self.under(id, body, span);
+            }
+            Lambda {
+                param,
+                param_type,
+                ret_val,
+                ..
+            } => {
+                if let Some(type_) = param_type {
+                    self.collect(type_, span);
+                }
+                self.under(param, ret_val, span);
+            }
+            Let {
+                name,
+                type_,
+                value,
+                body,
+            } => {
+                self.collect(type_, span);
+                self.collect(value, span);
+                self.under(name, body, span);
+            }
+            _ => {
+                for sub in expr.subexpressions() {
+                    self.collect(sub, span);
+                }
+            }
+        }
+    }
+
+    /// Collects the calls in `body`, under a binder of `id`.
+    fn under(&mut self, id: &Identifier, body: &Expression, span: Option<Span>) {
+        self.bound.push(id.clone());
+        self.collect(body, span);
+        self.bound.pop();
+    }
+
+    /// Records the call `expr` of `func` on `args`, if `func` is a function of the group.
+    fn call(
+        &mut self,
+        func: &Identifier,
+        args: &[&Expression],
+        expr: &Expression,
+        span: Option<Span>,
+    ) {
+        if self.bound.contains(func) {
+            return;
+        }
+        let Some(callee) = self.names.iter().position(|name| *name == func) else {
+            return;
+        };
+        let arity = self.group[callee].arity();
+        // an argument left out may be anything
+        let matrix = (0..arity)
+            .map(|j| {
+                self.patterns
+                    .iter()
+                    .map(|pattern| match args.get(j) {
+                        Some(arg) => self.order(arg, pattern),
+                        None => Order::Unknown,
+                    })
+                    .collect()
+            })
+            .collect();
+        self.sites.push(Site {
+            call: Call {
+                caller: self.caller,
+                params: self.patterns.len(),
+                callee,
+                matrix,
+            },
+            expr: expr.clone(),
+            span,
+        });
+    }
+
+    /// How `arg` relates to what `pattern` matches.
+    fn order(&self, arg: &Expression, pattern: &Pattern) -> Order {
+        if self.same(arg, pattern) {
+            Order::Equal
+        } else if pattern
+            .subpatterns()
+            .into_iter()
+            .skip(1)
+            .any(|sub| self.same(arg, sub))
+        {
+            Order::Smaller
+        } else {
+            Order::Unknown
+        }
+    }
+
+    /// Whether `expr` is built in the same way as `pattern`, from the same variables.
+    fn same(&self, expr: &Expression, pattern: &Pattern) -> bool {
+        match (expr.unlocated(), pattern) {
+            (Expression::Variable(id), Pattern::Variable(var)) => {
+                id == var && !self.bound.contains(id)
+            }
+            (Expression::Numeral(n), Pattern::Zero) => n.is_zero(),
+            (Expression::Numeral(n), Pattern::Succ(pred)) => n
+                .predecessor()
+                .is_some_and(|m| self.same(&Expression::Numeral(m), pred)),
+            (Expression::Succ { pred }, Pattern::Succ(pattern)) => self.same(pred, pattern),
+            (Expression::True, Pattern::True)
+            | (Expression::False, Pattern::False)
+            | (Expression::Star, Pattern::Star)
+            | (Expression::Refl { .. }, Pattern::Refl) => true,
+            (Expression::Pair { fst, snd }, Pattern::Pair(fst_pattern, snd_pattern)) => {
+                self.same(fst, fst_pattern) && self.same(snd, snd_pattern)
+            }
+            (expr, Pattern::Constructor { name, args }) => {
+                let mut fields = Vec::new();
+                let mut func = expr;
+                while let Expression::Application {
+                    func: inner, arg, ..
+                } = func
+                {
+                    fields.push(&**arg);
+                    func = inner.unlocated();
+                }
+                // the arguments before the fields are the parameters of the family
+                matches!(func, Expression::Variable(id) if id == name && !self.bound.contains(id))
+                    && fields.len() >= args.len()
+                    && fields
+                        .iter()
+                        .zip(args.iter().rev())
+                        .all(|(field, arg)| self.same(field, arg))
+            }
+            _ => false,
+        }
+    }
+}
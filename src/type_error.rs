@@ -1,9 +1,470 @@
+use crate::expression::Expression;
+use crate::meta::MetaVariable;
+use crate::pattern::{LeftHandSide, Pattern};
+use crate::{Identifier, Level, Span};
+use std::fmt;
+
+/// The ways in which type synthesis and type checking can fail.
+///
+/// Terms involved in an error are held as [`Expression`]s, reading back [`Value`](crate::value::Value)s where necessary.
+#[derive(Clone, Debug)]
+pub enum TypeErrorKind {
+    /// A variable is neither in the context nor globally defined.
+    UnboundVariable(Identifier),
+    /// A global definition reuses a name already defined.
+    AlreadyDefined(Identifier),
+    /// An expression is applied to an argument, but is not of a pi type.
+    NotAFunction {
+        func: Expression,
+        func_type: Expression,
+    },
+    /// A type must be synthesized for a lambda expression without a parameter type,
+    /// and its uses of the parameter do not decide one.
+    LambdaNeedsAnnotation { lambda: Expression },
+    /// A lambda expression is checked against a type which is not a pi type.
+    LambdaAgainstNonPi {
+        lambda: Expression,
+        type_: Expression,
+    },
+    /// A type must be synthesized for a hole.
+    HoleNeedsAnnotation,
+    /// An implicit lambda expression is checked against a type which is not an implicit pi type.
+    ImplicitLambdaAgainstExplicitPi {
+        lambda: Expression,
+        type_: Expression,
+    },
+    /// An implicit argument is given to a function whose next parameter is explicit.
+    NoImplicitParameter {
+        func: Expression,
+        func_type: Expression,
+    },
+    /// No solution was found for the implicit parameter `binder` of `func`.
+    CannotInferImplicit {
+        binder: Identifier,
+        func: Expression,
+    },
+    /// Unification would solve `meta` as `solution`, which mentions `meta` itself.
+    OccursCheck {
+        meta: MetaVariable,
+        solution: Expression,
+    },
+    /// Unification would solve `meta` as `solution`,
+    /// which mentions a `variable` that `meta` is not applied to.
+    ScopeCheck {
+        meta: MetaVariable,
+        variable: Identifier,
+        solution: Expression,
+    },
+    /// An equation postponed by unification turned out false once a metavariable was solved.
+    ConstraintFailed { lhs: Expression, rhs: Expression },
+    /// An equation postponed by unification was never solved.
+    UnsolvedConstraint { lhs: Expression, rhs: Expression },
+    /// A type must be synthesized for a pair.
+    PairNeedsAnnotation { pair: Expression },
+    /// A pair is checked against a type which is not a sigma type.
+    PairAgainstNonSigma { pair: Expression, type_: Expression },
+    /// An element of a record type must have a type synthesized for it.
+    RecordNeedsAnnotation { record: Expression },
+    /// An element of a record type is checked against a type which is not a record type.
+    RecordAgainstNonRecord {
+        record: Expression,
+        type_: Expression,
+    },
+    /// An element of a record type gives a different number of fields than the record type has.
+    WrongFieldCount {
+        record: Expression,
+        expected: usize,
+        found: usize,
+    },
+    /// A component is projected from an expression which is not of a sigma type.
+    NotAPair {
+        expr: Expression,
+        expr_type: Expression,
+    },
+    /// An expression is eliminated with `J`, but is not of an identity type.
+    NotAnEquality {
+        expr: Expression,
+        expr_type: Expression,
+    },
+    /// An expression is used as a type, but is not of a universe.
+    NotAType {
+        expr: Expression,
+        expr_type: Expression,
+    },
+    /// A universe level is too large to represent.
+    UniverseTooLarge(Level),
+    /// A type is used where a type in a universe at a lower level is expected.
+    LevelTooLarge { found: Level, expected: Level },
+    /// A universe level variable is not a parameter of the definition it occurs in.
+    UnboundLevel(Identifier),
+    /// A name is instantiated at a different number of universe levels than it has parameters.
+    WrongLevelCount {
+        name: Identifier,
+        expected: usize,
+        found: usize,
+    },
+    /// The type of a declared inductive family does not end in a universe.
+    NotAnArity(Expression),
+    /// The type of a constructor does not end in its family applied to the family's parameters.
+    NotAConstructorType {
+        family: Identifier,
+        type_: Expression,
+    },
+    /// A field of a constructor mentions its family other than strictly positively.
+    NotStrictlyPositive {
+        family: Identifier,
+        type_: Expression,
+    },
+    /// A binder in a declaration reuses the name of what is declared, a parameter or another binder,
+    /// or a clause binds the same variable twice.
+    ShadowedBinder(Identifier),
+    /// A clause has a different number of patterns than the first clause of its function.
+    WrongPatternCount { expected: usize, found: usize },
+    /// A function is defined by clauses with more patterns than its type has parameters.
+    TooManyPatterns { type_: Expression, found: usize },
+    /// A clause has no body, but none of its patterns is absurd.
+    MissingClauseBody(Vec<Pattern>),
+    /// A clause has a body, but one of its patterns is absurd.
+    AbsurdClauseBody(Vec<Pattern>),
+    /// A pattern is not built by a constructor of the type it is matched against,
+    /// or has the wrong number of fields.
+    PatternMismatch { pattern: Pattern, type_: Expression },
+    /// An expression is matched against a pattern,
+    /// but is not a variable or of an inductive type.
+    CannotSplit { expr: Expression, type_: Expression },
+    /// An absurd pattern is matched against a type which may have elements.
+    NotEmpty { type_: Expression },
+    /// Matching needs two expressions to be equal, but cannot tell how to make them so.
+    UnificationStuck { lhs: Expression, rhs: Expression },
+    /// The clauses of a function do not cover the arguments of each of `cases`.
+    MissingCases {
+        function: Identifier,
+        cases: Vec<Vec<Pattern>>,
+    },
+    /// A function calls itself, perhaps through other functions,
+    /// without an argument getting structurally smaller.
+    NotTerminating {
+        function: Identifier,
+        call: Expression,
+    },
+    /// Two expressions are not alpha-equivalent.
+    NotAlphaEquivalent { lhs: Expression, rhs: Expression },
+    /// The type synthesized for `context` is not the type it is checked against.
+    TypeMismatch {
+        expected: Expression,
+        found: Expression,
+        context: Expression,
+    },
+    /// A global definition failed to check.
+    InDefinition {
+        name: Identifier,
+        error: Box<TypeErrorKind>,
+    },
+    /// A [`Value`](crate::value::Value) was used in a way its type does not allow.
+    ///
+    /// This is never caused by checking an [`Expression`],
+    /// only by passing ill-typed values to functions such as
+    /// [`try_evaluate`](crate::evaluation::try_evaluate) and
+    /// [`try_read_back_typed`](crate::read_back::try_read_back_typed).
+    Internal(String),
+}
+
+impl fmt::Display for TypeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TypeErrorKind::*;
+        match self {
+            UnboundVariable(id) => write!(f, "Variable `{}` not found.", id),
+            AlreadyDefined(id) => write!(f, "Variable `{}` is already defined.", id),
+            NotAFunction { func, func_type } => write!(
+                f,
+                "Cannot call `{}` as a function, because it is of non-function type `{}`.",
+                func, func_type
+            ),
+            LambdaNeedsAnnotation { lambda } => write!(
+                f,
+                "Cannot infer a type for lambda expression `{}` without parameter type given.",
+                lambda
+            ),
+            LambdaAgainstNonPi { lambda, type_ } => write!(
+                f,
+                "{} is not of type {}, because all lambda terms are of pi types.",
+                lambda, type_
+            ),
+            HoleNeedsAnnotation => write!(f, "Cannot infer a type for a hole without an annotation."),
+            ImplicitLambdaAgainstExplicitPi { lambda, type_ } => write!(
+                f,
+                "{} is not of type {}, because implicit lambda terms are of implicit pi types.",
+                lambda, type_
+            ),
+            NoImplicitParameter { func, func_type } => write!(
+                f,
+                "Cannot give `{}` an implicit argument, because its type `{}` takes an explicit one.",
+                func, func_type
+            ),
+            CannotInferImplicit { binder, func } => write!(
+                f,
+                "Could not infer implicit argument `{}` of `{}`.",
+                binder, func
+            ),
+            OccursCheck { meta, solution } => write!(
+                f,
+                "Cannot solve `{}` as `{}`, because it would mention itself.",
+                meta, solution
+            ),
+            ScopeCheck {
+                meta,
+                variable,
+                solution,
+            } => write!(
+                f,
+                "Cannot solve `{}` as `{}`, because `{}` is not in its scope.",
+                meta, solution, variable
+            ),
+            ConstraintFailed { lhs, rhs } => write!(
+                f,
+                "Expected `{}` and `{}` to be equal, but they differ once the metavariables they mention are solved.",
+                lhs, rhs
+            ),
+            UnsolvedConstraint { lhs, rhs } => write!(
+                f,
+                "Could not tell whether `{}` and `{}` are equal, before the metavariables they mention are solved.",
+                lhs, rhs
+            ),
+            PairNeedsAnnotation { pair } => write!(
+                f,
+                "Cannot infer a type for pair `{}` without an annotation.",
+                pair
+            ),
+            PairAgainstNonSigma { pair, type_ } => write!(
+                f,
+                "{} is not of type {}, because all pairs are of sigma types.",
+                pair, type_
+            ),
+            RecordNeedsAnnotation { record } => write!(
+                f,
+                "Cannot infer a type for record `{}` without an annotation.",
+                record
+            ),
+            RecordAgainstNonRecord { record, type_ } => write!(
+                f,
+                "{} is not of type {}, because all records are of record types.",
+                record, type_
+            ),
+            WrongFieldCount {
+                record,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Cannot use `{}` as a record with {} fields, because it gives {}.",
+                record, expected, found
+            ),
+            NotAPair { expr, expr_type } => write!(
+                f,
+                "Cannot project from `{}` as a pair, because it is of non-sigma type `{}`.",
+                expr, expr_type
+            ),
+            NotAnEquality { expr, expr_type } => write!(
+                f,
+                "Cannot eliminate `{}` as an equality, because it is of non-identity type `{}`.",
+                expr, expr_type
+            ),
+            NotAType { expr, expr_type } => write!(
+                f,
+                "Cannot use `{}` as a type, because it is of non-universe type `{}`.",
+                expr, expr_type
+            ),
+            UniverseTooLarge(level) => write!(f, "Universe level `{}` is too large.", level),
+            LevelTooLarge { found, expected } => write!(
+                f,
+                "Universe level `{}` is too large to be used at level `{}`.",
+                found, expected
+            ),
+            UnboundLevel(id) => write!(f, "Universe level variable `{}` not found.", id),
+            WrongLevelCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Cannot instantiate `{}` at {} universe levels, because it has {} level parameters.",
+                name, found, expected
+            ),
+            NotAnArity(type_) => write!(
+                f,
+                "Cannot declare a family of type `{}`, because it does not end in a universe.",
+                type_
+            ),
+            NotAConstructorType { family, type_ } => write!(
+                f,
+                "Cannot declare a constructor of type `{}`, because it does not end in `{}` applied to its parameters.",
+                type_, family
+            ),
+            NotStrictlyPositive { family, type_ } => write!(
+                f,
+                "Cannot declare a field of type `{}`, because `{}` occurs in it other than strictly positively.",
+                type_, family
+            ),
+            ShadowedBinder(id) => write!(
+                f,
+                "Cannot bind `{}` again within the same declaration.",
+                id
+            ),
+            WrongPatternCount { expected, found } => write!(
+                f,
+                "Cannot match {} patterns in a clause, because the first clause matches {}.",
+                found, expected
+            ),
+            TooManyPatterns { type_, found } => write!(
+                f,
+                "Cannot match {} patterns, because type `{}` has fewer parameters.",
+                found, type_
+            ),
+            MissingClauseBody(patterns) => write!(
+                f,
+                "Clause `{}` must have a body, because none of its patterns is absurd.",
+                Patterns(patterns)
+            ),
+            AbsurdClauseBody(patterns) => write!(
+                f,
+                "Clause `{}` must not have a body, because one of its patterns is absurd.",
+                Patterns(patterns)
+            ),
+            PatternMismatch { pattern, type_ } => write!(
+                f,
+                "Cannot match `{}` against an element of type `{}`.",
+                pattern, type_
+            ),
+            CannotSplit { expr, type_ } => write!(
+                f,
+                "Cannot match on `{}`, because it is neither a variable nor built by a constructor of `{}`.",
+                expr, type_
+            ),
+            NotEmpty { type_ } => write!(
+                f,
+                "Cannot use an absurd pattern at type `{}`, because it may have elements.",
+                type_
+            ),
+            UnificationStuck { lhs, rhs } => write!(
+                f,
+                "Cannot tell how to make `{}` and `{}` equal while matching.",
+                lhs, rhs
+            ),
+            MissingCases { function, cases } => {
+                write!(f, "The clauses of `{}` do not cover ", function)?;
+                for (i, case) in cases.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "`{}`", LeftHandSide(function, case))?;
+                }
+                write!(f, ".")
+            }
+            NotTerminating { function, call } => write!(
+                f,
+                "Cannot tell that `{}` terminates, because call `{}` may not be on a structurally smaller argument.",
+                function, call
+            ),
+            NotAlphaEquivalent { lhs, rhs } => write!(
+                f,
+                "Expressions `{}` and `{}` are not alpha-equivalent.",
+                lhs, rhs
+            ),
+            TypeMismatch {
+                expected, found, ..
+            } => write!(
+                f,
+                "Expressions `{}` and `{}` are not alpha-equivalent.",
+                found, expected
+            ),
+            InDefinition { name, error } => write!(f, "Cannot define `{}`: {}", name, error),
+            Internal(msg) => write!(f, "Internal error: {}", msg),
+        }
+    }
+}
+
+/// Displays patterns separated by spaces.
+struct Patterns<'a>(&'a [Pattern]);
+
+impl fmt::Display for Patterns<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pattern) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", pattern)?;
+        }
+        Ok(())
+    }
+}
+
+/// The ways in which a definition may check, but likely not mean what was intended.
+#[derive(Clone, Debug)]
+pub enum Warning {
+    /// A clause of a function matches only arguments which earlier clauses already match.
+    UnreachableClause {
+        function: Identifier,
+        patterns: Vec<Pattern>,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::UnreachableClause { function, patterns } => write!(
+                f,
+                "Clause `{}` is unreachable, because earlier clauses match everything it does.",
+                LeftHandSide(function, patterns)
+            ),
+        }
+    }
+}
+
 /// The error type for type synthesis and type checking.
 #[derive(Debug)]
 pub struct TypeError {
-    pub msg: String,
+    pub kind: Box<TypeErrorKind>,
+    /// The span of the smallest located subterm in which the error occurred.
+    pub span: Option<Span>,
+    /// The spans of located terms enclosing [`span`](TypeError::span), innermost first.
+    pub notes: Vec<Span>,
 }
 
+impl TypeError {
+    /// Creates an error with no location.
+    pub fn new(kind: TypeErrorKind) -> TypeError {
+        TypeError {
+            kind: Box::new(kind),
+            span: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Records that the error occurred within `span`.
+    pub(crate) fn located(mut self, span: Span) -> TypeError {
+        match self.span {
+            None => self.span = Some(span),
+            Some(_) => self.notes.push(span),
+        }
+        self
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = self.span {
+            write!(f, "{}: ", span)?;
+        }
+        write!(f, "{}", self.kind)?;
+        for note in &self.notes {
+            write!(f, "\nnote: within {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TypeError {}
+
 /// A specialised result type for type synthesis and type checking.
 ///
 /// This type is used across [`alpha`](crate) for operations which may produce an error.
@@ -15,21 +476,23 @@ pub struct TypeError {
 /// A function that checks whether two identifiers are equal.
 ///
 /// ```
-///use alpha::{Identifier, TypeError};
+///use alpha::expression::Expression;
+///use alpha::{Identifier, TypeError, TypeErrorKind};
 ///
 /// fn check_equal(a: &Identifier, b: &Identifier) -> alpha::Result<()> {
 ///     if a == b {
 ///         Ok(())
 ///     } else {
-///         Err(TypeError {
-///             msg: format!("`{}` and `{}` are not equal.", a, b),
-///         })
+///         Err(TypeError::new(TypeErrorKind::NotAlphaEquivalent {
+///             lhs: Expression::Variable(a.clone()),
+///             rhs: Expression::Variable(b.clone()),
+///         }))
 ///     }
 /// }
 ///
-/// let x = Identifier { name: 0 };
-/// let y = Identifier { name: 1 };
-/// let z = Identifier { name: 1 };
+/// let x = Identifier::new("x");
+/// let y = Identifier::new("y");
+/// let z = Identifier::new("y");
 ///
 /// assert!(check_equal(&x, &x).is_ok());
 /// assert!(check_equal(&x, &y).is_err());
@@ -1,12 +1,21 @@
 //! Functions for type-checking and type-inference.
+//!
+//! Checking an [`Expression`] also elaborates it,
+//! filling in the implicit arguments it leaves out with the solutions found by unification.
+//!
+//! These functions do not panic on any [`Expression`];
+//! all failures are reported as [`TypeError`]s.
 
 use crate::environment::{type_var, Context, Definitions, Environment};
-use crate::equivalence::judgmentally_equal;
-use crate::evaluation::evaluate;
+use crate::equivalence::subtype;
+use crate::evaluation::{try_apply, try_evaluate, try_first};
 use crate::expression::Expression;
-use crate::read_back::read_back_typed;
-use crate::value::{Neutral, Type, Value};
-use crate::TypeError;
+use crate::identifier::fresh_in_context;
+use crate::meta::{force_type, implicit_argument, parameter_type, record_goal, refinement, zonk};
+use crate::read_back::try_read_back_type;
+use crate::unify::{check_solved, unify_types};
+use crate::value::{Closure, Neutral, Type, Value};
+use crate::{Identifier, Level, Natural, TypeError, TypeErrorKind};
 use std::ops::Deref;
 
 /// Checks the [`Type`] of an [`Expression`].
@@ -16,106 +25,1082 @@ pub fn check_type(
     expr: &Expression,
     type_: &Type,
 ) -> crate::Result<()> {
+    elaborate(defs, ctx, expr, type_).map(|_| ())
+}
+
+/// Synthesizes a [`Type`] for an [`Expression`].
+///
+/// # Examples
+///
+/// ```
+/// use alpha::environment::{Context, Definitions};
+/// use alpha::parse::parse;
+/// use alpha::typing::synth_type;
+///
+/// let defs = Definitions::new();
+/// let synth = |expr: &str| synth_type(&defs, &Context::EMPTY, &parse(expr).unwrap());
+///
+/// // eta: any two elements of the unit type are equal
+/// assert!(synth(r"(\x y. refl x : (x y : Unit) → Id Unit x y)").is_ok());
+/// assert!(synth(r"(\x y. refl x : (x y : Bool) → Id Bool x y)").is_err());
+///
+/// // the type of `if` depends on the boolean it is on
+/// let choose = r"(\b. if (\b. if (\_. U) Nat Bool b) 0 false b
+///     : (b : Bool) → if (\_. U) Nat Bool b)";
+/// assert!(synth(choose).is_ok());
+/// assert_eq!(synth(r"if (\_. Nat) 1 0 true").unwrap().to_string(), "Nat");
+/// assert!(synth(r"if (\_. Nat) 1 false true").is_err());
+///
+/// // there is no element of the empty type, so anything follows from one
+/// assert!(synth(r"(\e. absurd (\_. Id Nat 0 1) e : Empty → Id Nat 0 1)").is_ok());
+/// assert!(synth(r"absurd (\_. Nat) tt").is_err());
+/// ```
+pub fn synth_type(defs: &Definitions, ctx: &Context, expr: &Expression) -> crate::Result<Type> {
+    elaborate_synth(defs, ctx, expr).map(|(_, type_)| type_)
+}
+
+/// Checks the [`Type`] of an [`Expression`],
+/// and returns it with its implicit arguments filled in.
+///
+/// ```
+/// use alpha::environment::{Context, Definitions};
+/// use alpha::parse::parse;
+/// use alpha::typing::{elaborate, elaborate_synth};
+/// use alpha::Identifier;
+///
+/// let mut defs = Definitions::new();
+/// let expr = parse(r"\x.x").unwrap();
+/// let type_ = parse("Pi{A : U} Pi(x : A) A").unwrap();
+/// assert!(defs.define(Identifier::new("id"), &expr, &type_).is_ok());
+///
+/// // the implicit argument is found from the type of the explicit one
+/// let (three, nat) = elaborate_synth(&defs, &Context::EMPTY, &parse("id 3").unwrap()).unwrap();
+/// assert_eq!(three.to_string(), "((id){Nat})(3)");
+/// assert_eq!(nat.to_string(), "Nat");
+///
+/// // or from the type expected
+/// let bool_id = parse("(id : Pi(b : Bool) Bool)").unwrap();
+/// let (bool_id, _) = elaborate_synth(&defs, &Context::EMPTY, &bool_id).unwrap();
+/// assert_eq!(bool_id.to_string(), "((id){Bool} : \u{220F}(b : Bool)Bool)");
+///
+/// // and are abstracted over where the type expected is itself implicit
+/// let (id, id_type) = elaborate_synth(&defs, &Context::EMPTY, &parse("id").unwrap()).unwrap();
+/// let id_again = elaborate(&defs, &Context::EMPTY, &id, &id_type).unwrap();
+/// assert_eq!(id_again.to_string(), "\u{03BB}{A}(id){A}");
+///
+/// // or given explicitly
+/// assert!(elaborate_synth(&defs, &Context::EMPTY, &parse("id {Nat} 3").unwrap()).is_ok());
+/// assert!(elaborate_synth(&defs, &Context::EMPTY, &parse("id {Bool} 3").unwrap()).is_err());
+///
+/// // but must be found
+/// let type_ = parse("Pi{A : U} Nat").unwrap();
+/// assert!(defs.postulate(Identifier::new("f"), &type_).is_ok());
+/// let e = elaborate_synth(&defs, &Context::EMPTY, &parse("succ f").unwrap()).err().unwrap();
+/// assert_eq!(e.to_string(), "Could not infer implicit argument `A` of `f`.");
+/// ```
+pub fn elaborate(
+    defs: &Definitions,
+    ctx: &Context,
+    expr: &Expression,
+    type_: &Type,
+) -> crate::Result<Expression> {
+    let expr = elaborating(defs, || check(defs, ctx, expr, type_))?;
+    zonk(defs, &expr)
+}
+
+/// Synthesizes a [`Type`] for an [`Expression`],
+/// and returns it with its implicit arguments filled in.
+///
+/// ```
+/// use alpha::environment::{Context, Definitions};
+/// use alpha::parse::parse;
+/// use alpha::typing::elaborate_synth;
+///
+/// let defs = Definitions::new();
+///
+/// // the type of an unannotated parameter is found from its uses
+/// let succ = parse(r"\x. succ x").unwrap();
+/// let (succ, succ_type) = elaborate_synth(&defs, &Context::EMPTY, &succ).unwrap();
+/// assert_eq!(succ.to_string(), "\u{03BB}(x : Nat)succ(x)");
+/// assert_eq!(succ_type.to_string(), "\u{220F}(x : Nat)Nat");
+///
+/// // even where it is used as a function or a pair
+/// let twice = parse(r"\f (n : Nat). f (f n)").unwrap();
+/// let (_, twice_type) = elaborate_synth(&defs, &Context::EMPTY, &twice).unwrap();
+/// assert_eq!(
+///     twice_type.to_string(),
+///     "\u{220F}(f : \u{220F}(x : Nat)Nat)\u{220F}(n : Nat)Nat"
+/// );
+///
+/// // in whichever universe that type is
+/// let type_id = parse(r"\A. (A : U 1)").unwrap();
+/// let (_, type_id_type) = elaborate_synth(&defs, &Context::EMPTY, &type_id).unwrap();
+/// assert_eq!(type_id_type.to_string(), "\u{220F}(A : U 1)U 1");
+///
+/// // but must be found
+/// let e = elaborate_synth(&defs, &Context::EMPTY, &parse(r"\x. x").unwrap()).err().unwrap();
+/// assert!(e.to_string().starts_with("Cannot infer a type for lambda expression"));
+///
+/// // in every part
+/// for lambda in [r"\f. f 0", r"\p. fst p"] {
+///     let e = elaborate_synth(&defs, &Context::EMPTY, &parse(lambda).unwrap()).err().unwrap();
+///     assert!(e.to_string().starts_with("Cannot infer a type for lambda expression"));
+/// }
+/// ```
+pub fn elaborate_synth(
+    defs: &Definitions,
+    ctx: &Context,
+    expr: &Expression,
+) -> crate::Result<(Expression, Type)> {
+    let (expr, type_) = elaborating(defs, || infer(defs, ctx, expr))?;
+    // the type may be stuck on metavariables solved since, so is computed again
+    let type_ = try_read_back_type(defs, ctx, &type_)?;
+    let type_ = try_evaluate(defs, &Environment::from_context(ctx), &type_)?;
+    Ok((zonk(defs, &expr)?, Type::create_type_from_value(type_)))
+}
+
+/// Checks that an [`Expression`] is a type, and returns it elaborated
+/// with the level of its universe.
+pub(crate) fn check_is_type(
+    defs: &Definitions,
+    ctx: &Context,
+    expr: &Expression,
+) -> crate::Result<(Expression, Level)> {
+    let (expr, level) = elaborating(defs, || infer_universe(defs, ctx, expr))?;
+    Ok((zonk(defs, &expr)?, level))
+}
+
+/// Runs `elaborate`, failing if it leaves an implicit argument unsolved,
+/// and forgetting the metavariables it made if it fails.
+fn elaborating<T>(
+    defs: &Definitions,
+    elaborate: impl FnOnce() -> crate::Result<T>,
+) -> crate::Result<T> {
+    let mark = defs.metas().mark();
+    let result = elaborate().and_then(|elaborated| {
+        check_solved(defs, mark)?;
+        Ok(elaborated)
+    });
+    if result.is_err() {
+        defs.metas_mut().truncate(mark);
+    }
+    result
+}
+
+/// Checks an [`Expression`] against a [`Type`], and elaborates it.
+fn check(
+    defs: &Definitions,
+    ctx: &Context,
+    expr: &Expression,
+    type_: &Type,
+) -> crate::Result<Expression> {
     use Expression::*;
+    let type_ = force_type(defs, type_)?;
+    let type_: &Type = &type_;
+    if let Value::PiType {
+        param_type,
+        tclosure,
+        implicit: true,
+    } = type_.deref()
+    {
+        if !matches!(expr.unlocated(), Lambda { implicit: true, .. } | Hole(_)) {
+            // the implicit parameter is bound around `expr`, which cannot mention it
+            let param = fresh_in_context(defs, ctx, tclosure.param.name());
+            let var = Value::Neutral {
+                neu: Neutral::Variable(param.clone()),
+            };
+            let ret_type = Type::create_type_from_value(tclosure.try_call(defs, &var)?);
+            let body = check(defs, &ctx.extend(&param, param_type), expr, &ret_type)?;
+            return Ok(Lambda {
+                param,
+                param_type: None,
+                ret_val: Box::new(body),
+                implicit: true,
+            });
+        }
+    }
     match expr {
         Lambda {
             param,
-            param_type: None::<_>,
+            param_type: annotation,
             ret_val,
+            implicit,
         } => {
-            let Value::PiType{ param_type, tclosure } = type_.deref() else {
-                return Err(TypeError{
-                    msg: format!(
-                        "{} is not of type {}, because all lambda terms are of pi types.",
-                        expr,
-                        type_
-                    )
-                });
-            };
-            let ret_type = tclosure.call(
+            let Value::PiType {
+                param_type,
+                tclosure,
+                implicit: pi_implicit,
+            } = type_.deref()
+            else {
+                if let Value::Neutral {
+                    neu: Neutral::Meta { .. },
+                } = type_.deref()
+                {
+                    // the type is yet to be solved, so may be solved as the one synthesized
+                    return check_by_synthesis(defs, ctx, expr, type_);
+                }
+                return Err(TypeError::new(TypeErrorKind::LambdaAgainstNonPi {
+                    lambda: expr.clone(),
+                    type_: try_read_back_type(defs, ctx, type_)?,
+                }));
+            };
+            // an explicit lambda against an implicit pi type was bound around above
+            if implicit != pi_implicit {
+                return Err(TypeError::new(
+                    TypeErrorKind::ImplicitLambdaAgainstExplicitPi {
+                        lambda: expr.clone(),
+                        type_: try_read_back_type(defs, ctx, type_)?,
+                    },
+                ));
+            }
+            // a parameter type which is given must be the one of the pi type
+            let annotation = match annotation {
+                Some(annotation) => {
+                    let (annotation, _) = infer_universe(defs, ctx, annotation)?;
+                    let env = Environment::from_context(ctx);
+                    let annotation_val =
+                        Type::create_type_from_value(try_evaluate(defs, &env, &annotation)?);
+                    if let Err(mut e) = unify_types(defs, ctx, &annotation_val, param_type) {
+                        if let TypeErrorKind::NotAlphaEquivalent { .. } = *e.kind {
+                            e.kind = Box::new(TypeErrorKind::TypeMismatch {
+                                expected: try_read_back_type(defs, ctx, param_type)?,
+                                found: annotation.clone(),
+                                context: expr.clone(),
+                            });
+                        }
+                        return Err(e);
+                    }
+                    Some(Box::new(annotation))
+                }
+                None => None,
+            };
+            let ret_type = tclosure.try_call(
                 defs,
                 &Value::Neutral {
                     neu: Neutral::Variable(param.clone()),
                 },
-            );
-            check_type(
+            )?;
+            let ret_val = check(
                 defs,
                 &ctx.extend(param, param_type),
                 ret_val,
                 &Type::create_type_from_value(ret_type),
-            )
+            )?;
+            Ok(Lambda {
+                param: param.clone(),
+                param_type: annotation,
+                ret_val: Box::new(ret_val),
+                implicit: *implicit,
+            })
+        }
+        Pair { fst, snd } => {
+            let Value::SigmaType { fst_type, tclosure } = type_.deref() else {
+                return Err(TypeError::new(TypeErrorKind::PairAgainstNonSigma {
+                    pair: expr.clone(),
+                    type_: try_read_back_type(defs, ctx, type_)?,
+                }));
+            };
+            let fst = check(defs, ctx, fst, fst_type)?;
+            let fst_val = try_evaluate(defs, &Environment::from_context(ctx), &fst)?;
+            let snd_type = Type::create_type_from_value(tclosure.try_call(defs, &fst_val)?);
+            let snd = check(defs, ctx, snd, &snd_type)?;
+            Ok(Pair {
+                fst: Box::new(fst),
+                snd: Box::new(snd),
+            })
+        }
+        Record { fields } => {
+            let Value::RecordType { name, args } = type_.deref() else {
+                return Err(TypeError::new(TypeErrorKind::RecordAgainstNonRecord {
+                    record: expr.clone(),
+                    type_: try_read_back_type(defs, ctx, type_)?,
+                }));
+            };
+            let record = defs.record(name)?;
+            if fields.len() != record.fields.len() {
+                return Err(TypeError::new(TypeErrorKind::WrongFieldCount {
+                    record: expr.clone(),
+                    expected: record.fields.len(),
+                    found: fields.len(),
+                }));
+            }
+            let mut elaborated = Vec::new();
+            let mut field_vals = Vec::new();
+            for (index, field) in fields.iter().enumerate() {
+                let field_type = record.field_type(defs, index, args, &field_vals)?;
+                let field = check(defs, ctx, field, &field_type)?;
+                field_vals.push(try_evaluate(defs, &Environment::from_context(ctx), &field)?);
+                elaborated.push(field);
+            }
+            Ok(Record { fields: elaborated })
+        }
+        Let {
+            name,
+            type_: let_type,
+            value,
+            body,
+        } => {
+            let (let_type, value, let_type_val, value_val) = check_let(defs, ctx, let_type, value)?;
+            let body = check(
+                defs,
+                &ctx.define(name, &let_type_val, &value_val),
+                body,
+                type_,
+            )?;
+            Ok(Let {
+                name: name.clone(),
+                type_: Box::new(let_type),
+                value: Box::new(value),
+                body: Box::new(body),
+            })
+        }
+        Refl { elem } => {
+            let Value::IdType {
+                type_: elem_type, ..
+            } = type_.deref()
+            else {
+                return check_by_synthesis(defs, ctx, expr, type_);
+            };
+            let elem = check(defs, ctx, elem, elem_type)?;
+            let elem_val = try_evaluate(defs, &Environment::from_context(ctx), &elem)?;
+            let refl_type = Type::create_type_from_value(Value::IdType {
+                type_: elem_type.clone(),
+                lhs: Box::new(elem_val.clone()),
+                rhs: Box::new(elem_val),
+            });
+            check_subsumption(defs, ctx, expr, &refl_type, type_)?;
+            Ok(Refl {
+                elem: Box::new(elem),
+            })
         }
-        _ => {
-            let syn_type = synth_type(defs, ctx, expr)?;
-            judgmentally_equal(defs, ctx, &syn_type, type_, &Type::UNIVERSE)
+        Hole(meta) => {
+            record_goal(defs, ctx, *meta, type_, None)?;
+            Ok(expr.clone())
         }
+        Located { span, expr: inner } => match **inner {
+            Hole(meta) => {
+                record_goal(defs, ctx, meta, type_, Some(*span))?;
+                Ok(expr.clone())
+            }
+            _ => Ok(Located {
+                span: *span,
+                expr: Box::new(check(defs, ctx, inner, type_).map_err(|e| e.located(*span))?),
+            }),
+        },
+        _ => check_by_synthesis(defs, ctx, expr, type_),
     }
 }
 
-/// Synthesizes a [`Type`] for an [`Expression`].
-pub fn synth_type(defs: &Definitions, ctx: &Context, expr: &Expression) -> crate::Result<Type> {
+/// Checks the [`Type`] of an [`Expression`] by synthesizing a type and comparing it,
+/// after giving it the implicit arguments the synthesized type asks for.
+fn check_by_synthesis(
+    defs: &Definitions,
+    ctx: &Context,
+    expr: &Expression,
+    type_: &Type,
+) -> crate::Result<Expression> {
+    let (elaborated, syn_type) = infer(defs, ctx, expr)?;
+    let (elaborated, syn_type) = insert_implicits(defs, ctx, elaborated, syn_type)?;
+    check_subsumption(defs, ctx, expr, &syn_type, type_)?;
+    Ok(elaborated)
+}
+
+/// Applies `expr`, of type `type_`, to a fresh metavariable for each implicit parameter
+/// its type begins with, and returns the application with its type.
+fn insert_implicits(
+    defs: &Definitions,
+    ctx: &Context,
+    mut expr: Expression,
+    mut type_: Type,
+) -> crate::Result<(Expression, Type)> {
+    loop {
+        type_ = force_type(defs, &type_)?.into_owned();
+        let Value::PiType {
+            param_type,
+            tclosure,
+            implicit: true,
+        } = type_.deref()
+        else {
+            return Ok((expr, type_));
+        };
+        let arg = implicit_argument(defs, ctx, param_type, &tclosure.param, &expr)?;
+        let arg_val = try_evaluate(defs, &Environment::from_context(ctx), &arg)?;
+        type_ = Type::create_type_from_value(tclosure.try_call(defs, &arg_val)?);
+        expr = Expression::Application {
+            func: Box::new(expr),
+            arg: Box::new(arg),
+            implicit: true,
+        };
+    }
+}
+
+/// Checks that `found`, a type of `expr`, may be used as the `expected` type.
+fn check_subsumption(
+    defs: &Definitions,
+    ctx: &Context,
+    expr: &Expression,
+    found: &Type,
+    expected: &Type,
+) -> crate::Result<()> {
+    let Err(mut e) = subtype(defs, ctx, found, expected) else {
+        return Ok(());
+    };
+    // the types as a whole say more than the first parts of them which differ,
+    // but the error keeps where it was found
+    if let TypeErrorKind::NotAlphaEquivalent { .. } = *e.kind {
+        e.kind = Box::new(TypeErrorKind::TypeMismatch {
+            expected: try_read_back_type(defs, ctx, expected)?,
+            found: try_read_back_type(defs, ctx, found)?,
+            context: expr.clone(),
+        });
+    }
+    Err(e)
+}
+
+/// Synthesizes a [`Type`] for an [`Expression`], and elaborates it.
+fn infer(
+    defs: &Definitions,
+    ctx: &Context,
+    expr: &Expression,
+) -> crate::Result<(Expression, Type)> {
     use Expression::*;
+    let env = Environment::from_context(ctx);
     match expr {
-        Variable(id) => type_var(defs, ctx, id).cloned(),
+        Variable(id) => Ok((expr.clone(), type_var(defs, ctx, id, &[])?)),
+        Instance { name, levels } => Ok((expr.clone(), defs.instantiate_type(name, levels)?)),
         PiType {
             tparam,
             tparam_type,
             ret_type,
+            implicit,
         } => {
-            check_type(defs, ctx, tparam_type, &Type::UNIVERSE)?;
-            let tparam_type = evaluate(defs, &Environment::from_context(ctx), tparam_type);
-            check_type(
-                defs,
-                &ctx.extend(tparam, &Type::create_type_from_value(tparam_type)),
-                ret_type,
-                &Type::UNIVERSE,
-            )?;
-            Ok(Type::UNIVERSE)
+            let (tparam_type, ret_type, level) =
+                check_family(defs, ctx, tparam, tparam_type, ret_type)?;
+            let pi_type = PiType {
+                tparam: tparam.clone(),
+                tparam_type: Box::new(tparam_type),
+                ret_type: Box::new(ret_type),
+                implicit: *implicit,
+            };
+            Ok((pi_type, Type::universe(level)))
         }
         Lambda {
             param,
             param_type,
             ret_val,
+            implicit,
+        } => {
+            let param_type = match param_type {
+                Some(param_type) => infer_universe(defs, ctx, param_type)?.0,
+                // the uses of the parameter may decide its type
+                None => parameter_type(defs, ctx, expr)?,
+            };
+            let param_type_val =
+                Type::create_type_from_value(try_evaluate(defs, &env, &param_type)?);
+            let body_ctx = ctx.extend(param, &param_type_val);
+            let (ret_val, ret_type) = infer(defs, &body_ctx, ret_val)?;
+            let ret_type = try_read_back_type(defs, &body_ctx, &ret_type)?;
+            let lambda = Lambda {
+                param: param.clone(),
+                param_type: Some(Box::new(param_type)),
+                ret_val: Box::new(ret_val),
+                implicit: *implicit,
+            };
+            let pi_type = Value::PiType {
+                param_type: Box::new(param_type_val),
+                tclosure: Closure::new_in_ctx(ctx, param.clone(), ret_type),
+                implicit: *implicit,
+            };
+            Ok((lambda, Type::create_type_from_value(pi_type)))
+        }
+        Application {
+            func,
+            arg,
+            implicit,
+        } => {
+            let (func_expr, func_type) = infer(defs, ctx, func)?;
+            // an explicit argument is for the first explicit parameter
+            let (func_expr, func_type) = match implicit {
+                true => (func_expr, force_type(defs, &func_type)?.into_owned()),
+                false => insert_implicits(defs, ctx, func_expr, func_type)?,
+            };
+            // the type of the function may be left to its uses to decide
+            let func_type =
+                refine_meta_type(defs, ctx, func_type, |tparam_type, tparam, ret_type| {
+                    PiType {
+                        tparam,
+                        tparam_type: Box::new(tparam_type),
+                        ret_type: Box::new(ret_type),
+                        implicit: *implicit,
+                    }
+                })?;
+            match func_type.deref() {
+                Value::PiType {
+                    param_type,
+                    tclosure,
+                    implicit: pi_implicit,
+                } if pi_implicit == implicit => {
+                    let arg = check(defs, ctx, arg, param_type)?;
+                    let arg_val = try_evaluate(defs, &env, &arg)?;
+                    let application = Application {
+                        func: Box::new(func_expr),
+                        arg: Box::new(arg),
+                        implicit: *implicit,
+                    };
+                    let ret_type = tclosure.try_call(defs, &arg_val)?;
+                    Ok((application, Type::create_type_from_value(ret_type)))
+                }
+                Value::PiType { .. } => Err(TypeError::new(TypeErrorKind::NoImplicitParameter {
+                    func: *func.clone(),
+                    func_type: try_read_back_type(defs, ctx, &func_type)?,
+                })),
+                _ => Err(TypeError::new(TypeErrorKind::NotAFunction {
+                    func: *func.clone(),
+                    func_type: try_read_back_type(defs, ctx, &func_type)?,
+                })),
+            }
+        }
+        SigmaType {
+            tparam,
+            tparam_type,
+            snd_type,
         } => {
-            let Some(param_type) = param_type else {
-                return Err(TypeError{
-                    msg: format!("Cannot infer a type for lambda expression `{}` without parameter type given.", expr)
-                })
+            let (tparam_type, snd_type, level) =
+                check_family(defs, ctx, tparam, tparam_type, snd_type)?;
+            let sigma_type = SigmaType {
+                tparam: tparam.clone(),
+                tparam_type: Box::new(tparam_type),
+                snd_type: Box::new(snd_type),
+            };
+            Ok((sigma_type, Type::universe(level)))
+        }
+        Hole(_) => Err(TypeError::new(TypeErrorKind::HoleNeedsAnnotation)),
+        Meta(meta) => Ok((expr.clone(), defs.metas().get(*meta)?.type_.clone())),
+        Pair { .. } => Err(TypeError::new(TypeErrorKind::PairNeedsAnnotation {
+            pair: expr.clone(),
+        })),
+        Record { .. } => Err(TypeError::new(TypeErrorKind::RecordNeedsAnnotation {
+            record: expr.clone(),
+        })),
+        First { pair } => {
+            let (pair, fst_type, _) = synth_sigma_type(defs, ctx, pair)?;
+            Ok((
+                First {
+                    pair: Box::new(pair),
+                },
+                fst_type,
+            ))
+        }
+        Second { pair } => {
+            let (pair, _, tclosure) = synth_sigma_type(defs, ctx, pair)?;
+            let fst = try_first(&try_evaluate(defs, &env, &pair)?)?;
+            let snd_type = Type::create_type_from_value(tclosure.try_call(defs, &fst)?);
+            Ok((
+                Second {
+                    pair: Box::new(pair),
+                },
+                snd_type,
+            ))
+        }
+        IdType { type_, lhs, rhs } => {
+            let (type_, level) = infer_universe(defs, ctx, type_)?;
+            let type_val = Type::create_type_from_value(try_evaluate(defs, &env, &type_)?);
+            let lhs = check(defs, ctx, lhs, &type_val)?;
+            let rhs = check(defs, ctx, rhs, &type_val)?;
+            let id_type = IdType {
+                type_: Box::new(type_),
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+            Ok((id_type, Type::universe(level)))
+        }
+        Refl { elem } => {
+            let (elem, elem_type) = infer(defs, ctx, elem)?;
+            let elem_val = try_evaluate(defs, &env, &elem)?;
+            let refl_type = Type::create_type_from_value(Value::IdType {
+                type_: Box::new(elem_type),
+                lhs: Box::new(elem_val.clone()),
+                rhs: Box::new(elem_val),
+            });
+            Ok((
+                Refl {
+                    elem: Box::new(elem),
+                },
+                refl_type,
+            ))
+        }
+        J { motive, base, eq } => {
+            let (eq, eq_type) = infer(defs, ctx, eq)?;
+            let eq_type = force_type(defs, &eq_type)?.into_owned();
+            let Value::IdType { type_, lhs, rhs } = eq_type.deref() else {
+                return Err(TypeError::new(TypeErrorKind::NotAnEquality {
+                    expr: eq,
+                    expr_type: try_read_back_type(defs, ctx, &eq_type)?,
+                }));
             };
-            check_type(defs, ctx, param_type, &Type::UNIVERSE)?;
-            let param_type = Type::create_type_from_value(evaluate(
+            let (motive, motive_val) = check_motive(defs, ctx, motive, |level| {
+                j_motive_type(defs, ctx, type_, level)
+            })?;
+            let base = check(
                 defs,
-                &Environment::from_context(ctx),
-                param_type,
-            ));
-            let ret_type = synth_type(defs, &ctx.extend(param, &param_type), ret_val)?;
-            let ret_type = read_back_typed(defs, ctx, &ret_type, &Type::UNIVERSE);
-            Ok(Type::pi_type(ctx, param.clone(), param_type, ret_type))
-        }
-        Application { func, arg } => {
-            let func_type = synth_type(defs, ctx, func)?;
-            let Value::PiType{ param_type, tclosure } = func_type.deref() else {
-                return Err(TypeError{
-                    msg: format!(
-                        "Cannot call `{}` as a function, because it is of non-function type `{}`.",
-                        func, func_type
-                    )
-                })
-            };
-            check_type(defs, ctx, arg, param_type)?;
-            let arg = evaluate(defs, &Environment::from_context(ctx), arg);
-            Ok(Type::create_type_from_value(tclosure.call(defs, &arg)))
-        }
-        Universe => Ok(Type::UNIVERSE),
+                ctx,
+                base,
+                &j_base_type(defs, ctx, type_, &motive_val)?,
+            )?;
+            let eq_val = try_evaluate(defs, &env, &eq)?;
+            let ret_type = try_apply(defs, &motive_val, lhs)?;
+            let ret_type = try_apply(defs, &ret_type, rhs)?;
+            let ret_type = try_apply(defs, &ret_type, &eq_val)?;
+            let j = J {
+                motive: Box::new(motive),
+                base: Box::new(base),
+                eq: Box::new(eq),
+            };
+            Ok((j, Type::create_type_from_value(ret_type)))
+        }
+        Nat => Ok((expr.clone(), Type::universe(Level::ZERO))),
+        Numeral(_) => Ok((expr.clone(), Type::create_type_from_value(Value::Nat))),
+        Succ { pred } => {
+            let nat = Type::create_type_from_value(Value::Nat);
+            let pred = check(defs, ctx, pred, &nat)?;
+            Ok((
+                Succ {
+                    pred: Box::new(pred),
+                },
+                nat,
+            ))
+        }
+        NatRec {
+            motive,
+            base,
+            step,
+            target,
+        } => {
+            let (motive, motive_val) = check_motive(defs, ctx, motive, |level| {
+                motive_type(defs, ctx, Nat, level)
+            })?;
+            let base_type = try_apply(defs, &motive_val, &Value::Numeral(Natural::ZERO))?;
+            let base = check(defs, ctx, base, &Type::create_type_from_value(base_type))?;
+            let step = check(defs, ctx, step, &natrec_step_type(defs, ctx, &motive_val)?)?;
+            let target = check(defs, ctx, target, &Type::create_type_from_value(Value::Nat))?;
+            let target_val = try_evaluate(defs, &env, &target)?;
+            let ret_type = try_apply(defs, &motive_val, &target_val)?;
+            let natrec = NatRec {
+                motive: Box::new(motive),
+                base: Box::new(base),
+                step: Box::new(step),
+                target: Box::new(target),
+            };
+            Ok((natrec, Type::create_type_from_value(ret_type)))
+        }
+        Bool | Unit | Empty => Ok((expr.clone(), Type::universe(Level::ZERO))),
+        True | False => Ok((expr.clone(), Type::create_type_from_value(Value::Bool))),
+        If {
+            motive,
+            on_true,
+            on_false,
+            target,
+        } => {
+            let (motive, motive_val) = check_motive(defs, ctx, motive, |level| {
+                motive_type(defs, ctx, Bool, level)
+            })?;
+            let true_type = try_apply(defs, &motive_val, &Value::True)?;
+            let on_true = check(defs, ctx, on_true, &Type::create_type_from_value(true_type))?;
+            let false_type = try_apply(defs, &motive_val, &Value::False)?;
+            let on_false = check(
+                defs,
+                ctx,
+                on_false,
+                &Type::create_type_from_value(false_type),
+            )?;
+            let target = check(
+                defs,
+                ctx,
+                target,
+                &Type::create_type_from_value(Value::Bool),
+            )?;
+            let target_val = try_evaluate(defs, &env, &target)?;
+            let ret_type = try_apply(defs, &motive_val, &target_val)?;
+            let if_ = If {
+                motive: Box::new(motive),
+                on_true: Box::new(on_true),
+                on_false: Box::new(on_false),
+                target: Box::new(target),
+            };
+            Ok((if_, Type::create_type_from_value(ret_type)))
+        }
+        Star => Ok((expr.clone(), Type::create_type_from_value(Value::Unit))),
+        Absurd { motive, target } => {
+            let (motive, motive_val) = check_motive(defs, ctx, motive, |level| {
+                motive_type(defs, ctx, Empty, level)
+            })?;
+            let target = check(
+                defs,
+                ctx,
+                target,
+                &Type::create_type_from_value(Value::Empty),
+            )?;
+            let target_val = try_evaluate(defs, &env, &target)?;
+            let ret_type = try_apply(defs, &motive_val, &target_val)?;
+            let absurd = Absurd {
+                motive: Box::new(motive),
+                target: Box::new(target),
+            };
+            Ok((absurd, Type::create_type_from_value(ret_type)))
+        }
+        Universe(level) if defs.is_type_in_type() => {
+            Ok((expr.clone(), Type::universe(level.clone())))
+        }
+        Universe(level) => match level.successor() {
+            Some(successor) => Ok((expr.clone(), Type::universe(successor))),
+            None => Err(TypeError::new(TypeErrorKind::UniverseTooLarge(
+                level.clone(),
+            ))),
+        },
         Annotation { expr, type_ } => {
-            check_type(defs, ctx, type_, &Type::UNIVERSE)?;
-            let type_ = Type::create_type_from_value(evaluate(
+            let (type_, _) = infer_universe(defs, ctx, type_)?;
+            let type_val = Type::create_type_from_value(try_evaluate(defs, &env, &type_)?);
+            let expr = check(defs, ctx, expr, &type_val)?;
+            let annotation = Annotation {
+                expr: Box::new(expr),
+                type_: Box::new(type_),
+            };
+            Ok((annotation, type_val))
+        }
+        Let {
+            name,
+            type_,
+            value,
+            body,
+        } => {
+            let (type_, value, type_val, value_val) = check_let(defs, ctx, type_, value)?;
+            let (body, body_type) = infer(defs, &ctx.define(name, &type_val, &value_val), body)?;
+            let let_ = Let {
+                name: name.clone(),
+                type_: Box::new(type_),
+                value: Box::new(value),
+                body: Box::new(body),
+            };
+            Ok((let_, body_type))
+        }
+        Located { span, expr } => {
+            let (expr, type_) = infer(defs, ctx, expr).map_err(|e| e.located(*span))?;
+            let located = Located {
+                span: *span,
+                expr: Box::new(expr),
+            };
+            Ok((located, type_))
+        }
+    }
+}
+
+/// Checks that an [`Expression`] is a type, elaborates it,
+/// and returns the level of its universe.
+fn infer_universe(
+    defs: &Definitions,
+    ctx: &Context,
+    expr: &Expression,
+) -> crate::Result<(Expression, Level)> {
+    if let Expression::Located { span, expr } = expr {
+        let (expr, level) = infer_universe(defs, ctx, expr).map_err(|e| e.located(*span))?;
+        let located = Expression::Located {
+            span: *span,
+            expr: Box::new(expr),
+        };
+        return Ok((located, level));
+    }
+    let (elaborated, type_) = infer(defs, ctx, expr)?;
+    match force_type(defs, &type_)?.deref().deref() {
+        Value::Universe(level) => Ok((elaborated, level.clone())),
+        _ => Err(TypeError::new(TypeErrorKind::NotAType {
+            expr: expr.clone(),
+            expr_type: try_read_back_type(defs, ctx, &type_)?,
+        })),
+    }
+}
+
+/// Checks a type and a family of types over it, as found in pi and sigma types,
+/// elaborates them, and returns the greater level of their universes.
+fn check_family(
+    defs: &Definitions,
+    ctx: &Context,
+    tparam: &Identifier,
+    tparam_type: &Expression,
+    family: &Expression,
+) -> crate::Result<(Expression, Expression, Level)> {
+    let (tparam_type, tparam_level) = infer_universe(defs, ctx, tparam_type)?;
+    let tparam_type_val = try_evaluate(defs, &Environment::from_context(ctx), &tparam_type)?;
+    let (family, family_level) = infer_universe(
+        defs,
+        &ctx.extend(tparam, &Type::create_type_from_value(tparam_type_val)),
+        family,
+    )?;
+    Ok((tparam_type, family, tparam_level.max(&family_level)))
+}
+
+/// Checks an eliminator's `motive` against `motive_type` at the level of universe it is into,
+/// and elaborates and evaluates it.
+fn check_motive(
+    defs: &Definitions,
+    ctx: &Context,
+    motive: &Expression,
+    motive_type: impl Fn(Level) -> crate::Result<Type>,
+) -> crate::Result<(Expression, Value)> {
+    // a wrong guess of level is reported by checking against it
+    let level = motive_level(defs, ctx, motive, &motive_type(Level::ZERO)?).unwrap_or_default();
+    let motive = check(defs, ctx, motive, &motive_type(level)?)?;
+    let motive_val = try_evaluate(defs, &Environment::from_context(ctx), &motive)?;
+    Ok((motive, motive_val))
+}
+
+/// Finds the level of universe that `motive` is into, if it is a family of types of shape `shape`.
+fn motive_level(
+    defs: &Definitions,
+    ctx: &Context,
+    motive: &Expression,
+    shape: &Type,
+) -> Option<Level> {
+    match (motive, shape.deref()) {
+        (Expression::Located { expr, .. }, _) => motive_level(defs, ctx, expr, shape),
+        (
+            Expression::Lambda {
+                param,
+                param_type: None,
+                ret_val,
+                ..
+            },
+            Value::PiType {
+                param_type,
+                tclosure,
+                ..
+            },
+        ) => {
+            let var = Value::Neutral {
+                neu: Neutral::Variable(param.clone()),
+            };
+            let shape = Type::create_type_from_value(tclosure.try_call(defs, &var).ok()?);
+            motive_level(defs, &ctx.extend(param, param_type), ret_val, &shape)
+        }
+        (_, Value::Universe(_)) => check_is_type(defs, ctx, motive)
+            .ok()
+            .map(|(_, level)| level),
+        _ => codomain_level(defs, &synth_type(defs, ctx, motive).ok()?),
+    }
+}
+
+/// Finds the level of universe at the end of a chain of pi types, such as `∏(x : A)U 1`.
+fn codomain_level(defs: &Definitions, type_: &Type) -> Option<Level> {
+    match type_.deref() {
+        Value::PiType { tclosure, .. } => {
+            let var = Value::Neutral {
+                neu: Neutral::Variable(tclosure.param.clone()),
+            };
+            codomain_level(
                 defs,
-                &Environment::from_context(ctx),
-                type_,
-            ));
-            check_type(defs, ctx, expr, &type_)?;
-            Ok(type_)
+                &Type::create_type_from_value(tclosure.try_call(defs, &var).ok()?),
+            )
         }
+        Value::Universe(level) => Some(level.clone()),
+        _ => None,
     }
 }
+
+/// Synthesizes a sigma type for `pair`, elaborates it, and returns the components of its type.
+fn synth_sigma_type(
+    defs: &Definitions,
+    ctx: &Context,
+    pair: &Expression,
+) -> crate::Result<(Expression, Type, Closure)> {
+    let (elaborated, pair_type) = infer(defs, ctx, pair)?;
+    let pair_type = force_type(defs, &pair_type)?.into_owned();
+    let pair_type = refine_meta_type(defs, ctx, pair_type, |tparam_type, tparam, snd_type| {
+        Expression::SigmaType {
+            tparam,
+            tparam_type: Box::new(tparam_type),
+            snd_type: Box::new(snd_type),
+        }
+    })?;
+    match Value::from(pair_type) {
+        Value::SigmaType { fst_type, tclosure } => Ok((elaborated, *fst_type, tclosure)),
+        pair_type => Err(TypeError::new(TypeErrorKind::NotAPair {
+            expr: pair.clone(),
+            expr_type: try_read_back_type(defs, ctx, &Type::create_type_from_value(pair_type))?,
+        })),
+    }
+}
+
+/// Solves `type_`, a forced type, to a pi or sigma type made by `former`
+/// if it is an unsolved metavariable, so that the uses of a term of it may decide its parts,
+/// and gives it refined.
+fn refine_meta_type(
+    defs: &Definitions,
+    ctx: &Context,
+    type_: Type,
+    former: impl FnOnce(Expression, Identifier, Expression) -> Expression,
+) -> crate::Result<Type> {
+    let Value::Neutral {
+        neu: Neutral::Meta { meta, args },
+    } = type_.deref()
+    else {
+        return Ok(type_);
+    };
+    let refinement = refinement(defs, *meta, former)?;
+    let mut refined = try_evaluate(defs, &Environment::EMPTY, &refinement)?;
+    for arg in args {
+        refined = try_apply(defs, &refined, arg)?;
+    }
+    let refined = Type::create_type_from_value(refined);
+    unify_types(defs, ctx, &type_, &refined)?;
+    Ok(refined)
+}
+
+/// The type of motives for eliminating equalities in `type_`,
+/// namely `∏(x : A)∏(y : A)∏(p : Id(A)(x)(y))U i` for the universe at `level`.
+pub(crate) fn j_motive_type(
+    defs: &Definitions,
+    ctx: &Context,
+    type_: &Type,
+    level: Level,
+) -> crate::Result<Type> {
+    let type_ = try_read_back_type(defs, ctx, type_)?;
+    let x = fresh_in_context(defs, ctx, "x");
+    let y = fresh_in_context(defs, ctx, "y");
+    let p = fresh_in_context(defs, ctx, "p");
+    let motive_type = Expression::PiType {
+        tparam: x.clone(),
+        tparam_type: Box::new(type_.clone()),
+        ret_type: Box::new(Expression::PiType {
+            tparam: y.clone(),
+            tparam_type: Box::new(type_.clone()),
+            ret_type: Box::new(Expression::PiType {
+                tparam: p,
+                tparam_type: Box::new(Expression::IdType {
+                    type_: Box::new(type_),
+                    lhs: Box::new(Expression::Variable(x)),
+                    rhs: Box::new(Expression::Variable(y)),
+                }),
+                ret_type: Box::new(Expression::Universe(level)),
+                implicit: false,
+            }),
+            implicit: false,
+        }),
+        implicit: false,
+    };
+    try_evaluate(defs, &Environment::from_context(ctx), &motive_type)
+        .map(Type::create_type_from_value)
+}
+
+/// The type of base cases for eliminating equalities in `type_` with `motive`,
+/// namely `∏(x : A)C(x)(x)(refl(x))`.
+pub(crate) fn j_base_type(
+    defs: &Definitions,
+    ctx: &Context,
+    type_: &Type,
+    motive: &Value,
+) -> crate::Result<Type> {
+    let type_ = try_read_back_type(defs, ctx, type_)?;
+    let x = fresh_in_context(defs, ctx, "x");
+    let var = || Expression::Variable(x.clone());
+    type_over_motive(defs, ctx, motive, |c| Expression::PiType {
+        tparam: x.clone(),
+        tparam_type: Box::new(type_),
+        ret_type: Box::new(apply_all(
+            c,
+            [
+                var(),
+                var(),
+                Expression::Refl {
+                    elem: Box::new(var()),
+                },
+            ],
+        )),
+        implicit: false,
+    })
+}
+
+/// The type of motives for eliminating values of a closed type `target_type`,
+/// such as `∏(n : Nat)U i` for the universe at `level`.
+pub(crate) fn motive_type(
+    defs: &Definitions,
+    ctx: &Context,
+    target_type: Expression,
+    level: Level,
+) -> crate::Result<Type> {
+    let motive_type = Expression::PiType {
+        tparam: fresh_in_context(defs, ctx, "x"),
+        tparam_type: Box::new(target_type),
+        ret_type: Box::new(Expression::Universe(level)),
+        implicit: false,
+    };
+    try_evaluate(defs, &Environment::from_context(ctx), &motive_type)
+        .map(Type::create_type_from_value)
+}
+
+/// The type of step cases for eliminating natural numbers with `motive`,
+/// namely `∏(k : Nat)∏(ih : C(k))C(succ(k))`.
+pub(crate) fn natrec_step_type(
+    defs: &Definitions,
+    ctx: &Context,
+    motive: &Value,
+) -> crate::Result<Type> {
+    let k = fresh_in_context(defs, ctx, "k");
+    let ih = fresh_in_context(defs, ctx, "ih");
+    let var = || Expression::Variable(k.clone());
+    type_over_motive(defs, ctx, motive, |c| Expression::PiType {
+        tparam: k.clone(),
+        tparam_type: Box::new(Expression::Nat),
+        ret_type: Box::new(Expression::PiType {
+            tparam: ih,
+            tparam_type: Box::new(apply_all(c.clone(), [var()])),
+            ret_type: Box::new(apply_all(
+                c,
+                [Expression::Succ {
+                    pred: Box::new(var()),
+                }],
+            )),
+            implicit: false,
+        }),
+        implicit: false,
+    })
+}
+
+/// Evaluates the type built by `build` from a variable standing for `motive`.
+fn type_over_motive(
+    defs: &Definitions,
+    ctx: &Context,
+    motive: &Value,
+    build: impl FnOnce(Expression) -> Expression,
+) -> crate::Result<Type> {
+    let c = fresh_in_context(defs, ctx, "C");
+    let type_ = build(Expression::Variable(c.clone()));
+    let env = Environment::from_context(ctx);
+    try_evaluate(defs, &env.extend(&c, motive), &type_).map(Type::create_type_from_value)
+}
+
+/// Applies `func` to each of `args` in turn.
+pub(crate) fn apply_all(
+    func: Expression,
+    args: impl IntoIterator<Item = Expression>,
+) -> Expression {
+    args.into_iter()
+        .fold(func, |func, arg| Expression::Application {
+            func: Box::new(func),
+            arg: Box::new(arg),
+            implicit: false,
+        })
+}
+
+/// Checks the type and value of a `let`, and returns them elaborated and evaluated.
+fn check_let(
+    defs: &Definitions,
+    ctx: &Context,
+    type_: &Expression,
+    value: &Expression,
+) -> crate::Result<(Expression, Expression, Type, Value)> {
+    let (type_, _) = infer_universe(defs, ctx, type_)?;
+    let env = Environment::from_context(ctx);
+    let type_val = Type::create_type_from_value(try_evaluate(defs, &env, &type_)?);
+    let value = check(defs, ctx, value, &type_val)?;
+    let value_val = try_evaluate(defs, &env, &value)?;
+    Ok((type_, value, type_val, value_val))
+}
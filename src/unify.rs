@@ -0,0 +1,679 @@
+//! Higher-order pattern unification of [`Value`]s, solving metavariables.
+//!
+//! A metavariable applied to distinct variables, as in `?0 x y`, is solved by abstracting
+//! over them whatever it is equated with, provided that mentions neither the metavariable
+//! nor any other variable. Any other equation with a metavariable unsolved at its head
+//! is postponed, and tried again whenever a metavariable is solved,
+//! until no more can be solved. The metavariables and postponed constraints are kept
+//! in the [`MetaContext`](crate::meta::MetaContext) of the [`Definitions`].
+//!
+//! ```
+//! use alpha::environment::{Context, Definitions};
+//! use alpha::expression::Expression;
+//! use alpha::meta::fresh_meta;
+//! use alpha::parse::parse;
+//! use alpha::typing::synth_type;
+//! use alpha::unify::unify_types;
+//! use alpha::value::Type;
+//! use alpha::Identifier;
+//!
+//! let defs = Definitions::new();
+//! let ctx = Context::EMPTY;
+//! let nat = synth_type(&defs, &ctx, &parse("0").unwrap()).unwrap();
+//! let nat_to = |ret_type| Type::pi_type(&ctx, Identifier::new("n"), nat.clone(), ret_type);
+//!
+//! // `?F 0 = Nat` does not determine `?F`, so waits for `?F n = Nat`, which does
+//! let family = synth_type(&defs, &ctx, &parse(r"\(n : Nat) Nat").unwrap()).unwrap();
+//! let f = fresh_meta(&defs, &ctx, &family).unwrap();
+//! let apply_f = |arg| Expression::Application {
+//!     func: Box::new(f.clone()),
+//!     arg: Box::new(arg),
+//!     implicit: false,
+//! };
+//! let f_zero = nat_to(apply_f(parse("0").unwrap()));
+//! unify_types(&defs, &ctx, &f_zero, &nat_to(Expression::Nat)).unwrap();
+//! assert_eq!(defs.metas().postponed().count(), 1);
+//! let f_n = nat_to(apply_f(Expression::Variable(Identifier::new("n"))));
+//! unify_types(&defs, &ctx, &f_n, &nat_to(Expression::Nat)).unwrap();
+//! assert_eq!(defs.metas().postponed().count(), 0);
+//! let solution = defs.metas().iter().next().unwrap().1.solution.clone();
+//! assert_eq!(solution.unwrap().to_string(), "\u{03BB}n.Nat");
+//!
+//! // `?A = ∏(m : ?A)Nat` has no solution
+//! let a = fresh_meta(&defs, &ctx, &Type::universe(0)).unwrap();
+//! let a_to_nat = Expression::PiType {
+//!     tparam: Identifier::new("m"),
+//!     tparam_type: Box::new(a.clone()),
+//!     ret_type: Box::new(Expression::Nat),
+//!     implicit: false,
+//! };
+//! let e = unify_types(&defs, &ctx, &nat_to(a), &nat_to(a_to_nat)).unwrap_err();
+//! assert!(e.to_string().contains("because it would mention itself"));
+//! ```
+
+use crate::environment::{type_var, Context, Definitions};
+use crate::equivalence::{alpha_equivalent, judgmentally_equal, judgmentally_equal_types};
+use crate::evaluation::{try_apply, try_first, try_second};
+use crate::expression::Expression;
+use crate::identifier::fresh_identifier;
+use crate::inductive::lambdas;
+use crate::meta::{
+    force, force_type, move_to_universe, spine_type, Constraint, Mark, Meta, MetaVariable, Origin,
+};
+use crate::read_back::{try_read_back_neutral, try_read_back_type, try_read_back_typed};
+use crate::record::try_project;
+use crate::typing::{check_is_type, check_type, synth_type};
+use crate::value::{Closure, Neutral, Type, Value};
+use crate::{Identifier, TypeError, TypeErrorKind};
+use std::ops::Deref;
+
+/// The metavariable `val` is stuck on and its arguments, if `val` is one which unification may solve.
+fn flexible<'a>(
+    defs: &Definitions,
+    val: &'a Value,
+) -> crate::Result<Option<(MetaVariable, &'a [Value])>> {
+    let Value::Neutral {
+        neu: Neutral::Meta { meta, args },
+    } = val
+    else {
+        return Ok(None);
+    };
+    Ok(solvable(defs, *meta)?.then_some((*meta, args)))
+}
+
+/// Unifies the [`Value`]s `lhs` and `rhs` of type `type_`,
+/// solving metavariables so that they are judgmentally equal,
+/// or postponing the equations which do not yet determine a solution.
+///
+/// Elements of the unit type, and of record types, are equal by eta even when neither is known.
+///
+/// ```
+/// use alpha::environment::{Context, Definitions};
+/// use alpha::parse::parse;
+/// use alpha::record::RecordDeclaration;
+/// use alpha::typing::synth_type;
+/// use alpha::Identifier;
+///
+/// let mut defs = Definitions::new();
+/// let unit_eta = parse(r"(\x.\y. refl x : Pi(x : Unit) Pi(y : Unit) Id Unit x y)").unwrap();
+/// assert!(synth_type(&defs, &Context::EMPTY, &unit_eta).is_ok());
+///
+/// for (name, fields) in [("None", vec![]), ("Units", vec!["a", "b"])] {
+///     let record = RecordDeclaration {
+///         name: Identifier::new(name),
+///         params: vec![],
+///         fields: fields
+///             .into_iter()
+///             .map(|field| (Identifier::new(field), parse("Unit").unwrap()))
+///             .collect(),
+///     };
+///     assert!(defs.declare_record(&record).is_ok());
+///     let eta = format!(r"(\x.\y. refl x : Pi(x : {0}) Pi(y : {0}) Id {0} x y)", name);
+///     assert!(synth_type(&defs, &Context::EMPTY, &parse(&eta).unwrap()).is_ok());
+/// }
+/// ```
+pub fn unify(
+    defs: &Definitions,
+    ctx: &Context,
+    lhs: &Value,
+    rhs: &Value,
+    type_: &Type,
+) -> crate::Result<()> {
+    let type_ = force_type(defs, type_)?;
+    let type_: &Type = &type_;
+    let lhs = force(defs, lhs)?;
+    let rhs = force(defs, rhs)?;
+    if unify_flexible(defs, ctx, &lhs, &rhs, Some(type_))? {
+        return Ok(());
+    }
+    match type_.deref() {
+        // eta: functions are equal when they are equal on a fresh variable
+        Value::PiType {
+            param_type,
+            tclosure,
+            ..
+        } => {
+            let fresh_id = fresh_identifier(defs, ctx, tclosure);
+            let fresh_var = Value::Neutral {
+                neu: Neutral::Variable(fresh_id.clone()),
+            };
+            unify(
+                defs,
+                &ctx.extend(&fresh_id, param_type),
+                &try_apply(defs, &lhs, &fresh_var)?,
+                &try_apply(defs, &rhs, &fresh_var)?,
+                &Type::create_type_from_value(tclosure.try_call(defs, &fresh_var)?),
+            )
+        }
+        Value::SigmaType { fst_type, tclosure } => {
+            let fst = try_first(&lhs)?;
+            unify(defs, ctx, &fst, &try_first(&rhs)?, fst_type)?;
+            let snd_type = Type::create_type_from_value(tclosure.try_call(defs, &fst)?);
+            unify(defs, ctx, &try_second(&lhs)?, &try_second(&rhs)?, &snd_type)
+        }
+        Value::Universe(_) => unify_types(
+            defs,
+            ctx,
+            &Type::create_type_from_value(lhs.into_owned()),
+            &Type::create_type_from_value(rhs.into_owned()),
+        ),
+        // eta: every element of the unit type is the single one
+        Value::Unit => Ok(()),
+        // eta: elements of a record type are equal when their projections are
+        Value::RecordType { name, args } => {
+            let record = defs.record(name)?;
+            let mut fields = Vec::new();
+            for (index, field) in record.fields.iter().enumerate() {
+                let lhs_field = try_project(defs, name, field, args, &lhs)?;
+                let rhs_field = try_project(defs, name, field, args, &rhs)?;
+                let field_type = record.field_type(defs, index, args, &fields)?;
+                unify(defs, ctx, &lhs_field, &rhs_field, &field_type)?;
+                fields.push(lhs_field);
+            }
+            Ok(())
+        }
+        _ => match (lhs.as_ref(), rhs.as_ref()) {
+            (Value::Neutral { neu: lhs_neu }, Value::Neutral { neu: rhs_neu }) => {
+                unify_neutral(defs, ctx, lhs_neu, rhs_neu).map(|_| ())
+            }
+            (Value::Succ { pred: lhs_pred }, Value::Succ { pred: rhs_pred }) => {
+                unify(defs, ctx, lhs_pred, rhs_pred, type_)
+            }
+            (Value::Succ { pred }, Value::Numeral(n))
+            | (Value::Numeral(n), Value::Succ { pred }) => match n.predecessor() {
+                Some(n) => unify(defs, ctx, pred, &Value::Numeral(n), type_),
+                None => judgmentally_equal(defs, ctx, &lhs, &rhs, type_),
+            },
+            (Value::Refl { elem: lhs_elem }, Value::Refl { elem: rhs_elem }) => {
+                let Value::IdType { type_, .. } = type_.deref() else {
+                    return judgmentally_equal(defs, ctx, &lhs, &rhs, type_);
+                };
+                unify(defs, ctx, lhs_elem, rhs_elem, type_)
+            }
+            (
+                Value::Constructor {
+                    name: lhs_name,
+                    args: lhs_args,
+                },
+                Value::Constructor {
+                    name: rhs_name,
+                    args: rhs_args,
+                },
+            ) if lhs_name == rhs_name => {
+                let head_type = defs.instantiate_type(lhs_name, &[])?;
+                unify_spine(defs, ctx, head_type, lhs_args, rhs_args).map(|_| ())
+            }
+            _ => judgmentally_equal(defs, ctx, &lhs, &rhs, type_),
+        },
+    }
+}
+
+/// Unifies the [`Type`]s `lhs` and `rhs`, solving metavariables so that they are
+/// judgmentally equal, or postponing the equations which do not yet determine a solution.
+pub fn unify_types(defs: &Definitions, ctx: &Context, lhs: &Type, rhs: &Type) -> crate::Result<()> {
+    let lhs = force_type(defs, lhs)?;
+    let rhs = force_type(defs, rhs)?;
+    if unify_flexible(defs, ctx, &lhs, &rhs, None)? {
+        return Ok(());
+    }
+    match (&**lhs, &**rhs) {
+        (
+            Value::PiType {
+                param_type: lhs_param_type,
+                tclosure: lhs_tclosure,
+                implicit: lhs_implicit,
+            },
+            Value::PiType {
+                param_type: rhs_param_type,
+                tclosure: rhs_tclosure,
+                implicit: rhs_implicit,
+            },
+        ) if lhs_implicit == rhs_implicit => {
+            unify_types(defs, ctx, lhs_param_type, rhs_param_type)?;
+            unify_families(defs, ctx, rhs_param_type, lhs_tclosure, rhs_tclosure)
+        }
+        (
+            Value::SigmaType {
+                fst_type: lhs_fst_type,
+                tclosure: lhs_tclosure,
+            },
+            Value::SigmaType {
+                fst_type: rhs_fst_type,
+                tclosure: rhs_tclosure,
+            },
+        ) => {
+            unify_types(defs, ctx, lhs_fst_type, rhs_fst_type)?;
+            unify_families(defs, ctx, rhs_fst_type, lhs_tclosure, rhs_tclosure)
+        }
+        (
+            Value::IdType {
+                type_: lhs_type,
+                lhs: lhs_lhs,
+                rhs: lhs_rhs,
+            },
+            Value::IdType {
+                type_: rhs_type,
+                lhs: rhs_lhs,
+                rhs: rhs_rhs,
+            },
+        ) => {
+            unify_types(defs, ctx, lhs_type, rhs_type)?;
+            unify(defs, ctx, lhs_lhs, rhs_lhs, lhs_type)?;
+            unify(defs, ctx, lhs_rhs, rhs_rhs, lhs_type)
+        }
+        (
+            Value::Data {
+                name: lhs_name,
+                args: lhs_args,
+            },
+            Value::Data {
+                name: rhs_name,
+                args: rhs_args,
+            },
+        )
+        | (
+            Value::RecordType {
+                name: lhs_name,
+                args: lhs_args,
+            },
+            Value::RecordType {
+                name: rhs_name,
+                args: rhs_args,
+            },
+        ) if lhs_name == rhs_name => {
+            let head_type = defs.instantiate_type(lhs_name, &[])?;
+            unify_spine(defs, ctx, head_type, lhs_args, rhs_args).map(|_| ())
+        }
+        (Value::Neutral { neu: lhs_neu }, Value::Neutral { neu: rhs_neu }) => {
+            unify_neutral(defs, ctx, lhs_neu, rhs_neu).map(|_| ())
+        }
+        _ => judgmentally_equal_types(defs, ctx, &lhs, &rhs),
+    }
+}
+
+/// Unifies the families of types of two pi or sigma types, over a type `param_type`.
+fn unify_families(
+    defs: &Definitions,
+    ctx: &Context,
+    param_type: &Type,
+    lhs: &Closure,
+    rhs: &Closure,
+) -> crate::Result<()> {
+    let fresh_id = fresh_identifier(defs, ctx, rhs);
+    let fresh_var = Value::Neutral {
+        neu: Neutral::Variable(fresh_id.clone()),
+    };
+    unify_types(
+        defs,
+        &ctx.extend(&fresh_id, param_type),
+        &Type::create_type_from_value(lhs.try_call(defs, &fresh_var)?),
+        &Type::create_type_from_value(rhs.try_call(defs, &fresh_var)?),
+    )
+}
+
+/// Unifies the arguments of two applications of a head of type `head_type`,
+/// and gives the type of the applications.
+fn unify_spine(
+    defs: &Definitions,
+    ctx: &Context,
+    mut head_type: Type,
+    lhs_args: &[Value],
+    rhs_args: &[Value],
+) -> crate::Result<Type> {
+    for (lhs, rhs) in lhs_args.iter().zip(rhs_args) {
+        let Value::PiType {
+            param_type,
+            tclosure,
+            ..
+        } = force_type(defs, &head_type)?.into_owned().into()
+        else {
+            return Err(TypeError::new(TypeErrorKind::Internal(format!(
+                "Cannot unify `{}` with `{}` because it is ill-typed.",
+                lhs, rhs
+            ))));
+        };
+        unify(defs, ctx, lhs, rhs, &param_type)?;
+        head_type = Type::create_type_from_value(tclosure.try_call(defs, lhs)?);
+    }
+    if lhs_args.len() != rhs_args.len() {
+        return Err(TypeError::new(TypeErrorKind::Internal(format!(
+            "Cannot unify applications to {} and {} arguments.",
+            lhs_args.len(),
+            rhs_args.len()
+        ))));
+    }
+    Ok(head_type)
+}
+
+/// Unifies neutral values, and gives their type.
+fn unify_neutral(
+    defs: &Definitions,
+    ctx: &Context,
+    lhs: &Neutral,
+    rhs: &Neutral,
+) -> crate::Result<Type> {
+    match (lhs, rhs) {
+        (Neutral::Variable(lhs_id), Neutral::Variable(rhs_id)) if lhs_id == rhs_id => {
+            type_var(defs, ctx, lhs_id, &[])
+        }
+        (
+            Neutral::Application {
+                func: lhs_func,
+                arg: lhs_arg,
+            },
+            Neutral::Application {
+                func: rhs_func,
+                arg: rhs_arg,
+            },
+        ) => {
+            let func_type = unify_neutral(defs, ctx, lhs_func, rhs_func)?;
+            unify_spine(
+                defs,
+                ctx,
+                func_type,
+                std::slice::from_ref(lhs_arg),
+                std::slice::from_ref(rhs_arg),
+            )
+        }
+        (
+            Neutral::Function {
+                name: lhs_name,
+                args: lhs_args,
+            },
+            Neutral::Function {
+                name: rhs_name,
+                args: rhs_args,
+            },
+        ) if lhs_name == rhs_name && lhs_args.len() == rhs_args.len() => {
+            let head_type = defs.instantiate_type(lhs_name, &[])?;
+            unify_spine(defs, ctx, head_type, lhs_args, rhs_args)
+        }
+        (
+            Neutral::Meta {
+                meta: lhs_meta,
+                args: lhs_args,
+            },
+            Neutral::Meta {
+                meta: rhs_meta,
+                args: rhs_args,
+            },
+        ) if lhs_meta == rhs_meta && lhs_args.len() == rhs_args.len() => {
+            let head_type = defs.metas().get(*lhs_meta)?.type_.clone();
+            unify_spine(defs, ctx, head_type, lhs_args, rhs_args)
+        }
+        _ => {
+            let lhs = try_read_back_neutral(defs, ctx, lhs)?;
+            alpha_equivalent(&lhs, &try_read_back_neutral(defs, ctx, rhs)?)?;
+            synth_type(defs, ctx, &lhs)
+        }
+    }
+}
+
+/// Solves whichever of `lhs` and `rhs` is an unsolved metavariable applied to distinct variables
+/// to be the other, where `type_` is their type, or `None` for types,
+/// or postpones the equation if it is stuck on a metavariable which may yet be solved.
+///
+/// Is `false` if neither is a metavariable to be solved.
+fn unify_flexible(
+    defs: &Definitions,
+    ctx: &Context,
+    lhs: &Value,
+    rhs: &Value,
+    type_: Option<&Type>,
+) -> crate::Result<bool> {
+    let (meta, args, other) = match (flexible(defs, lhs)?, flexible(defs, rhs)?) {
+        (Some((lhs_meta, lhs_args)), Some((rhs_meta, rhs_args))) if lhs_meta == rhs_meta => {
+            let Value::Neutral { neu: lhs_neu } = lhs else {
+                unreachable!("a flexible value is neutral")
+            };
+            let Value::Neutral { neu: rhs_neu } = rhs else {
+                unreachable!("a flexible value is neutral")
+            };
+            if lhs_args.len() == rhs_args.len() {
+                unify_neutral(defs, ctx, lhs_neu, rhs_neu)?;
+                return Ok(true);
+            }
+            equal(defs, ctx, lhs, rhs, type_)?;
+            return Ok(true);
+        }
+        (Some((meta, args)), _) => (meta, args, rhs),
+        (None, Some((meta, args))) => (meta, args, lhs),
+        (None, None) => {
+            // an equation on a hole waits for the hole to be filled, rather than failing
+            if (on_hole(defs, lhs)? || on_hole(defs, rhs)?)
+                && equal(defs, ctx, lhs, rhs, type_).is_err()
+            {
+                postpone(defs, ctx, lhs, rhs, type_);
+                return Ok(true);
+            }
+            return Ok(false);
+        }
+    };
+    // the arguments must be distinct variables, for the solution to be unique
+    let mut vars: Vec<Identifier> = Vec::new();
+    for arg in args {
+        match force(defs, arg)?.as_ref() {
+            Value::Neutral {
+                neu: Neutral::Variable(var),
+            } if !vars.contains(var) => vars.push(var.clone()),
+            _ => {
+                postpone(defs, ctx, lhs, rhs, type_);
+                return Ok(true);
+            }
+        }
+    }
+    let solution = match type_ {
+        Some(type_) => try_read_back_typed(defs, ctx, other, type_)?,
+        None => try_read_back_type(defs, ctx, &Type::create_type_from_value(other.clone()))?,
+    };
+    // the solution may mention neither the metavariable itself,
+    // nor any variable it is not applied to
+    let occurs = solution.metas().contains(&meta);
+    let out_of_scope = ctx
+        .names()
+        .find(|name| !vars.contains(name) && solution.has_free_variable(name));
+    if occurs || out_of_scope.is_some() {
+        // solving another metavariable may yet remove the offending occurrence
+        for other in solution.metas() {
+            if other != meta && solvable(defs, other)? {
+                postpone(defs, ctx, lhs, rhs, type_);
+                return Ok(true);
+            }
+        }
+        if equal(defs, ctx, lhs, rhs, type_).is_ok() {
+            return Ok(true);
+        }
+        let kind = match out_of_scope {
+            _ if occurs => TypeErrorKind::OccursCheck { meta, solution },
+            Some(variable) => TypeErrorKind::ScopeCheck {
+                meta,
+                variable: variable.clone(),
+                solution,
+            },
+            None => unreachable!("the solution is rejected for a reason"),
+        };
+        return Err(TypeError::new(kind));
+    }
+    if type_.is_none() {
+        if defs.metas().get(meta)?.origin.infers_universe() {
+            // a type which was not given is in whichever universe its solution is
+            let (_, level) = check_is_type(defs, ctx, &solution)?;
+            move_to_universe(defs, meta, level)?;
+        } else {
+            // a type may be too large for the universe the metavariable is in
+            let expected = spine_type(defs, meta, args)?;
+            check_type(defs, ctx, &solution, &expected)?;
+        }
+    }
+    defs.metas_mut().solve(meta, lambdas(vars.iter(), solution));
+    retry_postponed(defs)?;
+    Ok(true)
+}
+
+/// Whether `meta` is unsolved and for unification to solve, rather than a hole for the user to fill.
+fn solvable(defs: &Definitions, meta: MetaVariable) -> crate::Result<bool> {
+    let metas = defs.metas();
+    let entry = metas.get(meta)?;
+    Ok(entry.solution.is_none() && !matches!(entry.origin, Origin::Hole(_)))
+}
+
+/// Whether `val` is a hole applied to arguments, which the user rather than unification solves.
+fn on_hole(defs: &Definitions, val: &Value) -> crate::Result<bool> {
+    let Value::Neutral {
+        neu: Neutral::Meta { meta, .. },
+    } = val
+    else {
+        return Ok(false);
+    };
+    let metas = defs.metas();
+    let entry = metas.get(*meta)?;
+    Ok(entry.solution.is_none() && matches!(entry.origin, Origin::Hole(_)))
+}
+
+/// Checks that `lhs` and `rhs` are judgmentally equal, where `type_` is their type,
+/// or `None` for types.
+fn equal(
+    defs: &Definitions,
+    ctx: &Context,
+    lhs: &Value,
+    rhs: &Value,
+    type_: Option<&Type>,
+) -> crate::Result<()> {
+    match type_ {
+        Some(type_) => judgmentally_equal(defs, ctx, lhs, rhs, type_),
+        None => judgmentally_equal_types(
+            defs,
+            ctx,
+            &Type::create_type_from_value(lhs.clone()),
+            &Type::create_type_from_value(rhs.clone()),
+        ),
+    }
+}
+
+/// Records the equation of `lhs` and `rhs` in `ctx` to be tried again later.
+fn postpone(defs: &Definitions, ctx: &Context, lhs: &Value, rhs: &Value, type_: Option<&Type>) {
+    let mut context: Vec<(Identifier, Type, Option<Value>)> = ctx
+        .into_iter()
+        .zip(ctx.values())
+        .map(|((name, type_), (_, value))| (name.clone(), type_.clone(), value.cloned()))
+        .collect();
+    context.reverse();
+    defs.metas_mut().postpone(Constraint {
+        id: 0,
+        context,
+        lhs: lhs.clone(),
+        rhs: rhs.clone(),
+        type_: type_.cloned(),
+    });
+}
+
+/// Tries each postponed constraint again, now that a metavariable has been solved.
+///
+/// Each further solution tries those still postponed again in turn, until none can be solved.
+fn retry_postponed(defs: &Definitions) -> crate::Result<()> {
+    let mut constraints = defs.metas_mut().take_postponed().into_iter();
+    while let Some(constraint) = constraints.next() {
+        // a constraint postponed again is still the one it was
+        let outer = defs.metas_mut().retry_as(Some(constraint.id));
+        let result = in_context(
+            &Context::EMPTY,
+            &constraint.context,
+            &mut |ctx| match &constraint.type_ {
+                Some(type_) => unify(defs, ctx, &constraint.lhs, &constraint.rhs, type_),
+                None => unify_types(
+                    defs,
+                    ctx,
+                    &Type::create_type_from_value(constraint.lhs.clone()),
+                    &Type::create_type_from_value(constraint.rhs.clone()),
+                ),
+            },
+        );
+        defs.metas_mut().retry_as(outer);
+        if let Err(e) = result {
+            for constraint in constraints {
+                defs.metas_mut().restore(constraint);
+            }
+            // the sides differ now that they can be computed further
+            return match *e.kind {
+                TypeErrorKind::NotAlphaEquivalent { .. } => {
+                    let (lhs, rhs) = read_back_constraint(defs, &constraint)?;
+                    Err(TypeError::new(TypeErrorKind::ConstraintFailed { lhs, rhs }))
+                }
+                _ => Err(e),
+            };
+        }
+    }
+    Ok(())
+}
+
+/// Calls `body` in `ctx` extended by each of `bindings` in turn.
+fn in_context<R>(
+    ctx: &Context,
+    bindings: &[(Identifier, Type, Option<Value>)],
+    body: &mut dyn FnMut(&Context) -> crate::Result<R>,
+) -> crate::Result<R> {
+    let Some(((name, type_, value), rest)) = bindings.split_first() else {
+        return body(ctx);
+    };
+    match value {
+        None => in_context(&ctx.extend(name, type_), rest, body),
+        Some(value) => in_context(&ctx.define(name, type_, value), rest, body),
+    }
+}
+
+/// Fails for the first implicit argument or parameter type, or part of a parameter type,
+/// created since `mark` which was not solved, or else for the first constraint postponed
+/// since `mark` which is still unsolved, unless it waits on a hole.
+pub(crate) fn check_solved(defs: &Definitions, mark: Mark) -> crate::Result<()> {
+    let metas: Vec<Meta> = {
+        let metas = defs.metas();
+        metas
+            .iter()
+            .skip(mark.metas)
+            .map(|(_, entry)| entry.clone())
+            .collect()
+    };
+    for entry in metas {
+        let kind = match (entry.origin, entry.solution) {
+            (Origin::Implicit { binder, func }, None) => {
+                TypeErrorKind::CannotInferImplicit { binder, func }
+            }
+            (Origin::Parameter { lambda }, None) => TypeErrorKind::LambdaNeedsAnnotation { lambda },
+            _ => continue,
+        };
+        return Err(TypeError::new(kind));
+    }
+    let constraints: Vec<Constraint> = defs
+        .metas()
+        .postponed()
+        .filter(|constraint| constraint.id >= mark.constraints)
+        .cloned()
+        .collect();
+    for constraint in constraints {
+        if on_hole(defs, force(defs, &constraint.lhs)?.as_ref())?
+            || on_hole(defs, force(defs, &constraint.rhs)?.as_ref())?
+        {
+            continue;
+        }
+        let (lhs, rhs) = read_back_constraint(defs, &constraint)?;
+        return Err(TypeError::new(TypeErrorKind::UnsolvedConstraint {
+            lhs,
+            rhs,
+        }));
+    }
+    Ok(())
+}
+
+/// Reads back both sides of `constraint`, as far as they can now be computed.
+fn read_back_constraint(
+    defs: &Definitions,
+    constraint: &Constraint,
+) -> crate::Result<(Expression, Expression)> {
+    in_context(&Context::EMPTY, &constraint.context, &mut |ctx| {
+        let read_back = |val: &Value| match &constraint.type_ {
+            Some(type_) => try_read_back_typed(defs, ctx, val, type_),
+            None => try_read_back_type(defs, ctx, &Type::create_type_from_value(val.clone())),
+        };
+        Ok((read_back(&constraint.lhs)?, read_back(&constraint.rhs)?))
+    })
+}
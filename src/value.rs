@@ -1,9 +1,12 @@
 //! Types representing computed values.
 
 pub use crate::dictionaries::Closure;
-use crate::expression::Expression;
+use crate::expression::{Expression, Levels};
+use crate::inductive::eliminator_name;
 use crate::lists::Context;
-use crate::Identifier;
+use crate::meta::MetaVariable;
+use crate::record::projection_name;
+use crate::{Identifier, Level, Natural};
 use std::fmt;
 use std::ops::Deref;
 
@@ -12,14 +15,83 @@ use std::ops::Deref;
 /// A [`Value`] is not a beta-redex at the top level.
 #[derive(Clone)]
 pub enum Value {
+    /// A pi type, whose parameter is `implicit` if its arguments are left to be inferred.
     PiType {
         param_type: Box<Type>,
         tclosure: Closure,
+        implicit: bool,
     },
     Lambda {
         closure: Closure,
     },
-    Universe,
+    SigmaType {
+        fst_type: Box<Type>,
+        tclosure: Closure,
+    },
+    Pair {
+        fst: Box<Value>,
+        snd: Box<Value>,
+    },
+    IdType {
+        type_: Box<Type>,
+        lhs: Box<Value>,
+        rhs: Box<Value>,
+    },
+    Refl {
+        elem: Box<Value>,
+    },
+    Nat,
+    Numeral(Natural),
+    /// The successor of a value which is not a numeral.
+    Succ {
+        pred: Box<Value>,
+    },
+    Bool,
+    True,
+    False,
+    Unit,
+    Star,
+    Empty,
+    Universe(Level),
+    /// A declared inductive family applied to `args`.
+    Data {
+        name: Identifier,
+        args: Vec<Value>,
+    },
+    /// A constructor of a declared inductive family applied to `args`.
+    Constructor {
+        name: Identifier,
+        args: Vec<Value>,
+    },
+    /// The eliminator of a declared inductive family,
+    /// applied to fewer arguments than it needs to compute.
+    Eliminator {
+        family: Identifier,
+        levels: Vec<Level>,
+        args: Vec<Value>,
+    },
+    /// A declared record type applied to `args`.
+    RecordType {
+        name: Identifier,
+        args: Vec<Value>,
+    },
+    /// An element of a record type, given by its fields in order.
+    Record {
+        fields: Vec<Value>,
+    },
+    /// The projection of `field` from the record type `record`,
+    /// applied to fewer arguments than it needs to compute.
+    Projection {
+        record: Identifier,
+        field: Identifier,
+        args: Vec<Value>,
+    },
+    /// A function defined by pattern matching,
+    /// applied to fewer arguments than it matches on.
+    Function {
+        name: Identifier,
+        args: Vec<Value>,
+    },
     Neutral {
         neu: Neutral,
     },
@@ -32,13 +104,77 @@ impl fmt::Display for Value {
             PiType {
                 param_type,
                 tclosure,
+                implicit: false,
             } => write!(
                 f,
                 "\u{220F}({} : {}){}",
                 tclosure.param, param_type, tclosure.body
             ),
+            PiType {
+                param_type,
+                tclosure,
+                implicit: true,
+            } => write!(
+                f,
+                "\u{220F}{{{} : {}}}{}",
+                tclosure.param, param_type, tclosure.body
+            ),
             Lambda { closure } => write!(f, "\u{03BB}({}){}", closure.param, closure.body),
-            Universe => write!(f, "U"),
+            SigmaType { fst_type, tclosure } => write!(
+                f,
+                "\u{03A3}({} : {}){}",
+                tclosure.param, fst_type, tclosure.body
+            ),
+            Pair { fst, snd } => write!(f, "({}, {})", fst, snd),
+            IdType { type_, lhs, rhs } => write!(f, "Id({})({})({})", type_, lhs, rhs),
+            Refl { elem } => write!(f, "refl({})", elem),
+            Nat => write!(f, "Nat"),
+            Numeral(n) => n.fmt(f),
+            Succ { pred } => write!(f, "succ({})", pred),
+            Bool => write!(f, "Bool"),
+            True => write!(f, "true"),
+            False => write!(f, "false"),
+            Unit => write!(f, "Unit"),
+            Star => write!(f, "tt"),
+            Empty => write!(f, "Empty"),
+            Universe(level) => match level.as_constant() {
+                Some(0) => write!(f, "U"),
+                Some(n) => write!(f, "U {}", n),
+                None => write!(f, "U.{{{}}}", level),
+            },
+            Data { name, args }
+            | Constructor { name, args }
+            | RecordType { name, args }
+            | Function { name, args } => {
+                name.fmt(f)?;
+                Arguments(args).fmt(f)
+            }
+            Eliminator {
+                family,
+                levels,
+                args,
+            } => {
+                write!(f, "{}.{{{}}}", eliminator_name(family), Levels(levels))?;
+                Arguments(args).fmt(f)
+            }
+            Record { fields } => {
+                write!(f, "{{")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    field.fmt(f)?;
+                }
+                write!(f, "}}")
+            }
+            Projection {
+                record,
+                field,
+                args,
+            } => {
+                projection_name(record, field).fmt(f)?;
+                Arguments(args).fmt(f)
+            }
             Neutral { neu } => write!(f, "{}", neu),
         }
     }
@@ -51,7 +187,70 @@ impl fmt::Display for Value {
 #[derive(Clone)]
 pub enum Neutral {
     Variable(Identifier),
-    Application { func: Box<Neutral>, arg: Box<Value> },
+    /// A postulate instantiated at the universe levels `levels`,
+    /// kept apart from the variables so that no binder of the same name captures it.
+    Instance {
+        name: Identifier,
+        levels: Vec<Level>,
+    },
+    Application {
+        func: Box<Neutral>,
+        arg: Box<Value>,
+    },
+    First {
+        pair: Box<Neutral>,
+    },
+    Second {
+        pair: Box<Neutral>,
+    },
+    J {
+        motive: Box<Value>,
+        base: Box<Value>,
+        eq: Box<Neutral>,
+    },
+    NatRec {
+        motive: Box<Value>,
+        base: Box<Value>,
+        step: Box<Value>,
+        target: Box<Neutral>,
+    },
+    If {
+        motive: Box<Value>,
+        on_true: Box<Value>,
+        on_false: Box<Value>,
+        target: Box<Neutral>,
+    },
+    Absurd {
+        motive: Box<Value>,
+        target: Box<Neutral>,
+    },
+    /// The eliminator of a declared inductive family,
+    /// applied to every argument but the `target` it eliminates.
+    Eliminator {
+        family: Identifier,
+        levels: Vec<Level>,
+        args: Vec<Value>,
+        target: Box<Neutral>,
+    },
+    /// The projection of `field` from the record type `record`,
+    /// applied to the parameters `args` and the `target` it projects from.
+    Projection {
+        record: Identifier,
+        field: Identifier,
+        args: Vec<Value>,
+        target: Box<Neutral>,
+    },
+    /// A function defined by pattern matching, applied to every argument it matches on,
+    /// where a value it must match to decide on a clause is neutral.
+    Function {
+        name: Identifier,
+        args: Vec<Value>,
+    },
+    /// A metavariable applied to the values of the variables in scope where it occurs.
+    Meta {
+        meta: MetaVariable,
+        args: Vec<Value>,
+    },
 }
 
 impl fmt::Display for Neutral {
@@ -59,11 +258,68 @@ impl fmt::Display for Neutral {
         use Neutral::*;
         match self {
             Variable(id) => id.fmt(f),
+            Instance { name, levels } if levels.is_empty() => name.fmt(f),
+            Instance { name, levels } => write!(f, "{}.{{{}}}", name, Levels(levels)),
             Application { func, arg } => write!(f, "({})({})", func, arg),
+            First { pair } => write!(f, "fst({})", pair),
+            Second { pair } => write!(f, "snd({})", pair),
+            J { motive, base, eq } => write!(f, "J({})({})({})", motive, base, eq),
+            NatRec {
+                motive,
+                base,
+                step,
+                target,
+            } => write!(f, "natrec({})({})({})({})", motive, base, step, target),
+            If {
+                motive,
+                on_true,
+                on_false,
+                target,
+            } => write!(f, "if({})({})({})({})", motive, on_true, on_false, target),
+            Absurd { motive, target } => write!(f, "absurd({})({})", motive, target),
+            Eliminator {
+                family,
+                levels,
+                args,
+                target,
+            } => write!(
+                f,
+                "{}.{{{}}}{}({})",
+                eliminator_name(family),
+                Levels(levels),
+                Arguments(args),
+                target
+            ),
+            Projection {
+                record,
+                field,
+                args,
+                target,
+            } => write!(
+                f,
+                "{}{}({})",
+                projection_name(record, field),
+                Arguments(args),
+                target
+            ),
+            Function { name, args } => write!(f, "{}{}", name, Arguments(args)),
+            Meta { meta, args } => write!(f, "{}{}", meta, Arguments(args)),
         }
     }
 }
 
+/// Displays values each in parentheses, as arguments.
+struct Arguments<'a>(&'a [Value]);
+
+impl fmt::Display for Arguments<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for arg in self.0 {
+            write!(f, "({})", arg)?;
+        }
+        Ok(())
+    }
+}
+
 /// A [`Value`] which denotes a type.
 #[derive(Clone)]
 pub struct Type(/*pub(crate)*/ Value);
@@ -103,11 +359,14 @@ impl Type {
         Type(Value::PiType {
             param_type: Box::new(param_type),
             tclosure: Closure::new_in_ctx(ctx, param.clone(), ret_type),
+            implicit: false,
         })
     }
 
-    /// The universe type.
-    pub const UNIVERSE: Type = Type(Value::Universe);
+    /// The universe type at `level`.
+    pub fn universe(level: impl Into<Level>) -> Type {
+        Type(Value::Universe(level.into()))
+    }
 }
 
 pub(crate) struct TypedValue {